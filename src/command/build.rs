@@ -1,31 +1,122 @@
-use std::sync::Arc;
+use std::{future::Future, sync::Arc};
+
+use tokio::join;
 
 use crate::{
     compile,
     compile::ChangeSet,
     config::{Config, Project},
     ext::{
-        anyhow::{Context, Result},
+        anyhow::{bail, Context, Result},
         fs,
     },
+    logger::{self, Event},
+    signal::{Interrupt, Outcome},
+    Opts,
 };
+use clap::Args;
+
+#[derive(Clone, Debug, Args, PartialEq, Default)]
+pub struct BuildCommand {
+    #[command(flatten)]
+    pub opts: Opts,
+
+    /// Only build the front: the wasm, the style and the assets.
+    #[arg(long, conflicts_with = "server_only")]
+    pub front_only: bool,
+
+    /// Only build the server.
+    #[arg(long)]
+    pub server_only: bool,
+}
 
-pub async fn build_all(conf: &Config) -> Result<()> {
+pub async fn build_all(conf: &Config, cmd: &BuildCommand) -> Result<()> {
     for proj in &conf.projects {
-        build_proj(proj).await?;
+        if cmd.server_only {
+            if proj.bin.is_none() {
+                bail!("The csr-only project {} has no server to build", proj.name);
+            }
+            build_parts(proj, false, true).await?;
+            continue;
+        }
+        build_parts(proj, true, !cmd.front_only).await?;
+        if proj.release && proj.precompress {
+            compile::precompress(proj).await?;
+        }
+        if !conf.cli.no_manifest {
+            compile::manifest(proj).await?;
+        }
     }
     Ok(())
 }
 
 pub async fn build_proj(proj: &Arc<Project>) -> Result<()> {
-    if proj.site.root_dir.exists() {
-        fs::rm_dir_content(&proj.site.root_dir).await.dot()?;
+    build_parts(proj, true, true).await
+}
+
+/// builds the site and/or the server
+async fn build_parts(proj: &Arc<Project>, site: bool, server: bool) -> Result<()> {
+    logger::emit(Event::BuildStarted);
+    if super::hook::pre_build(proj).await? == Outcome::Stopped {
+        bail!("The pre-build command was interrupted");
+    }
+    if site && proj.site.root_dir.exists() {
+        // the assets resync removes the stale files itself, so that the unchanged assets are kept
+        let dir = match proj.assets.is_some() && !proj.force_resync {
+            true => proj.site.root_dir.join(&proj.site.pkg_dir),
+            false => proj.site.root_dir.clone(),
+        };
+        fs::rm_dir_content(&dir).await.dot()?;
     }
     let changes = ChangeSet::all_changes();
 
-    compile::front(proj, &changes).await.await??;
-    compile::assets(proj, &changes, true).await.await??;
-    compile::style(proj, &changes).await.await??;
-    compile::server(proj, &changes).await.await??;
+    if site && server && proj.parallel {
+        // the front and the server use separate target dirs (target/front and target/server),
+        // so the two cargo builds don't wait for each other's build directory lock
+        let server = async { compile::server(proj, &changes).await.await? };
+        let (site, server) = join!(
+            stop_others_on_err(build_site(proj, &changes)),
+            stop_others_on_err(server)
+        );
+        site?;
+        server?;
+    } else {
+        if site {
+            build_site(proj, &changes).await?;
+        }
+        if server {
+            compile::server(proj, &changes).await.await??;
+        }
+    }
+    super::hook::post_build(proj).await
+}
+
+/// both sides of a concurrent build are awaited, so that no cargo process outlives the build.
+/// When one side fails, the other is stopped instead of finishing its build.
+async fn stop_others_on_err<T>(build: impl Future<Output = Result<T>>) -> Result<T> {
+    let res = build.await;
+    if res.is_err() {
+        Interrupt::request_stop();
+    }
+    res
+}
+
+/// builds everything but the server
+async fn build_site(proj: &Arc<Project>, changes: &ChangeSet) -> Result<()> {
+    compile::assets(proj, changes, true).await.await??;
+    if proj.parallel {
+        // the wasm and js, and the css, are distinct files of the pkg dir and their
+        // intermediates distinct files of the tmp dir
+        let front = async { compile::front(proj, changes).await.await? };
+        let style = async { compile::style(proj, changes).await.await? };
+        let (front, style) = join!(stop_others_on_err(front), stop_others_on_err(style));
+        front?;
+        style?;
+    } else {
+        compile::front(proj, changes).await.await??;
+        compile::style(proj, changes).await.await??;
+    }
+    compile::hash_files(proj).await?;
+    compile::index(proj).await?;
     Ok(())
 }
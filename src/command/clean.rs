@@ -0,0 +1,53 @@
+use camino::Utf8PathBuf;
+use clap::Args;
+
+use crate::{
+    config::Config,
+    ext::anyhow::{Context, Result},
+    ext::fs,
+    logger::GRAY,
+    Opts,
+};
+
+#[derive(Clone, Debug, Args, PartialEq, Default)]
+pub struct CleanCommand {
+    #[command(flatten)]
+    pub opts: Opts,
+
+    /// Only remove the site root, keeping the compilation caches of the front and the server.
+    #[arg(long)]
+    pub site_only: bool,
+}
+
+/// Removes the dirs written by cargo-leptos, leaving the rest of the cargo target dir untouched.
+pub async fn clean_all(conf: &Config, clean: &CleanCommand) -> Result<()> {
+    let mut dirs: Vec<Utf8PathBuf> = Vec::new();
+    for proj in &conf.projects {
+        dirs.push(proj.site.root_dir.clone());
+        if !clean.site_only {
+            dirs.push(proj.lib.target_dir.clone());
+            if let Some(bin) = &proj.bin {
+                dirs.push(bin.target_dir.clone());
+            }
+            dirs.push(proj.tmp_dir.clone());
+            if let Some(assets) = &proj.assets {
+                dirs.push(assets.cache_dir.clone());
+            }
+        }
+    }
+    dirs.sort();
+    dirs.dedup();
+
+    let mut removed = 0;
+    for dir in dirs.iter().filter(|dir| dir.exists()) {
+        fs::remove_dir_all(dir)
+            .await
+            .context(format!("Could not remove {dir:?}"))?;
+        log::info!("Clean removed {}", GRAY.paint(dir.as_str()));
+        removed += 1;
+    }
+    if removed == 0 {
+        log::info!("Clean found nothing to remove");
+    }
+    Ok(())
+}
@@ -0,0 +1,62 @@
+use camino::Utf8Path;
+
+use crate::compile::check_wasm_target;
+use crate::config::Config;
+use crate::ext::anyhow::{bail, Result};
+use crate::ext::exe::{self, Exe};
+use crate::Opts;
+
+struct Check {
+    name: &'static str,
+    result: Result<()>,
+    /// how to fix a failure
+    hint: &'static str,
+}
+
+/// Checks the environment needed for building, logs a pass/fail checklist
+/// and fails if any check failed.
+pub async fn doctor(opts: &Opts, cwd: &Utf8Path, manifest_path: &Utf8Path) -> Result<()> {
+    let checks = [
+        Check {
+            name: "wasm target installed",
+            result: check_wasm_target().await,
+            hint: "install it with: rustup target add wasm32-unknown-unknown",
+        },
+        Check {
+            name: "cache dir writable",
+            result: exe::check_cache_dir(),
+            hint: "make the cache dir writable, it is where the downloaded executables are kept",
+        },
+        Check {
+            name: "dart-sass available",
+            result: Exe::Sass.get().await.map(drop),
+            hint: "check your internet connection or put sass on the PATH",
+        },
+        Check {
+            name: "wasm-opt available",
+            result: Exe::WasmOpt.get().await.map(drop),
+            hint: "check your internet connection or put wasm-opt on the PATH",
+        },
+        Check {
+            name: "leptos metadata valid",
+            result: Config::load(opts.clone(), cwd, manifest_path, false).map(drop),
+            hint: "see the parameters reference in the cargo-leptos README",
+        },
+    ];
+
+    let mut failed = Vec::new();
+    for check in &checks {
+        match &check.result {
+            Ok(()) => log::info!("Doctor passed {}", check.name),
+            Err(e) => {
+                log::error!("Doctor failed {}: {e:#}", check.name);
+                log::error!("Doctor hint: {}", check.hint);
+                failed.push(check.name);
+            }
+        }
+    }
+    if !failed.is_empty() {
+        bail!("Doctor checks failed: {}", failed.join(", "));
+    }
+    Ok(())
+}
@@ -1,12 +1,19 @@
-use std::sync::Arc;
+use std::collections::VecDeque;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use camino::Utf8Path;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::net::TcpStream;
 use tokio::process::Command;
+use tokio::task::JoinHandle;
 
-use crate::config::{Config, Project};
-use crate::ext::anyhow::{anyhow, Context, Result};
+use crate::config::{Config, End2EndConfig, Project};
+use crate::ext::anyhow::{anyhow, bail, Context, Result};
 use crate::service::serve;
-use crate::signal::Interrupt;
+use crate::signal::{Interrupt, Outcome, Product};
 
 pub async fn end2end_all(conf: &Config) -> Result<()> {
     for proj in &conf.projects {
@@ -19,18 +26,82 @@ pub async fn end2end_proj(proj: &Arc<Project>) -> Result<()> {
     if let Some(e2e) = &proj.end2end {
         super::build::build_proj(proj).await.dot()?;
         let server = serve::spawn(proj).await;
-        try_run(&e2e.cmd, &e2e.dir)
-            .await
-            .context(format!("running: {}", &e2e.cmd))?;
+        let res = match wait_until_ready(proj.site.addr, e2e).await {
+            Ok(()) => try_run(&e2e.cmd, &e2e.dir, &[], "e2e")
+                .await
+                .context(format!("End2end command failed: {}", &e2e.cmd)),
+            Err(e) => Err(e),
+        };
         Interrupt::request_shutdown().await;
         server.await.dot()??;
+        res?;
     } else {
         log::info!("end2end the Crate.toml package.metadata.leptos.end2end_cmd parameter not set")
     }
     Ok(())
 }
 
-async fn try_run(cmd: &str, dir: &Utf8Path) -> Result<()> {
+/// the interval between the probes of the server
+const READY_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// waits until the server accepts connections or, with a ready path, answers it with a success
+async fn wait_until_ready(addr: SocketAddr, e2e: &End2EndConfig) -> Result<()> {
+    // a server listening on all the interfaces is reached on localhost
+    let addr = match addr.ip().is_unspecified() {
+        true => SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), addr.port()),
+        false => addr,
+    };
+    let probed = match &e2e.ready_path {
+        Some(path) => format!("http://{addr}/{}", path.trim_start_matches('/')),
+        None => addr.to_string(),
+    };
+    log::debug!("End2end waiting for the server at {probed}");
+
+    let start = Instant::now();
+    loop {
+        let ready = match &e2e.ready_path {
+            Some(_) => reqwest::get(&probed)
+                .await
+                .is_ok_and(|resp| resp.status().is_success()),
+            None => TcpStream::connect(addr).await.is_ok(),
+        };
+        if ready {
+            log::debug!("End2end server ready after {:?}", start.elapsed());
+            return Ok(());
+        }
+        if start.elapsed() >= e2e.ready_timeout {
+            bail!(
+                "End2end server not ready at {probed} after {:?}. Raise the end2end-ready-timeout if it needs more time to start.",
+                e2e.ready_timeout
+            );
+        }
+        tokio::time::sleep(READY_POLL_INTERVAL).await;
+    }
+}
+
+/// the number of output lines included in the error when the command fails
+const TAIL_LINES: usize = 20;
+
+/// runs the command, streaming its output prefixed with the label, and fails if it doesn't succeed
+pub(super) async fn try_run(
+    cmd: &str,
+    dir: &Utf8Path,
+    envs: &[(&'static str, String)],
+    label: &'static str,
+) -> Result<()> {
+    match run(cmd, dir, envs, label).await? {
+        Outcome::Stopped => bail!("Interrupted"),
+        _ => Ok(()),
+    }
+}
+
+/// like try_run, but the command killed by an interrupt is Stopped instead of failed
+pub(super) async fn run(
+    cmd: &str,
+    dir: &Utf8Path,
+    envs: &[(&'static str, String)],
+    label: &'static str,
+) -> Result<Outcome> {
     let mut parts = cmd.split(' ');
     let exe = parts
         .next()
@@ -38,17 +109,67 @@ async fn try_run(cmd: &str, dir: &Utf8Path) -> Result<()> {
 
     let args = parts.collect::<Vec<_>>();
 
-    log::trace!("End2End running {cmd:?}");
+    log::trace!("Run {label} {cmd:?}");
     let mut process = Command::new(exe)
         .args(args)
+        .envs(envs.iter().cloned())
         .current_dir(dir)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
         .spawn()
         .context(format!("Could not spawn command {cmd:?}"))?;
 
+    let tail = Arc::new(Mutex::new(VecDeque::with_capacity(TAIL_LINES)));
+    let stdout = stream(process.stdout.take(), &tail, label);
+    let stderr = stream(process.stderr.take(), &tail, label);
+
     let mut int = Interrupt::subscribe_any();
-    tokio::select! {
-      _ = int.recv() => {},
-      _ = process.wait() => {}
+    let status = tokio::select! {
+      _ = int.recv() => {
+          process.kill().await.context("Could not kill process")?;
+          return Ok(Outcome::Stopped)
+      },
+      status = process.wait() => status.dot()?
+    };
+    stdout.await.dot()?;
+    stderr.await.dot()?;
+
+    if status.success() {
+        return Ok(Outcome::Success(Product::None));
     }
-    Ok(())
+    let tail = tail.lock().unwrap().iter().cloned().collect::<Vec<_>>();
+    let code = status
+        .code()
+        .map(|c| c.to_string())
+        .unwrap_or_else(|| "none".to_string());
+    bail!(
+        "Exited with code {code}. Last lines of output:\n{}",
+        tail.join("\n")
+    )
+}
+
+/// logs each line of the output and keeps the last ones in the tail
+fn stream<R>(
+    output: Option<R>,
+    tail: &Arc<Mutex<VecDeque<String>>>,
+    label: &'static str,
+) -> JoinHandle<()>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+{
+    let tail = tail.clone();
+    tokio::spawn(async move {
+        let Some(output) = output else {
+            return;
+        };
+        let mut lines = BufReader::new(output).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            log::info!("[{label}] {line}");
+            let mut tail = tail.lock().unwrap();
+            if tail.len() == TAIL_LINES {
+                tail.pop_front();
+            }
+            tail.push_back(line);
+        }
+    })
 }
@@ -0,0 +1,28 @@
+use crate::config::{Hook, Project};
+use crate::ext::anyhow::{bail, Context, Result};
+use crate::signal::{Outcome, Product};
+
+use super::end2end::run;
+
+/// Stopped when interrupted, by a change in watch mode
+pub async fn pre_build(proj: &Project) -> Result<Outcome> {
+    run_hook(proj, &proj.pre_build, "pre-build").await
+}
+
+pub async fn post_build(proj: &Project) -> Result<()> {
+    match run_hook(proj, &proj.post_build, "post-build").await? {
+        Outcome::Stopped => bail!("The post-build command was interrupted"),
+        _ => Ok(()),
+    }
+}
+
+/// runs the hook with the same env vars as the cargo builds
+async fn run_hook(proj: &Project, hook: &Option<Hook>, label: &'static str) -> Result<Outcome> {
+    let Some(hook) = hook else {
+        return Ok(Outcome::Success(Product::None));
+    };
+    log::info!("Hook running {label} {:?}", hook.cmd);
+    run(&hook.cmd, &hook.dir, &proj.to_envs(), label)
+        .await
+        .context(format!("The {label} command failed: {}", hook.cmd))
+}
@@ -1,13 +1,20 @@
 mod build;
+mod clean;
+mod doctor;
 mod end2end;
+mod hook;
 mod new;
 mod serve;
 mod test;
+mod upgrade;
 pub mod watch;
 
-pub use build::build_all;
+pub use build::{build_all, BuildCommand};
+pub use clean::{clean_all, CleanCommand};
+pub use doctor::doctor;
 pub use end2end::end2end_all;
 pub use new::NewCommand;
-pub use serve::serve;
-pub use test::test_all;
-pub use watch::watch;
+pub use serve::{serve, ServeCommand};
+pub use test::{test_all, watch_tests, TestCommand};
+pub use upgrade::clean_on_upgrade;
+pub use watch::{watch, WatchCommand};
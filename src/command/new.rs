@@ -1,9 +1,11 @@
-use crate::ext::anyhow::{Context, Result};
-use clap::Args;
+use crate::ext::anyhow::{bail, Context, Result};
+use camino::Utf8PathBuf;
+use clap::{Args, ValueEnum};
 
 use tokio::process::Command;
 
 use crate::ext::exe::Exe;
+use crate::fs;
 
 // A subset of the cargo-generate commands available.
 // See: https://github.com/cargo-generate/cargo-generate/blob/main/src/args.rs
@@ -47,10 +49,74 @@ pub struct NewCommand {
     /// Generate the template directly into the current dir. No subfolder will be created and no vcs is initialized.
     #[clap(long, action)]
     pub init: bool,
+
+    /// Expand a template embedded in cargo-leptos, without network access, instead of a cargo-generate template.
+    #[clap(long, value_enum, conflicts_with_all = ["git", "path", "branch", "tag"])]
+    pub template: Option<Template>,
+
+    /// Expand the embedded template (axum unless --template is given), without network access.
+    #[clap(long, action, conflicts_with_all = ["git", "path", "branch", "tag"])]
+    pub offline: bool,
+}
+
+/// The templates embedded in cargo-leptos
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Template {
+    /// Server using axum
+    Axum,
+    /// Server using actix-web
+    Actix,
+}
+
+impl Template {
+    /// the (path, content) of the files, with the {{project-name}} and {{crate_name}} placeholders
+    fn files(&self) -> Vec<(&'static str, &'static str)> {
+        let mut files = vec![
+            (".gitignore", "/target\n"),
+            (
+                "src/app.rs",
+                include_str!("../../templates/common/src/app.rs"),
+            ),
+            (
+                "src/lib.rs",
+                include_str!("../../templates/common/src/lib.rs"),
+            ),
+            (
+                "style/main.scss",
+                include_str!("../../templates/common/style/main.scss"),
+            ),
+        ];
+        match self {
+            Self::Axum => files.extend([
+                (
+                    "Cargo.toml",
+                    include_str!("../../templates/axum/Cargo.toml"),
+                ),
+                (
+                    "src/main.rs",
+                    include_str!("../../templates/axum/src/main.rs"),
+                ),
+            ]),
+            Self::Actix => files.extend([
+                (
+                    "Cargo.toml",
+                    include_str!("../../templates/actix/Cargo.toml"),
+                ),
+                (
+                    "src/main.rs",
+                    include_str!("../../templates/actix/src/main.rs"),
+                ),
+            ]),
+        }
+        files
+    }
 }
 
 impl NewCommand {
     pub async fn run(&self) -> Result<()> {
+        if self.offline || self.template.is_some() {
+            return self.expand(self.template.unwrap_or(Template::Axum)).await;
+        }
         let args = self.to_args();
         let exe = Exe::CargoGenerate.get().await.dot()?;
 
@@ -63,6 +129,48 @@ impl NewCommand {
         Ok(())
     }
 
+    /// writes the embedded template to the project dir
+    async fn expand(&self, template: Template) -> Result<()> {
+        let Some(name) = &self.name else {
+            bail!("The project --name is required with an embedded template");
+        };
+        let name = if self.force {
+            name.clone()
+        } else {
+            kebab_case(name)
+        };
+        let crate_name = name.replace('-', "_");
+        let dir = if self.init {
+            Utf8PathBuf::from(".")
+        } else {
+            Utf8PathBuf::from(&name)
+        };
+
+        let files = template.files();
+        if !self.init && dir.exists() {
+            bail!("The directory {dir:?} already exists");
+        }
+        if let Some((path, _)) = files.iter().find(|(path, _)| dir.join(path).exists()) {
+            bail!("The file {:?} already exists", dir.join(path));
+        }
+
+        for (path, content) in files {
+            let file = dir.join(path);
+            let content = content
+                .replace("{{project-name}}", &name)
+                .replace("{{crate_name}}", &crate_name);
+            if let Some(parent) = file.parent() {
+                fs::create_dir_all(parent).await?;
+            }
+            fs::write(&file, content).await?;
+            if self.verbose {
+                log::info!("New wrote {file}");
+            }
+        }
+        log::info!("New created the project {name} from the embedded {template:?} template");
+        Ok(())
+    }
+
     pub fn to_args(&self) -> Vec<String> {
         let mut args = vec![];
         opt_push(&mut args, "git", &absolute_git_url(&self.git));
@@ -77,6 +185,25 @@ impl NewCommand {
     }
 }
 
+/// the name in kebab-case, as cargo-generate does: my_App -> my-app
+fn kebab_case(name: &str) -> String {
+    let mut kebab = String::new();
+    let mut prev_lower = false;
+    for c in name.chars() {
+        if c == '_' || c == ' ' || c == '-' {
+            kebab.push('-');
+            prev_lower = false;
+            continue;
+        }
+        if c.is_uppercase() && prev_lower {
+            kebab.push('-');
+        }
+        prev_lower = c.is_lowercase() || c.is_ascii_digit();
+        kebab.extend(c.to_lowercase());
+    }
+    kebab
+}
+
 fn bool_push(args: &mut Vec<String>, name: &str, set: bool) {
     if set {
         args.push(format!("--{name}"))
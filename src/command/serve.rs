@@ -1,12 +1,49 @@
 use std::sync::Arc;
 
 use crate::config::Project;
-use crate::ext::anyhow::{Context, Result};
+use crate::ext::anyhow::{bail, Context, Result};
 use crate::service::serve;
+use crate::Opts;
+use clap::Args;
 
-pub async fn serve(proj: &Arc<Project>) -> Result<()> {
-    super::build::build_proj(proj).await.dot()?;
+#[derive(Clone, Debug, Args, PartialEq, Default)]
+pub struct ServeCommand {
+    #[command(flatten)]
+    pub opts: Opts,
+
+    /// Serve the already built site with the already built server, without building them.
+    #[arg(long)]
+    pub no_build: bool,
+}
+
+pub async fn serve(proj: &Arc<Project>, cmd: &ServeCommand) -> Result<()> {
+    if cmd.no_build {
+        check_built(proj)?;
+    } else {
+        super::build::build_proj(proj).await.dot()?;
+    }
     let server = serve::spawn(proj).await;
     server.await??;
     Ok(())
 }
+
+/// the server executable and the site root are needed for serving without building
+fn check_built(proj: &Project) -> Result<()> {
+    let build = match proj.release {
+        true => "cargo leptos build --release",
+        false => "cargo leptos build",
+    };
+    if let Some(bin) = proj.bin.as_ref().filter(|bin| !bin.exe_file.exists()) {
+        bail!(
+            "The server executable {:?} was not found. Build it first with: {build}",
+            bin.exe_file.as_str()
+        );
+    }
+    if !proj.site.root_dir.exists() {
+        bail!(
+            "The site root {:?} was not found. Build it first with: {build}",
+            proj.site.root_dir.as_str()
+        );
+    }
+    Ok(())
+}
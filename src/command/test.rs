@@ -1,26 +1,278 @@
-use crate::compile::{front_cargo_process, server_cargo_process};
-use crate::config::{Config, Project};
-use crate::ext::anyhow::{Context, Result};
+use camino::Utf8PathBuf;
+use clap::Args;
+use tokio::process::Command;
+
+use crate::command::end2end::end2end_proj;
+use crate::compile::{build_cargo_front_cmd, build_cargo_server_cmd, ChangeSet};
+use crate::config::{BinPackage, Config, Project, TestRunner};
+use crate::ext::anyhow::{bail, Context, Result};
+use crate::ext::{exe::Exe, fs};
 use crate::logger::GRAY;
+use crate::service;
+use crate::signal::Interrupt;
+use crate::Opts;
+use std::sync::Arc;
+
+#[derive(Clone, Debug, Args, PartialEq, Default)]
+pub struct TestCommand {
+    /// Only run the tests whose name contains the filter.
+    // declared before the flattened opts, so that it comes before their trailing cargo args
+    pub filter: Option<String>,
+
+    #[command(flatten)]
+    pub opts: Opts,
+
+    /// Also run the end-to-end tests. Failures of all test suites are reported at the end.
+    #[arg(long)]
+    pub all: bool,
+
+    /// Instrument the server and front tests for code coverage, and generate
+    /// lcov and html reports in target/coverage using grcov.
+    #[arg(long)]
+    pub coverage: bool,
+}
 
-pub async fn test_all(conf: &Config) -> Result<()> {
+struct SuiteResult {
+    name: String,
+    passed: bool,
+}
+
+pub async fn test_all(conf: &Config, cmd: &TestCommand) -> Result<()> {
+    let mut results = Vec::new();
     for proj in &conf.projects {
-        test_proj(proj).await?;
+        results.extend(test_proj(proj, cmd).await?);
+    }
+
+    let failed = report(&results);
+    if !failed.is_empty() {
+        bail!("Test suites failed: {}", failed.join(", "));
     }
     Ok(())
 }
 
-pub async fn test_proj(proj: &Project) -> Result<()> {
-    let (envs, line, mut proc) = server_cargo_process("test", proj).dot()?;
+/// Re-runs the tests of the changed side (server and/or front) on each source change,
+/// without building the site or starting the server.
+pub async fn watch_tests(proj: &Arc<Project>, cmd: &TestCommand) -> Result<()> {
+    let _watch = service::notify::spawn(proj).await?;
+
+    let mut changes = ChangeSet::all_changes();
+    let mut int = Interrupt::subscribe_any();
+    loop {
+        let mut results = Vec::new();
+        match &proj.bin {
+            Some(bin) if changes.need_server_build() => {
+                results.push(test_server(proj, bin, cmd, None).await?)
+            }
+            _ => {}
+        }
+        if changes.need_front_build() {
+            results.push(test_front(proj, cmd, None).await?);
+        }
+        if !results.is_empty() {
+            report(&results);
+        }
+        Interrupt::clear_source_changes().await;
+
+        log::debug!("Test waiting for changes");
+        int.recv().await.dot()?;
+        if Interrupt::is_shutdown_requested().await {
+            log::debug!("Shutting down");
+            return Ok(());
+        }
+        changes = Interrupt::get_source_changes().await;
+    }
+}
+
+/// logs the results and returns the names of the failed suites
+fn report(results: &[SuiteResult]) -> Vec<&str> {
+    for result in results {
+        if result.passed {
+            log::info!("Test passed {}", result.name);
+        } else {
+            log::error!("Test failed {}", result.name);
+        }
+    }
+
+    results
+        .iter()
+        .filter(|r| !r.passed)
+        .map(|r| r.name.as_str())
+        .collect()
+}
+
+async fn test_proj(proj: &Arc<Project>, cmd: &TestCommand) -> Result<Vec<SuiteResult>> {
+    let mut results = Vec::new();
+    let coverage = if cmd.coverage {
+        Some(Coverage::start(proj).await?)
+    } else {
+        None
+    };
+
+    if let Some(bin) = &proj.bin {
+        results.push(test_server(proj, bin, cmd, coverage.as_ref()).await?);
+    }
+    results.push(test_front(proj, cmd, coverage.as_ref()).await?);
+
+    if let Some(coverage) = &coverage {
+        coverage.report().await.context("Coverage report")?;
+    }
 
-    proc.wait().await.dot()?;
+    if cmd.all {
+        if proj.end2end.is_some() {
+            let passed = match end2end_proj(proj).await {
+                Ok(()) => true,
+                Err(e) => {
+                    log::error!("Test end2end {e:#}");
+                    false
+                }
+            };
+            results.push(SuiteResult {
+                name: format!("{} end2end", proj.name),
+                passed,
+            });
+        } else {
+            log::info!("Test no end2end tests configured for {}", proj.name);
+        }
+    }
+    Ok(results)
+}
+
+async fn test_server(
+    proj: &Project,
+    bin: &BinPackage,
+    cmd: &TestCommand,
+    coverage: Option<&Coverage>,
+) -> Result<SuiteResult> {
+    let mut command = Command::new("cargo");
+    let runner = bin.test_runner;
+    ensure_runner(runner)?;
+    let (envs, line) = build_cargo_server_cmd(runner.cargo_cmd(), proj, &mut command);
+    if let Some(filter) = &cmd.filter {
+        command.arg(filter);
+    }
+    if let Some(coverage) = coverage {
+        command.envs(coverage.envs("server"));
+    }
+
+    let status = command.spawn()?.wait().await.dot()?;
     log::debug!("Cargo envs: {}", GRAY.paint(envs));
     log::info!("Cargo server tests finished {}", GRAY.paint(line));
+    Ok(SuiteResult {
+        name: format!("{} server", proj.name),
+        passed: status.success(),
+    })
+}
 
-    let (envs, line, mut proc) = front_cargo_process("test", false, proj).dot()?;
+async fn test_front(
+    proj: &Project,
+    cmd: &TestCommand,
+    coverage: Option<&Coverage>,
+) -> Result<SuiteResult> {
+    let mut command = Command::new("cargo");
+    let runner = proj.lib.test_runner;
+    ensure_runner(runner)?;
+    let (envs, line) = build_cargo_front_cmd(runner.cargo_cmd(), false, proj, &mut command);
+    if let Some(filter) = &cmd.filter {
+        command.arg(filter);
+    }
+    if let Some(coverage) = coverage {
+        command.envs(coverage.envs("front"));
+    }
 
-    proc.wait().await.dot()?;
+    let status = command.spawn()?.wait().await.dot()?;
     log::debug!("Cargo envs: {}", GRAY.paint(envs));
     log::info!("Cargo front tests finished {}", GRAY.paint(line));
+    Ok(SuiteResult {
+        name: format!("{} front", proj.name),
+        passed: status.success(),
+    })
+}
+
+fn ensure_runner(runner: TestRunner) -> Result<()> {
+    if runner == TestRunner::Nextest && which::which("cargo-nextest").is_err() {
+        bail!("The test-runner nextest is configured but cargo-nextest was not found. Install it: https://nexte.st/book/pre-built-binaries");
+    }
     Ok(())
 }
+
+struct Coverage {
+    /// absolute path to the dir where the coverage reports are written
+    dir: Utf8PathBuf,
+    /// where grcov looks for the instrumented binaries
+    target_dir: Utf8PathBuf,
+}
+
+impl Coverage {
+    /// prepares the coverage dir, removing the profiles of previous runs
+    async fn start(proj: &Project) -> Result<Self> {
+        let me = Self {
+            dir: proj
+                .working_dir
+                .join(&proj.target_dir)
+                .join("coverage")
+                .join(&proj.name),
+            target_dir: proj.target_dir.clone(),
+        };
+        let profraw = me.profraw_dir();
+        if profraw.exists() {
+            fs::rm_dir_content(&profraw).await?;
+        }
+        fs::create_dir_all(&profraw).await?;
+        Ok(me)
+    }
+
+    fn profraw_dir(&self) -> Utf8PathBuf {
+        self.dir.join("profraw")
+    }
+
+    /// env vars for instrumenting the cargo test process of the given side
+    fn envs(&self, side: &str) -> Vec<(&'static str, String)> {
+        let rustflags = match std::env::var("RUSTFLAGS") {
+            Ok(flags) if !flags.is_empty() => format!("{flags} -C instrument-coverage"),
+            _ => "-C instrument-coverage".to_string(),
+        };
+        let profile_file = self.profraw_dir().join(format!("{side}-%p-%m.profraw"));
+        vec![
+            ("RUSTFLAGS", rustflags),
+            ("LLVM_PROFILE_FILE", profile_file.to_string()),
+        ]
+    }
+
+    async fn report(&self) -> Result<()> {
+        let grcov = Exe::Grcov.get().await.dot()?;
+        let profraw = self.profraw_dir();
+        let args = [
+            profraw.as_str(),
+            "--binary-path",
+            self.target_dir.as_str(),
+            "--source-dir",
+            ".",
+            "--output-types",
+            "lcov,html",
+            "--branch",
+            "--ignore-not-existing",
+            "--output-path",
+            self.dir.as_str(),
+        ];
+        log::debug!(
+            "Coverage running {}",
+            GRAY.paint(format!("grcov {}", args.join(" ")))
+        );
+
+        let status = Command::new(grcov)
+            .args(args)
+            .status()
+            .await
+            .context("Could not spawn grcov")?;
+        if !status.success() {
+            bail!(
+                "grcov failed with {status}. It requires the llvm-tools, install them with: rustup component add llvm-tools-preview"
+            );
+        }
+        log::info!(
+            "Coverage report written to {}",
+            GRAY.paint(self.dir.join("html").join("index.html").as_str())
+        );
+        Ok(())
+    }
+}
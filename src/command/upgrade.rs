@@ -0,0 +1,50 @@
+use crate::{
+    config::Config,
+    ext::{
+        anyhow::{Context, Result},
+        fs,
+    },
+    logger::GRAY,
+};
+
+const VERSION_FILE: &str = ".leptos-version";
+
+/// Cleans the site outputs once when the target dir was last built by another
+/// cargo-leptos version, as the naming and layout of the outputs may have changed.
+pub async fn clean_on_upgrade(conf: &Config) -> Result<()> {
+    let marker = conf.target_dir.join(VERSION_FILE);
+    let current = env!("CARGO_PKG_VERSION");
+    let previous = if marker.exists() {
+        Some(fs::read_to_string(&marker).await?.trim().to_string())
+    } else {
+        None
+    };
+    if previous.as_deref() == Some(current) {
+        return Ok(());
+    }
+
+    for proj in &conf.projects {
+        let mut dirs = vec![proj.site.root_dir.clone(), proj.tmp_dir.clone()];
+        if let Some(assets) = &proj.assets {
+            dirs.push(assets.cache_dir.clone());
+        }
+        let dirs = dirs.into_iter().filter(|d| d.exists()).collect::<Vec<_>>();
+        if dirs.is_empty() {
+            continue;
+        }
+        log::info!(
+            "Leptos cleaning the outputs of {} built by cargo-leptos {}, now {current}",
+            proj.name,
+            previous.as_deref().unwrap_or("unknown")
+        );
+        for dir in dirs {
+            log::debug!("Leptos cleaning {}", GRAY.paint(dir.as_str()));
+            fs::rm_dir_content(&dir).await?;
+        }
+    }
+
+    fs::create_dir_all(&conf.target_dir).await?;
+    fs::write(&marker, current)
+        .await
+        .context(format!("Could not write {marker:?}"))
+}
@@ -1,44 +1,187 @@
 use std::sync::Arc;
 
 use crate::{
-    compile::{self},
-    config::Project,
+    compile::{self, SassError},
+    config::{Config, Project},
     ext::anyhow::Context,
+    logger::{self, Event},
     service,
-    signal::{Interrupt, Product, ProductSet, ReloadSignal, ServerRestart},
+    signal::{Interrupt, Outcome, Product, ProductSet, ReloadSignal, ServerRestart},
 };
 use anyhow::Result;
+use clap::Args;
 use tokio::try_join;
 
 use super::build::build_proj;
+use crate::Opts;
 
-pub async fn watch(proj: &Arc<Project>) -> Result<()> {
-    build_proj(proj).await?;
+#[derive(Clone, Debug, Args, PartialEq, Default)]
+pub struct WatchCommand {
+    #[command(flatten)]
+    pub opts: Opts,
 
-    let _watch = service::notify::spawn(proj).await?;
+    /// Re-run the server and front tests on each change instead of building and serving the site.
+    #[arg(long)]
+    pub test: bool,
 
-    service::serve::spawn(proj).await;
-    service::reload::spawn(proj).await;
+    /// Only run the tests whose name contains the filter (with --test).
+    #[arg(long, requires = "test")]
+    pub test_filter: Option<String>,
+}
 
-    let res = run_loop(proj).await;
-    if res.is_err() {
-        Interrupt::request_shutdown().await;
+/// watches the current project of the config, restarting with the reloaded config when it changes
+pub async fn watch(config: &Config) -> Result<()> {
+    let mut reloaded: Option<Config> = None;
+    loop {
+        let config = reloaded.as_ref().unwrap_or(config);
+        let restarted = reloaded.is_some();
+        match watch_proj(&config.current_project()?, config, restarted).await? {
+            Some(new) => reloaded = Some(new),
+            None => return Ok(()),
+        }
     }
-    res
 }
 
-pub async fn run_loop(proj: &Arc<Project>) -> Result<()> {
+/// builds and serves the project until shutdown, or until its config changed: then returns the
+/// reloaded config once the services are stopped
+async fn watch_proj(
+    proj: &Arc<Project>,
+    config: &Config,
+    restarted: bool,
+) -> Result<Option<Config>> {
+    match build_proj(proj).await {
+        // after a restart, a failed build waits for the next change like in the watch loop
+        Err(e) if restarted => {
+            log::error!("Watch {e:#}");
+            logger::emit(Event::BuildFailed {
+                error: format!("{e:#}"),
+            });
+        }
+        res => res?,
+    }
+
+    let mut services = vec![service::notify::spawn(proj).await?];
+    let serve = service::serve::spawn(proj).await;
+    services.push(service::reload::spawn(proj).await);
+    if let Some(grace) = proj.exit_on_no_clients {
+        services.push(service::reload::spawn_exit_on_no_clients(grace));
+    }
+    // the status line would be mixed with the json events, or shown despite --quiet
+    if !logger::is_json() && log::max_level() >= log::LevelFilter::Info {
+        services.push(service::status::spawn());
+    }
+    if restarted {
+        // the pages reconnect to the restarted reload server
+        ReloadSignal::send_full();
+    }
+
+    match run_loop(proj, config).await {
+        Err(e) => {
+            Interrupt::request_shutdown().await;
+            Err(e)
+        }
+        Ok(None) => Ok(None),
+        Ok(Some(new)) => {
+            Interrupt::request_restart();
+            serve.await??;
+            for service in services {
+                service.await?;
+            }
+            Interrupt::clear_source_changes().await;
+            Ok(Some(new))
+        }
+    }
+}
+
+/// a sass error is logged as a failed style build, without holding back the other products
+fn sass_failed(style: Result<Outcome>) -> Result<Outcome> {
+    match style {
+        Err(e) => match e.downcast_ref::<SassError>() {
+            Some(sass) => {
+                log::error!("Style {sass}");
+                logger::emit(Event::BuildFailed {
+                    error: sass.to_string(),
+                });
+                Ok(Outcome::Failed)
+            }
+            None => Err(e),
+        },
+        ok => ok,
+    }
+}
+
+/// like a failed cargo build, a failed step is reported and the watch waits for the next change
+fn build_failed(e: &anyhow::Error) {
+    log::error!("Watch {e:#}");
+    logger::emit(Event::BuildFailed {
+        error: format!("{e:#}"),
+    });
+    service::status::build_finished();
+}
+
+/// builds the changes until shutdown, or until the config changed: then returns the reloaded one
+pub async fn run_loop(proj: &Arc<Project>, config: &Config) -> Result<Option<Config>> {
     let mut int = Interrupt::subscribe_any();
+    // the products of a stopped build, already registered as built, are served with the next one
+    let mut built = ProductSet::empty();
     loop {
         log::debug!("Watch waiting for changes");
         int.recv().await.dot()?;
 
         if Interrupt::is_shutdown_requested().await {
             log::debug!("Shutting down");
-            return Ok(());
+            return Ok(None);
+        }
+
+        // the changes of several rapid saves are built together, in one build
+        Interrupt::drain(&mut int);
+        let changes = Interrupt::take_source_changes().await;
+        if changes.is_empty() {
+            continue;
+        }
+        logger::emit(Event::BuildStarted);
+
+        if changes.need_config_reload() {
+            match config.reload() {
+                Ok(new) => {
+                    log::info!("Watch config changed, restarting");
+                    return Ok(Some(new));
+                }
+                // like a failed cargo build, an invalid config waits for the next change
+                Err(e) => {
+                    log::error!("Watch could not reload the config: {e:#}");
+                    logger::emit(Event::BuildFailed {
+                        error: format!("{e:#}"),
+                    });
+                    service::status::build_finished();
+                    continue;
+                }
+            }
         }
 
-        let changes = Interrupt::get_source_changes().await;
+        // like a failed cargo build, a failed hook waits for the next change
+        match super::hook::pre_build(proj).await {
+            Ok(Outcome::Stopped) => {
+                log::debug!("Watch pre-build stopped, its changes are built with the next ones");
+                Interrupt::restore_source_changes(changes).await;
+                continue;
+            }
+            Ok(_) => {}
+            Err(e) => {
+                build_failed(&e);
+                continue;
+            }
+        }
+        // shared path dependencies are in both the lib and the bin src paths, so they trigger both builds
+        match (changes.need_front_build(), changes.need_server_build()) {
+            (true, false) => {
+                log::info!("Watch only front sources changed, skipping the server build")
+            }
+            (false, true) => {
+                log::info!("Watch only server sources changed, skipping the wasm build")
+            }
+            _ => {}
+        }
 
         let server_hdl = compile::server(proj, &changes).await;
         let front_hdl = compile::front(proj, &changes).await;
@@ -47,8 +190,17 @@ pub async fn run_loop(proj: &Arc<Project>) -> Result<()> {
 
         let (serve, front, assets, style) =
             try_join!(server_hdl, front_hdl, assets_hdl, style_hdl)?;
+        let outcomes = vec![serve?, front?, assets?, sass_failed(style)?];
 
-        let set = ProductSet::from(vec![serve?, front?, assets?, style?]);
+        // a build stopped by a change is redone in the next build, with the new changes
+        let stopped = outcomes.contains(&Outcome::Stopped);
+        built.add(outcomes);
+        if stopped {
+            log::debug!("Watch build stopped, its changes are built with the next ones");
+            Interrupt::restore_source_changes(changes).await;
+            continue;
+        }
+        let set = built.take();
 
         if set.is_empty() {
             log::trace!("Build step done with no changes");
@@ -56,17 +208,45 @@ pub async fn run_loop(proj: &Arc<Project>) -> Result<()> {
             log::trace!("Build step done with changes: {set}");
         }
 
-        if set.only_style() {
+        if !set.is_empty() {
+            if let Err(e) = super::hook::post_build(proj).await {
+                build_failed(&e);
+                continue;
+            }
+        }
+
+        // the injected file names only change when they are hashed, which is handled below
+        let index_changed = proj.index_file.is_some() && changes.need_index_build();
+        if index_changed {
+            if let Err(e) = compile::index(proj).await {
+                build_failed(&e);
+                continue;
+            }
+        }
+
+        if proj.hash_file.is_some() && set.contains_any(&[Product::Front, Product::Style]) {
+            let hashed = async {
+                compile::hash_files(proj).await?;
+                compile::index(proj).await
+            };
+            if let Err(e) = hashed.await {
+                build_failed(&e);
+                continue;
+            }
+            // the server reads the hashed file names at startup
+            ServerRestart::send();
+            log::info!("Watch updated {set}. Server restarting")
+        } else if set.only_style() && !index_changed {
             ReloadSignal::send_style();
             log::info!("Watch updated style")
         } else if set.contains(&Product::Server) {
             // send product change, then the server will send the reload once it has restarted
             ServerRestart::send();
             log::info!("Watch updated {set}. Server restarting")
-        } else if set.contains_any(&[Product::Front, Product::Assets]) {
+        } else if set.contains_any(&[Product::Front, Product::Assets]) || index_changed {
             ReloadSignal::send_full();
             log::info!("Watch updated {set}")
         }
-        Interrupt::clear_source_changes().await;
+        service::status::build_finished();
     }
 }
@@ -0,0 +1,94 @@
+use std::process::Stdio;
+
+use crate::config::{AssetTransform, AssetsConfig};
+use crate::ext::anyhow::{anyhow, ensure, Context, Result};
+use crate::{fs, logger::GRAY};
+use camino::{Utf8Path, Utf8PathBuf};
+use tokio::{io::AsyncWriteExt, process::Command};
+
+/// copies the asset, running it through the first matching transform of the pipeline.
+/// If the transform fails, the asset is copied as is.
+pub async fn copy_asset(
+    assets: &AssetsConfig,
+    rel: &Utf8Path,
+    from: &Utf8Path,
+    to: &Utf8Path,
+) -> Result<()> {
+    let Some(transform) = assets.transform_for(rel) else {
+        fs::copy(from, to).await?;
+        return Ok(());
+    };
+
+    match transformed(&assets.cache_dir, transform, from).await {
+        Ok(cached) => {
+            log::debug!(
+                "Assets transformed {} -> {}",
+                GRAY.paint(from.as_str()),
+                GRAY.paint(to.as_str())
+            );
+            fs::copy(cached, to).await?;
+        }
+        Err(e) => {
+            log::warn!(
+                "Assets transform {:?} failed, copying {from} untransformed: {e:#}",
+                transform.cmd
+            );
+            fs::copy(from, to).await?;
+        }
+    }
+    Ok(())
+}
+
+/// returns the path of the transformed file in the cache, running the transform if it isn't cached
+async fn transformed(
+    cache_dir: &Utf8Path,
+    transform: &AssetTransform,
+    from: &Utf8Path,
+) -> Result<Utf8PathBuf> {
+    let data = fs::read(from).await?;
+    let key = seahash::hash(&[transform.cmd.as_bytes(), &data].concat());
+    let ext = from.extension().unwrap_or_default();
+    let cached = cache_dir.join(format!("{key:x}")).with_extension(ext);
+
+    if cached.exists() {
+        log::trace!("Assets transform cache hit {}", GRAY.paint(from.as_str()));
+        return Ok(cached);
+    }
+    fs::create_dir_all(cache_dir).await?;
+
+    let mut parts = transform.cmd.split_whitespace();
+    let exe = parts
+        .next()
+        .ok_or_else(|| anyhow!("Invalid command {:?}", transform.cmd))?;
+    let mut command = Command::new(exe);
+    command.args(parts);
+
+    if transform.in_place {
+        let work = cache_dir.join(format!("part-{key:x}")).with_extension(ext);
+        fs::write(&work, &data).await?;
+        let status = command
+            .arg(&work)
+            .status()
+            .await
+            .context(format!("Could not spawn command {:?}", transform.cmd))?;
+        if !status.success() {
+            let _ = fs::remove_file(&work).await;
+        }
+        ensure!(status.success(), "exited with {status}");
+        fs::rename(&work, &cached).await?;
+    } else {
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .context(format!("Could not spawn command {:?}", transform.cmd))?;
+        let mut stdin = child.stdin.take().context("Could not open stdin")?;
+        let write = async move { stdin.write_all(&data).await };
+        let (written, output) = tokio::join!(write, child.wait_with_output());
+        let output = output?;
+        ensure!(output.status.success(), "exited with {}", output.status);
+        written.context("Could not write to stdin")?;
+        fs::write(&cached, &output.stdout).await?;
+    }
+    Ok(cached)
+}
@@ -1,15 +1,23 @@
-use std::sync::Arc;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::Arc,
+};
 
-use super::ChangeSet;
-use crate::config::Project;
-use crate::ext::anyhow::{Context, Result};
+use super::{asset_pipeline::copy_asset, ChangeSet};
+use crate::config::{AssetsConfig, AssetsConflict, Project};
+use crate::ext::anyhow::{bail, Context, Result};
 use crate::service::notify::Watched;
 use crate::service::site::SourcedSiteFile;
 use crate::signal::{Outcome, Product};
 use crate::{ext::PathExt, fs, logger::GRAY};
 use camino::{Utf8Path, Utf8PathBuf};
+use futures_util::{stream, StreamExt, TryStreamExt};
+use ignore::gitignore::Gitignore;
 use tokio::task::JoinHandle;
 
+/// the optional gitignore-style file, in the root of an assets dir, listing the files not to copy
+const LEPTOS_IGNORE: &str = ".leptosignore";
+
 pub async fn assets(
     proj: &Arc<Project>,
     changes: &ChangeSet,
@@ -26,14 +34,19 @@ pub async fn assets(
 
         let change = if first_sync {
             log::trace!("Assets starting full resync");
-            resync(&assets.dir, dest_root).await?;
+            resync(assets, dest_root, &proj.site.pkg_dir, proj.force_resync).await?;
             true
         } else {
             let mut changed = false;
+            let ignores = assets
+                .dirs
+                .iter()
+                .map(|dir| AssetsIgnore::new(dir))
+                .collect::<Vec<_>>();
             for watched in changes.asset_iter() {
                 log::trace!("Assets processing {watched:?}");
                 let change =
-                    update_asset(&proj, watched.clone(), &assets.dir, dest_root, &[]).await?;
+                    update_asset(&proj, watched.clone(), assets, dest_root, &ignores).await?;
                 changed |= change;
             }
             changed
@@ -51,28 +64,79 @@ pub async fn assets(
 async fn update_asset(
     proj: &Project,
     watched: Watched,
-    src_root: &Utf8Path,
+    assets: &AssetsConfig,
     dest_root: &Utf8Path,
-    reserved: &[Utf8PathBuf],
+    ignores: &[AssetsIgnore],
 ) -> Result<bool> {
-    if let Some(path) = watched.path() {
-        if reserved.contains(path) {
-            log::warn!("Assets reserved filename for Leptos. Please remove {path:?}");
-            return Ok(false);
-        }
+    let src_dirs = &assets.dirs;
+    let Some(path) = watched.path() else {
+        resync(assets, dest_root, &proj.site.pkg_dir, proj.force_resync).await?;
+        return Ok(true);
+    };
+    let Some(src_pos) = src_dirs.iter().rposition(|dir| path.starts_with(dir)) else {
+        log::debug!("Assets {path:?} is not in any assets dir");
+        return Ok(false);
+    };
+    let src_root = src_dirs[src_pos].as_path();
+    if reserved(src_root, &proj.site.pkg_dir)
+        .iter()
+        .any(|reserved| path.starts_with(reserved))
+    {
+        log::warn!("Assets reserved filename for Leptos. Please remove {path:?}");
+        return Ok(false);
+    }
+    let ignore = &ignores[src_pos];
+
+    if *path == ignore.file {
+        log::debug!("Assets {LEPTOS_IGNORE} changed, resyncing");
+        resync(assets, dest_root, &proj.site.pkg_dir, proj.force_resync).await?;
+        return Ok(true);
     }
+    let rel = path.unbase(src_root)?;
+    if assets.is_excluded(&rel) {
+        log::trace!("Assets ignoring {path:?} excluded in this profile");
+        return Ok(false);
+    }
+    if ignore.is_ignored(path, path.is_dir()) {
+        log::trace!("Assets ignoring {path:?} listed in {LEPTOS_IGNORE}");
+        return Ok(false);
+    }
+    if let Some(overriding) = find_in_dirs(&src_dirs[src_pos + 1..], &rel) {
+        log::debug!(
+            "Assets ignoring {} overridden by {}",
+            GRAY.paint(path.as_str()),
+            GRAY.paint(overriding.as_str())
+        );
+        return Ok(false);
+    }
+
     Ok(match watched {
         Watched::Create(f) => {
             let to = f.rebase(src_root, dest_root)?;
-            if f.is_dir() {
-                fs::copy_dir_all(f, to).await?;
-            } else {
-                fs::copy(&f, &to).await?;
+            if let Some(overridden) = find_in_dirs(&src_dirs[..src_pos], &rel) {
+                on_conflict(assets, &overridden, &f, &to)?;
             }
+            copy_any(assets, ignore, &rel, f, to).await?;
             true
         }
         Watched::Remove(f) => {
             let path = f.rebase(src_root, dest_root)?;
+            let overridden = find_in_dirs(&src_dirs[..src_pos], &rel).and_then(|overridden| {
+                let ignore = ignores
+                    .iter()
+                    .rev()
+                    .find(|i| overridden.starts_with(&i.dir))?;
+                let is_dir = overridden.is_dir();
+                (!ignore.is_ignored(&overridden, is_dir)).then_some((overridden, ignore))
+            });
+            if let Some((overridden, ignore)) = overridden {
+                log::debug!(
+                    "Assets restoring overridden {}",
+                    GRAY.paint(overridden.as_str())
+                );
+                copy_any(assets, ignore, &rel, overridden, path).await?;
+                return Ok(true);
+            }
             if path.is_dir() {
                 fs::remove_dir_all(&path)
                     .await
@@ -92,6 +156,11 @@ async fn update_asset(
                 .context(format!("rename {from:?} to {to:?}"))?;
             true
         }
+        Watched::Write(f) if assets.transform_for(&rel).is_some() => {
+            let to = f.rebase(src_root, dest_root)?;
+            copy_asset(assets, &rel, &f, &to).await?;
+            true
+        }
         Watched::Write(f) => {
             let file = SourcedSiteFile {
                 source: f.clone(),
@@ -100,88 +169,296 @@ async fn update_asset(
             };
             proj.site.updated(&file).await?
         }
-        Watched::Rescan => {
-            resync(src_root, dest_root).await?;
-            true
-        }
+        Watched::Rescan => unreachable!("handled above"),
     })
 }
 
-pub fn reserved(src: &Utf8Path) -> Vec<Utf8PathBuf> {
-    vec![src.join("index.html"), src.join("pkg")]
+/// the site files written by the build: the index.html from the index-file and the pkg dir
+pub fn reserved(src: &Utf8Path, pkg_dir: &Utf8Path) -> Vec<Utf8PathBuf> {
+    vec![src.join("index.html"), src.join(pkg_dir)]
 }
 
-// pub async fn update(config: &Config) -> Result<()> {
-//     if let Some(src) = &config.leptos.assets_dir {
-//         let dest = DEST.to_canoncial_dir().dot()?;
-//         let src = src.to_canoncial_dir().dot()?;
+/// the file of a later assets dir overrides the one of an earlier dir copied to the same site file
+fn on_conflict(
+    assets: &AssetsConfig,
+    previous: &Utf8Path,
+    from: &Utf8Path,
+    to: &Utf8Path,
+) -> Result<()> {
+    match assets.on_conflict {
+        AssetsConflict::Warn => log::warn!(
+            "Assets conflict {} overrides {} in {}",
+            GRAY.paint(from.as_str()),
+            GRAY.paint(previous.as_str()),
+            GRAY.paint(to.as_str())
+        ),
+        AssetsConflict::Error => {
+            bail!("Assets conflict: both {previous:?} and {from:?} are copied to {to:?}")
+        }
+    }
+    Ok(())
+}
 
-//         resync(&src, &dest)
-//             .await
-//             .context(format!("Could not synchronize {src:?} with {dest:?}"))?;
-//     }
-//     Ok(())
-// }
+/// finds the relative path in the dirs, searching from the last dir
+fn find_in_dirs(dirs: &[Utf8PathBuf], rel: &Utf8Path) -> Option<Utf8PathBuf> {
+    dirs.iter()
+        .rev()
+        .map(|dir| dir.join(rel))
+        .find(|p| p.exists())
+}
 
-async fn resync(src: &Utf8Path, dest: &Utf8Path) -> Result<()> {
-    clean_dest(dest)
-        .await
-        .context(format!("Cleaning {dest:?}"))?;
-    let reserved = reserved(src);
-    mirror(src, dest, &reserved)
-        .await
-        .context(format!("Mirroring {src:?} -> {dest:?}"))
+async fn copy_any(
+    assets: &AssetsConfig,
+    ignore: &AssetsIgnore,
+    rel: &Utf8Path,
+    from: Utf8PathBuf,
+    to: Utf8PathBuf,
+) -> Result<()> {
+    if from.is_dir() {
+        fs::copy_dir_all(from, to, |path, is_dir| ignore.is_ignored(path, is_dir)).await
+    } else {
+        copy_asset(assets, rel, &from, &to).await
+    }
 }
 
-async fn clean_dest(dest: &Utf8Path) -> Result<()> {
-    let mut entries = fs::read_dir(dest).await?;
-    while let Some(entry) = entries.next_entry().await? {
-        let path = entry.path();
+/// the assets listed in the [`LEPTOS_IGNORE`] file of an assets dir
+struct AssetsIgnore {
+    dir: Utf8PathBuf,
+    file: Utf8PathBuf,
+    ignore: Gitignore,
+}
 
-        if entry.file_type().await?.is_dir() {
-            if entry.file_name() != "pkg" {
-                log::debug!(
-                    "Assets removing folder {}",
-                    GRAY.paint(path.to_string_lossy())
-                );
-                fs::remove_dir_all(path).await?;
+impl AssetsIgnore {
+    fn new(dir: &Utf8Path) -> Self {
+        let file = dir.join(LEPTOS_IGNORE);
+        let ignore = if file.exists() {
+            let (ignore, err) = Gitignore::new(&file);
+            if let Some(err) = err {
+                log::warn!("Assets invalid line in {file:?}: {err}");
             }
-        } else if entry.file_name() != "index.html" {
-            log::debug!(
-                "Assets removing file {}",
-                GRAY.paint(path.to_string_lossy())
-            );
-            fs::remove_file(path).await?;
+            ignore
+        } else {
+            Gitignore::empty()
+        };
+        Self {
+            dir: dir.to_path_buf(),
+            file,
+            ignore,
         }
     }
+
+    /// if the path, or one of its parent dirs, is ignored.
+    /// The ignore file itself is never copied
+    fn is_ignored(&self, path: &Utf8Path, is_dir: bool) -> bool {
+        path == self.file
+            || self
+                .ignore
+                .matched_path_or_any_parents(path, is_dir)
+                .is_ignore()
+    }
+}
+
+/// Mirrors the assets dirs to the dest. Unless forced, the files already up to date in the dest are
+/// not copied again and only the stale ones are removed, instead of cleaning the dest first.
+pub async fn resync(
+    assets: &AssetsConfig,
+    dest: &Utf8Path,
+    pkg_dir: &Utf8Path,
+    force: bool,
+) -> Result<()> {
+    let keep = [dest.join(pkg_dir), dest.join("index.html")];
+    if force {
+        clean_dest(dest, &keep)
+            .await
+            .context(format!("Cleaning {dest:?}"))?;
+    }
+    let mut written = HashMap::new();
+    for src in &assets.dirs {
+        let reserved = reserved(src, pkg_dir);
+        let ignore = AssetsIgnore::new(src);
+        mirror(src, dest, &reserved, &ignore, &mut written, assets)
+            .await
+            .context(format!("Mirroring {src:?} -> {dest:?}"))?;
+    }
+    if !force {
+        remove_stale(dest, &written, &keep)
+            .await
+            .context(format!("Removing stale assets in {dest:?}"))?;
+    }
     Ok(())
 }
 
-async fn mirror(src_root: &Utf8Path, dest_root: &Utf8Path, reserved: &[Utf8PathBuf]) -> Result<()> {
-    let mut entries = src_root.read_dir_utf8()?;
-    while let Some(Ok(entry)) = entries.next() {
-        let from = entry.path().to_path_buf();
-        let to = from.rebase(src_root, dest_root)?;
-        if reserved.contains(&from) {
-            log::warn!("");
-            continue;
+/// removes what was neither mirrored to the dest nor is to keep. The paths are compared exactly,
+/// so that a file renamed to another case replaces the previous one.
+async fn remove_stale(
+    dest: &Utf8Path,
+    written: &HashMap<String, (Utf8PathBuf, Utf8PathBuf)>,
+    keep: &[Utf8PathBuf],
+) -> Result<()> {
+    // per lowercased path, the exact path of the written files and of their dirs
+    let mut files = HashMap::new();
+    let mut kept_dirs = HashMap::new();
+    for (_, file) in written.values() {
+        files.insert(file.as_str().to_lowercase(), file.as_str());
+    }
+    for file in written.values().map(|(_, to)| to).chain(keep) {
+        let mut dir = file.parent();
+        while let Some(parent) = dir {
+            let key = parent.as_str().to_lowercase();
+            if kept_dirs.insert(key, parent.as_str()).is_some() {
+                break;
+            }
+            dir = parent.parent();
         }
+    }
 
-        if entry.file_type()?.is_dir() {
-            log::debug!(
-                "Assets copy folder {} -> {}",
-                GRAY.paint(from.as_str()),
-                GRAY.paint(to.as_str())
-            );
-            fs::copy_dir_all(from, to).await?;
-        } else {
+    let mut dirs = VecDeque::from([dest.to_path_buf()]);
+    while let Some(dir) = dirs.pop_front() {
+        let entries = dir.read_dir_utf8()?.collect::<Result<Vec<_>, _>>()?;
+        let listed = entries
+            .iter()
+            .map(|entry| entry.path().as_str())
+            .collect::<HashSet<_>>();
+        for entry in &entries {
+            let path = entry.path();
+            if keep.iter().any(|keep| keep == path) {
+                continue;
+            }
+            if entry.file_type()?.is_dir() {
+                if is_kept(path, &kept_dirs, &listed) {
+                    dirs.push_back(path.to_path_buf());
+                } else {
+                    log::debug!("Assets removing stale folder {}", GRAY.paint(path.as_str()));
+                    fs::remove_dir_all(path).await?;
+                }
+            } else if !is_kept(path, &files, &listed) {
+                log::debug!("Assets removing stale file {}", GRAY.paint(path.as_str()));
+                fs::remove_file(path).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// if the path is a kept one. On a case-insensitive file system, a copy over a file differing in
+/// case keeps the name of that file: the kept path is then not listed, and the file is the kept one.
+fn is_kept(path: &Utf8Path, kept: &HashMap<String, &str>, listed: &HashSet<&str>) -> bool {
+    match kept.get(&path.as_str().to_lowercase()) {
+        Some(exact) => *exact == path.as_str() || !listed.contains(exact),
+        None => false,
+    }
+}
+
+/// if the dest is an unchanged copy of the src: same size and not older
+fn is_up_to_date(from: &Utf8Path, to: &Utf8Path) -> bool {
+    let (Ok(from), Ok(to)) = (from.metadata(), to.metadata()) else {
+        return false;
+    };
+    match (from.modified(), to.modified()) {
+        (Ok(from_time), Ok(to_time)) => from.len() == to.len() && to_time >= from_time,
+        _ => false,
+    }
+}
+
+/// removes everything in the dest but the paths to keep, and the dirs leading to them
+async fn clean_dest(dest: &Utf8Path, keep: &[Utf8PathBuf]) -> Result<()> {
+    let mut dirs = VecDeque::from([dest.to_path_buf()]);
+    while let Some(dir) = dirs.pop_front() {
+        for entry in dir.read_dir_utf8()? {
+            let entry = entry?;
+            let path = entry.path();
+            if keep.iter().any(|keep| keep == path) {
+                continue;
+            }
+            if entry.file_type()?.is_dir() {
+                if keep.iter().any(|keep| keep.starts_with(path)) {
+                    dirs.push_back(path.to_path_buf());
+                } else {
+                    log::debug!("Assets removing folder {}", GRAY.paint(path.as_str()));
+                    fs::remove_dir_all(path).await?;
+                }
+            } else {
+                log::debug!("Assets removing file {}", GRAY.paint(path.as_str()));
+                fs::remove_file(path).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// copies all files from the src_root to the dest_root, assets.copy_concurrency at once.
+/// The written map tracks, per (lowercased) destination, the source already copied there and
+/// the exact destination.
+async fn mirror(
+    src_root: &Utf8Path,
+    dest_root: &Utf8Path,
+    reserved: &[Utf8PathBuf],
+    ignore: &AssetsIgnore,
+    written: &mut HashMap<String, (Utf8PathBuf, Utf8PathBuf)>,
+    assets: &AssetsConfig,
+) -> Result<()> {
+    let mut dirs = VecDeque::from([src_root.to_path_buf()]);
+    let mut copies = Vec::new();
+
+    while let Some(dir) = dirs.pop_front() {
+        let mut entries = dir.read_dir_utf8()?;
+        while let Some(Ok(entry)) = entries.next() {
+            let from = entry.path().to_path_buf();
+            let to = from.rebase(src_root, dest_root)?;
+            if reserved.contains(&from) {
+                log::warn!("Assets reserved filename for Leptos. Please remove {from:?}");
+                continue;
+            }
+            if assets.is_excluded(&from.unbase(src_root)?) {
+                log::debug!(
+                    "Assets skipping {} excluded in this profile",
+                    GRAY.paint(from.as_str())
+                );
+                continue;
+            }
+
+            let is_dir = entry.file_type()?.is_dir();
+            if ignore.is_ignored(&from, is_dir) {
+                log::debug!(
+                    "Assets skipping {} listed in {LEPTOS_IGNORE}",
+                    GRAY.paint(from.as_str())
+                );
+                continue;
+            }
+
+            if is_dir {
+                fs::create_dir_all(&to).await?;
+                dirs.push_back(from);
+                continue;
+            }
+
+            let previous = written.insert(to.as_str().to_lowercase(), (from.clone(), to.clone()));
+            if let Some((previous, _)) = &previous {
+                on_conflict(assets, previous, &from, &to)?;
+            }
+
+            let rel = from.unbase(src_root)?;
+            // an overriding file is always copied, as the dest was just written
+            if previous.is_none()
+                && assets.transform_for(&rel).is_none()
+                && is_up_to_date(&from, &to)
+            {
+                log::trace!("Assets up to date {}", GRAY.paint(to.as_str()));
+                continue;
+            }
             log::debug!(
                 "Assets copy file {} -> {}",
                 GRAY.paint(from.as_str()),
                 GRAY.paint(to.as_str())
             );
-            fs::copy(from, to).await?;
+            copies.push((rel, from, to));
         }
     }
-    Ok(())
+
+    // the dirs are all created by the walk, so the files can be copied in any order
+    stream::iter(copies)
+        .map(|(rel, from, to)| async move { copy_asset(assets, &rel, &from, &to).await })
+        .buffer_unordered(assets.copy_concurrency)
+        .try_collect()
+        .await
 }
@@ -12,6 +12,8 @@ pub enum Change {
     Asset(Watched),
     /// sent when a style file changed
     Style,
+    /// sent when the index file template changed
+    Index,
     /// Cargo.toml changed
     Conf,
 }
@@ -25,6 +27,7 @@ impl ChangeSet {
             Change::BinSource,
             Change::LibSource,
             Change::Style,
+            Change::Index,
             Change::Conf,
             Change::Asset(Watched::Rescan),
         ])
@@ -46,6 +49,15 @@ impl ChangeSet {
         self.0.contains(&Change::LibSource) || self.0.contains(&Change::Conf)
     }
 
+    pub fn need_index_build(&self) -> bool {
+        self.0.contains(&Change::Index) || self.0.contains(&Change::Conf)
+    }
+
+    /// the config is reloaded, and the watch restarted, when the Cargo.toml or an env file changed
+    pub fn need_config_reload(&self) -> bool {
+        self.0.contains(&Change::Conf)
+    }
+
     pub fn asset_iter(&self) -> impl Iterator<Item = &Watched> {
         self.0.iter().filter_map(|change| match change {
             Change::Asset(a) => Some(a),
@@ -58,6 +70,13 @@ impl ChangeSet {
             || (css_in_source && self.0.contains(&Change::LibSource))
     }
 
+    /// adds the changes not already in the set
+    pub fn extend(&mut self, other: ChangeSet) {
+        for change in other.0 {
+            self.add(change);
+        }
+    }
+
     pub fn add(&mut self, change: Change) -> bool {
         if !self.0.contains(&change) {
             self.0.push(change);
@@ -0,0 +1,77 @@
+use crate::ext::anyhow::{ensure, Context, Result};
+
+const EXTERNAL_DEBUG_INFO: &str = "external_debug_info";
+
+/// Removes the DWARF custom sections from the wasm and adds an `external_debug_info`
+/// section pointing the browser's DWARF extension to the sidecar file holding them.
+///
+/// The sidecar url is resolved relative to the url of the wasm file.
+pub fn split_dwarf(wasm: &[u8], sidecar_url: &str) -> Result<Vec<u8>> {
+    ensure!(
+        wasm.len() >= 8 && wasm.starts_with(b"\0asm"),
+        "Not a wasm module"
+    );
+    let mut out = wasm[..8].to_vec();
+    let mut pos = 8;
+
+    while pos < wasm.len() {
+        let start = pos;
+        let id = wasm[pos];
+        pos += 1;
+        let size = read_leb128(wasm, &mut pos)?;
+        let payload = wasm
+            .get(pos..pos + size)
+            .context("Truncated wasm section")?;
+        pos += size;
+
+        if id == 0 {
+            let mut name_pos = 0;
+            let name_len = read_leb128(payload, &mut name_pos)?;
+            let name = payload
+                .get(name_pos..name_pos + name_len)
+                .context("Truncated wasm custom section name")?;
+            if name.starts_with(b".debug") {
+                continue;
+            }
+        }
+        out.extend_from_slice(&wasm[start..pos]);
+    }
+
+    let mut section = Vec::new();
+    write_leb128(&mut section, EXTERNAL_DEBUG_INFO.len());
+    section.extend_from_slice(EXTERNAL_DEBUG_INFO.as_bytes());
+    write_leb128(&mut section, sidecar_url.len());
+    section.extend_from_slice(sidecar_url.as_bytes());
+
+    out.push(0);
+    write_leb128(&mut out, section.len());
+    out.extend(section);
+    Ok(out)
+}
+
+pub(super) fn read_leb128(bytes: &[u8], pos: &mut usize) -> Result<usize> {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let byte = *bytes.get(*pos).context("Truncated wasm LEB128")?;
+        *pos += 1;
+        value |= ((byte & 0x7f) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+        shift += 7;
+        ensure!(shift < 35, "Invalid wasm LEB128");
+    }
+}
+
+fn write_leb128(out: &mut Vec<u8>, mut value: usize) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
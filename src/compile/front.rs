@@ -1,16 +1,19 @@
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
+use super::dwarf::split_dwarf;
+use super::wasm_size;
 use super::ChangeSet;
-use crate::config::Project;
+use crate::config::{BindgenTarget, Project, WasmDwarf};
 use crate::ext::fs;
 use crate::ext::sync::wait_interruptible;
 use crate::signal::{Interrupt, Outcome, Product};
 use crate::{
     ext::{
-        anyhow::{Context, Result},
+        anyhow::{bail, Context, Result},
         exe::Exe,
     },
-    logger::GRAY,
+    logger::{self, Event, GRAY},
 };
 use camino::Utf8Path;
 use itertools::Itertools;
@@ -28,7 +31,12 @@ pub async fn front(proj: &Arc<Project>, changes: &ChangeSet) -> JoinHandle<Resul
         }
 
         fs::create_dir_all(&proj.site.root_relative_pkg_dir()).await?;
+        check_wasm_target().await?;
 
+        log::info!(
+            "Cargo compiling the lib to wasm{}",
+            first_build_hint(&proj.lib.wasm_file.source)
+        );
         let (envs, line, process) = front_cargo_process("build", true, &proj)?;
 
         if !wait_interruptible("Cargo", process, Interrupt::subscribe_any()).await? {
@@ -37,10 +45,83 @@ pub async fn front(proj: &Arc<Project>, changes: &ChangeSet) -> JoinHandle<Resul
         log::debug!("Cargo envs: {}", GRAY.paint(envs));
         log::info!("Cargo finished {}", GRAY.paint(line));
 
-        bindgen(&proj).await.dot()
+        log::info!("Front running wasm-bindgen");
+        let outcome = bindgen(&proj).await.dot()?;
+        if let Outcome::Success(_) = outcome {
+            logger::emit(Event::WasmDone);
+        }
+        Ok(outcome)
     })
 }
 
+/// the target of the front build
+pub const WASM_TARGET: &str = "wasm32-unknown-unknown";
+
+/// Fails with the command installing the wasm target when it is missing from the toolchain, instead
+/// of the opaque error of rustc. Checked in the sysroot, which also covers the toolchains not
+/// managed by rustup. When rustc cannot tell, the build goes on and cargo reports the problem.
+pub async fn check_wasm_target() -> Result<()> {
+    static INSTALLED: AtomicBool = AtomicBool::new(false);
+    if INSTALLED.load(Ordering::Relaxed) {
+        return Ok(());
+    }
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let output = match Command::new(rustc)
+        .args(["--print", "sysroot"])
+        .output()
+        .await
+    {
+        Ok(output) if output.status.success() => output,
+        _ => return Ok(()),
+    };
+    let sysroot = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    let target_dir = Utf8Path::new(&sysroot)
+        .join("lib/rustlib")
+        .join(WASM_TARGET);
+    if !target_dir.exists() {
+        bail!("The {WASM_TARGET} target is not installed for the toolchain {sysroot}. Install it with: rustup target add {WASM_TARGET}");
+    }
+    INSTALLED.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+/// the first build compiles all the dependencies, which makes it a lot slower
+pub fn first_build_hint(output: &Utf8Path) -> &'static str {
+    if output.exists() {
+        ""
+    } else {
+        " (this can take a while on the first build)"
+    }
+}
+
+/// the --jobs argument of the cargo command, named --build-jobs by nextest
+pub fn jobs_arg(cmd: &str, proj: &Project) -> Option<String> {
+    let flag = match cmd.starts_with("nextest") {
+        true => "--build-jobs",
+        false => "--jobs",
+    };
+    proj.jobs.map(|jobs| format!("{flag}={jobs}"))
+}
+
+/// The CARGO_ENCODED_RUSTFLAGS with the extra flags appended to the ambient CARGO_ENCODED_RUSTFLAGS
+/// or RUSTFLAGS, which cargo would otherwise ignore as CARGO_ENCODED_RUSTFLAGS takes precedence.
+pub fn encoded_rustflags(extra: &[String]) -> Option<String> {
+    if extra.is_empty() {
+        return None;
+    }
+    let ambient = match (
+        std::env::var("CARGO_ENCODED_RUSTFLAGS"),
+        std::env::var("RUSTFLAGS"),
+    ) {
+        (Ok(encoded), _) if !encoded.is_empty() => {
+            encoded.split('\x1f').map(str::to_string).collect()
+        }
+        (_, Ok(flags)) => flags.split_whitespace().map(str::to_string).collect(),
+        _ => Vec::new(),
+    };
+    Some(ambient.iter().chain(extra).join("\x1f"))
+}
+
 pub fn front_cargo_process(
     cmd: &str,
     wasm: bool,
@@ -57,14 +138,14 @@ pub fn build_cargo_front_cmd(
     proj: &Project,
     command: &mut Command,
 ) -> (String, String) {
-    let mut args = vec![
-        cmd.to_string(),
+    let mut args = cmd.split(' ').map(str::to_string).collect::<Vec<_>>();
+    args.extend([
         format!("--package={}", proj.lib.name.as_str()),
         "--lib".to_string(),
-        "--target-dir=target/front".to_string(),
-    ];
+        format!("--target-dir={}", proj.lib.target_dir),
+    ]);
     if wasm {
-        args.push("--target=wasm32-unknown-unknown".to_string());
+        args.push(format!("--target={WASM_TARGET}"));
     }
 
     if !proj.lib.default_features {
@@ -77,8 +158,14 @@ pub fn build_cargo_front_cmd(
     if proj.release {
         args.push("--release".to_string());
     }
+    args.extend(jobs_arg(cmd, proj));
+    args.extend(proj.lib.cargo_args.iter().cloned());
 
-    let envs = proj.to_envs();
+    let mut envs = proj.to_envs();
+    // the lib rustflags target the wasm build, they are not used for the native lib tests
+    if let Some(flags) = encoded_rustflags(&proj.lib.rustflags).filter(|_| wasm) {
+        envs.push(("CARGO_ENCODED_RUSTFLAGS", flags));
+    }
 
     let envs_str = envs
         .iter()
@@ -94,24 +181,76 @@ pub fn build_cargo_front_cmd(
 async fn bindgen(proj: &Project) -> Result<Outcome> {
     let wasm_file = &proj.lib.wasm_file;
     let interrupt = Interrupt::subscribe_any();
+    check_wasm_bindgen_version(proj)?;
 
     // see:
     // https://github.com/rustwasm/wasm-bindgen/blob/main/crates/cli-support/src/lib.rs#L95
     // https://github.com/rustwasm/wasm-bindgen/blob/main/crates/cli/src/bin/wasm-bindgen.rs#L13
-    let mut bindgen = Bindgen::new()
-        .input_path(&wasm_file.source)
-        .web(true)
-        .dot()?
+    let mut bindgen = Bindgen::new();
+    bindgen.input_path(&wasm_file.source);
+    match proj.lib.bindgen_target {
+        BindgenTarget::Web => bindgen.web(true).dot()?,
+        // the nodejs shim requires <out_name>_bg.wasm
+        BindgenTarget::Nodejs => bindgen.nodejs(true).dot()?.out_name(&proj.lib.output_name),
+    };
+    if let Some(enable) = proj.lib.wasm_reference_types {
+        bindgen.reference_types(enable);
+    }
+    let mut bindgen = bindgen
+        .typescript(proj.lib.ts_file.is_some())
         .omit_imports(true)
+        .keep_debug(proj.lib.wasm_dwarf != WasmDwarf::Strip)
         .generate_output()
         .dot()?;
 
-    bindgen.wasm_mut().emit_wasm_file(&wasm_file.dest).dot()?;
-    log::trace!("Front wrote wasm to {:?}", wasm_file.dest.as_str());
-    if proj.release && !optimize(&wasm_file.dest, interrupt).await.dot()? {
-        return Ok(Outcome::Stopped);
+    if proj.lib.bindgen_target == BindgenTarget::Nodejs
+        && !(bindgen.local_modules().is_empty() && bindgen.snippets().is_empty())
+    {
+        bail!("The bindgen-target nodejs does not support JS snippets (wasm_bindgen(module = ...) or inline_js)");
     }
 
+    if proj.lib.wasm_dwarf == WasmDwarf::Split {
+        let wasm = bindgen.wasm_mut().emit_wasm();
+        let sidecar = wasm_file.dest.with_extension("debug.wasm");
+        fs::write(&sidecar, &wasm).await?;
+        log::trace!("Front wrote DWARF sidecar to {:?}", sidecar.as_str());
+        let wasm = split_dwarf(&wasm, sidecar.file_name().unwrap_or_default()).dot()?;
+        fs::write(&wasm_file.dest, wasm).await?;
+    } else if proj.release && (proj.lib.wasm_opt || proj.lib.wasm_snip) {
+        // the unoptimized wasm is an intermediate, keep it out of the site
+        fs::create_dir_all(&proj.tmp_dir).await?;
+        let unoptimized = proj
+            .tmp_dir
+            .join(format!("{}.no-optimisation.wasm", proj.lib.output_name));
+        bindgen.wasm_mut().emit_wasm_file(&unoptimized).dot()?;
+        log::trace!("Front wrote unoptimized wasm to {:?}", unoptimized.as_str());
+
+        let optimized = snip_and_optimize(proj, &unoptimized, interrupt).await;
+        if matches!(optimized, Ok(true)) {
+            size_report(proj, Some(&unoptimized));
+        }
+        if proj.lib.keep_unoptimized_wasm {
+            log::info!(
+                "Front kept the unoptimized wasm {}",
+                GRAY.paint(unoptimized.as_str())
+            );
+        } else {
+            fs::remove_file(&unoptimized).await?;
+        }
+        if !optimized.dot()? {
+            return Ok(Outcome::Stopped);
+        }
+    } else {
+        bindgen.wasm_mut().emit_wasm_file(&wasm_file.dest).dot()?;
+        if proj.release {
+            log::info!("Front skipping wasm-opt, the release wasm is not size optimized");
+        }
+        if proj.release || proj.size_report {
+            size_report(proj, None);
+        }
+    }
+    log::trace!("Front wrote wasm to {:?}", wasm_file.dest.as_str());
+
     let module_js = bindgen.local_modules().values().join("\n");
 
     let snippets = bindgen
@@ -137,6 +276,15 @@ async fn bindgen(proj: &Project) -> Result<Outcome> {
         "Front js {}",
         if js_changed { "changed" } else { "unchanged" }
     );
+    if let (Some(ts_file), Some(ts)) = (&proj.lib.ts_file, bindgen.ts()) {
+        // the declarations are not used by the site, so they don't trigger a reload
+        if proj.site.updated_with(ts_file, ts.as_bytes()).await.dot()? {
+            log::debug!(
+                "Front wrote typescript declarations to {:?}",
+                ts_file.dest.as_str()
+            );
+        }
+    }
     log::debug!(
         "Front wasm {}",
         if wasm_changed { "changed" } else { "unchanged" }
@@ -148,10 +296,160 @@ async fn bindgen(proj: &Project) -> Result<Outcome> {
     }
 }
 
-async fn optimize(file: &Utf8Path, interrupt: broadcast::Receiver<()>) -> Result<bool> {
-    let wasm_opt = Exe::WasmOpt.get().await.dot()?;
+/// the version of the wasm-bindgen cli support compiled into cargo-leptos
+fn cli_wasm_bindgen_version() -> String {
+    // the version can be followed by the git hash
+    let version = wasm_bindgen_shared::version();
+    version
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// The bindings generated by another wasm-bindgen version than the one of the project can be
+/// subtly broken, for instance rendering a blank page.
+fn check_wasm_bindgen_version(proj: &Project) -> Result<()> {
+    static WARNED: AtomicBool = AtomicBool::new(false);
+
+    let Some(project) = &proj.lib.wasm_bindgen_version else {
+        return Ok(());
+    };
+    let cli = cli_wasm_bindgen_version();
+    if *project == cli {
+        return Ok(());
+    }
+    let msg = format!(
+        "the project uses wasm-bindgen {project} but cargo-leptos was built with wasm-bindgen {cli}, \
+         which can generate broken bindings. Pin the project to wasm-bindgen = \"={cli}\" or install \
+         a cargo-leptos built with {project}."
+    );
+    if proj.lib.strict_wasm_bindgen {
+        bail!("Front {msg}");
+    }
+    // only once in watch mode
+    if !WARNED.swap(true, Ordering::Relaxed) {
+        log::warn!("Front {msg}");
+    }
+    Ok(())
+}
+
+/// the size report is informative, a failure doesn't fail the build
+fn size_report(proj: &Project, unoptimized: Option<&Utf8Path>) {
+    let wasm = &proj.lib.wasm_file.dest;
+    if let Err(e) = wasm_size::report(wasm, unoptimized, proj.size_report) {
+        log::warn!("Front could not report the wasm size: {e:#}");
+    }
+}
+
+/// snips the unoptimized wasm, when configured, before optimizing it so that
+/// wasm-opt removes the code that became unreferenced
+async fn snip_and_optimize(
+    proj: &Project,
+    unoptimized: &Utf8Path,
+    interrupt: broadcast::Receiver<()>,
+) -> Result<bool> {
+    let dest = &proj.lib.wasm_file.dest;
+    let snipped = unoptimized.with_extension("snipped.wasm");
+    let from = if proj.lib.wasm_snip {
+        let snipped_ok = snip(
+            unoptimized,
+            &snipped,
+            &proj.lib.wasm_snip_patterns,
+            interrupt.resubscribe(),
+        )
+        .await;
+        if !snipped_ok? {
+            return Ok(false);
+        }
+        snipped.as_path()
+    } else {
+        unoptimized
+    };
+
+    let res = if proj.lib.wasm_opt {
+        optimize(
+            from,
+            dest,
+            &proj.lib.wasm_opt_args(),
+            proj.lib.wasm_opt_version.as_deref(),
+            proj.lib.wasm_opt_required,
+            interrupt,
+        )
+        .await
+    } else {
+        log::info!("Front skipping wasm-opt, the release wasm is not size optimized");
+        fs::copy(from, dest).await.map(|_| true)
+    };
+    if snipped.exists() {
+        fs::remove_file(&snipped).await?;
+    }
+    res
+}
+
+async fn snip(
+    from: &Utf8Path,
+    to: &Utf8Path,
+    patterns: &[String],
+    interrupt: broadcast::Receiver<()>,
+) -> Result<bool> {
+    log::info!("Front snipping the panicking and formatting code with wasm-snip");
+    let wasm_snip = which::which("wasm-snip")
+        .context("wasm-snip was not found on the PATH. Install it with: cargo install wasm-snip")?;
 
-    let args = [file.as_str(), "-Os", "-o", file.as_str()];
+    let mut args = vec![
+        from.as_str(),
+        "-o",
+        to.as_str(),
+        "--snip-rust-fmt-code",
+        "--snip-rust-panicking-code",
+    ];
+    for pattern in patterns {
+        args.extend(["-p", pattern.as_str()]);
+    }
+    log::trace!(
+        "Front running {}",
+        GRAY.paint(format!("wasm-snip {}", args.join(" ")))
+    );
+    let process = Command::new(wasm_snip)
+        .args(&args)
+        .spawn()
+        .context("Could not spawn command")?;
+    wait_interruptible("wasm-snip", process, interrupt).await
+}
+
+async fn optimize(
+    from: &Utf8Path,
+    to: &Utf8Path,
+    opt_args: &[String],
+    version: Option<&str>,
+    required: bool,
+    interrupt: broadcast::Receiver<()>,
+) -> Result<bool> {
+    log::info!(
+        "Front optimizing the wasm with wasm-opt {}",
+        opt_args.join(" ")
+    );
+    let wasm_opt = match Exe::WasmOpt.get_version(version).await {
+        Ok(wasm_opt) => wasm_opt,
+        Err(e) if !required => {
+            log::warn!(
+                "Front wasm-opt is unavailable, shipping the unoptimized release wasm which is \
+                 considerably larger and slower to load. {e:#}"
+            );
+            fs::copy(from, to).await?;
+            return Ok(true);
+        }
+        Err(e) => return Err(e).dot(),
+    };
+
+    let mut args = vec![from.as_str()];
+    args.extend(opt_args.iter().map(String::as_str));
+    args.extend(["-o", to.as_str()]);
+    log::trace!(
+        "Front running {}",
+        GRAY.paint(format!("wasm-opt {}", args.join(" ")))
+    );
     let process = Command::new(wasm_opt)
         .args(args)
         .spawn()
@@ -0,0 +1,81 @@
+use crate::config::Project;
+use crate::ext::anyhow::{Context, Result};
+use crate::ext::fs;
+use crate::logger::GRAY;
+use camino::{Utf8Path, Utf8PathBuf};
+
+/// Renames the js, wasm and css files of the site to <name>.<hash>.<ext>, where the hash
+/// is taken from the content, and writes the hashes to the hash file read by the server.
+pub async fn hash_files(proj: &Project) -> Result<()> {
+    let Some(hash_file) = &proj.hash_file else {
+        return Ok(());
+    };
+    let mut files = vec![
+        ("js", &proj.lib.js_file.dest),
+        ("wasm", &proj.lib.wasm_file.dest),
+    ];
+    if let Some(style) = &proj.style {
+        files.push(("css", &style.file.dest));
+    }
+
+    let mut content = String::new();
+    for (kind, file) in files {
+        if let Some(hash) = hash_file_name(file)
+            .await
+            .context(format!("hash {file:?}"))?
+        {
+            content.push_str(&format!("{kind}: {hash}\n"));
+        }
+    }
+    fs::write(hash_file, content).await?;
+    log::debug!("Hash files written to {}", GRAY.paint(hash_file.as_str()));
+    Ok(())
+}
+
+/// returns the hash of the file, which is None if the file was never built.
+pub async fn hash_file_name(file: &Utf8Path) -> Result<Option<String>> {
+    let dir = file.parent().unwrap_or(Utf8Path::new(""));
+    let (Some(stem), Some(ext)) = (file.file_stem(), file.extension()) else {
+        return Ok(None);
+    };
+
+    if !file.exists() {
+        // unchanged since the last build, so the hashed file is up to date
+        return Ok(hashed_files(dir, stem, ext)
+            .await?
+            .into_iter()
+            .next()
+            .map(|(hash, _)| hash));
+    }
+
+    let hash = format!("{:x}", seahash::hash(&fs::read(file).await?));
+    for (old_hash, old_file) in hashed_files(dir, stem, ext).await? {
+        if old_hash != hash {
+            log::trace!("Hash removing stale {}", GRAY.paint(old_file.as_str()));
+            fs::remove_file(&old_file).await?;
+        }
+    }
+    let hashed = dir.join(format!("{stem}.{hash}.{ext}"));
+    fs::rename(file, &hashed).await?;
+    Ok(Some(hash))
+}
+
+/// the files in the dir named <stem>.<hash>.<ext>
+async fn hashed_files(dir: &Utf8Path, stem: &str, ext: &str) -> Result<Vec<(String, Utf8PathBuf)>> {
+    let mut found = Vec::new();
+    let mut entries = fs::read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let hash = name
+            .strip_prefix(stem)
+            .and_then(|s| s.strip_prefix('.'))
+            .and_then(|s| s.strip_suffix(ext))
+            .and_then(|s| s.strip_suffix('.'));
+        if let Some(hash) = hash {
+            if !hash.is_empty() && hash.chars().all(|c| c.is_ascii_hexdigit()) {
+                found.push((hash.to_string(), dir.join(&name)));
+            }
+        }
+    }
+    Ok(found)
+}
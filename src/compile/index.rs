@@ -0,0 +1,84 @@
+use super::manifest::site_file;
+use crate::config::Project;
+use crate::ext::anyhow::{ensure, Context, Result};
+use crate::ext::fs;
+use crate::logger::GRAY;
+
+/// the placeholder of the index file replaced with the leptos head elements
+pub const HEAD_PLACEHOLDER: &str = "<!-- leptos-head -->";
+
+/// Writes the index file template to the site index.html, with the elements loading
+/// the front, and the reload script in watch mode, injected at the head placeholder
+pub async fn index(proj: &Project) -> Result<()> {
+    let Some(index) = &proj.index_file else {
+        return Ok(());
+    };
+    let template = fs::read_to_string(&index.source).await?;
+    let hashes = match &proj.hash_file {
+        Some(hash_file) => fs::read_to_string(hash_file).await?,
+        None => String::new(),
+    };
+    let html = render(proj, &template, &hashes).context(format!("index-file {}", index.source))?;
+    if proj
+        .site
+        .updated_with(&index.as_site_file(), html.as_bytes())
+        .await?
+    {
+        log::debug!("Index written to {}", GRAY.paint(index.dest.as_str()));
+    }
+    Ok(())
+}
+
+/// the template with the head elements injected at the placeholder
+pub fn render(proj: &Project, template: &str, hashes: &str) -> Result<String> {
+    ensure!(
+        template.matches(HEAD_PLACEHOLDER).count() == 1,
+        "The index file must contain the {HEAD_PLACEHOLDER} placeholder exactly once"
+    );
+    let js = site_file(hashes, "js", &proj.lib.js_file.site);
+    let wasm = site_file(hashes, "wasm", &proj.lib.wasm_file.site);
+
+    let mut head = vec![
+        format!(r#"<link rel="modulepreload" href="/{js}">"#),
+        format!(
+            r#"<link rel="preload" href="/{wasm}" as="fetch" type="application/wasm" crossorigin="">"#
+        ),
+    ];
+    if let Some(style) = &proj.style {
+        let css = site_file(hashes, "css", &style.file.site);
+        head.push(format!(
+            r#"<link id="leptos" rel="stylesheet" href="/{css}">"#
+        ));
+    }
+    head.push(format!(
+        r#"<script type="module">import init, {{ hydrate }} from '/{js}'; init('/{wasm}').then(hydrate);</script>"#
+    ));
+    if proj.watch {
+        head.push(reload_script(proj));
+    }
+    Ok(template.replace(HEAD_PLACEHOLDER, &head.join("\n")))
+}
+
+/// connects to the reload websocket, see the reload service for the messages.
+/// The scheme follows the page, as an https page cannot open a ws:// websocket
+fn reload_script(proj: &Project) -> String {
+    let port = proj.site.reload.port();
+    let path = &proj.site.reload_path;
+    format!(
+        r#"<script>
+(function () {{
+  const protocol = window.location.protocol === "https:" ? "wss" : "ws";
+  const ws = new WebSocket(`${{protocol}}://${{window.location.hostname}}:{port}{path}`);
+  ws.onmessage = (ev) => {{
+    const msg = JSON.parse(ev.data);
+    if (msg.all) window.location.reload();
+    if (msg.css) {{
+      const link = document.getElementById("leptos");
+      if (link) link.href = `/${{msg.css}}?version=${{Date.now()}}`;
+    }}
+  }};
+  ws.onclose = () => console.warn("Live-reload stopped. Manual reload necessary.");
+}})();
+</script>"#
+    )
+}
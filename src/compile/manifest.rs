@@ -0,0 +1,103 @@
+use crate::config::Project;
+use crate::ext::anyhow::{Context, Result};
+use crate::ext::fs;
+use crate::logger::GRAY;
+use camino::{Utf8Path, Utf8PathBuf};
+use serde::Serialize;
+
+/// the manifest written in the site root after a build
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// increased on any breaking change of the manifest structure
+const MANIFEST_VERSION: u32 = 1;
+
+/// Describes the output of a build, for deployment tooling
+#[derive(Debug, Serialize)]
+pub struct Manifest {
+    pub version: u32,
+    pub release: bool,
+    pub site_root: String,
+    pub site_pkg_dir: String,
+    pub lib_features: Vec<String>,
+    pub bin_features: Vec<String>,
+    pub files: Vec<ManifestFile>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ManifestFile {
+    /// js, wasm or css
+    pub kind: &'static str,
+    /// the path relative to the site root, including the hash when the files are hashed
+    pub file: String,
+    /// in bytes
+    pub size: u64,
+}
+
+impl Manifest {
+    /// the hashes are the content of the hash file, if any. Files not found are left out.
+    pub fn new(proj: &Project, hashes: &str) -> Self {
+        let mut files = vec![
+            ("js", &proj.lib.js_file.site),
+            ("wasm", &proj.lib.wasm_file.site),
+        ];
+        if let Some(style) = &proj.style {
+            files.push(("css", &style.file.site));
+        }
+        let files = files
+            .into_iter()
+            .filter_map(|(kind, site)| {
+                let file = site_file(hashes, kind, site);
+                let meta = std::fs::metadata(proj.site.root_dir.join(&file)).ok()?;
+                Some(ManifestFile {
+                    kind,
+                    file: file.to_string(),
+                    size: meta.len(),
+                })
+            })
+            .collect();
+
+        Self {
+            version: MANIFEST_VERSION,
+            release: proj.release,
+            site_root: proj.site.root_dir.to_string(),
+            site_pkg_dir: proj.site.pkg_dir.to_string(),
+            lib_features: proj.lib.features.clone(),
+            bin_features: proj
+                .bin
+                .as_ref()
+                .map(|bin| bin.features.clone())
+                .unwrap_or_default(),
+            files,
+        }
+    }
+}
+
+/// the site file of the given kind (js, wasm or css), hashed when the hash file content has its hash
+pub fn site_file(hashes: &str, kind: &str, site: &Utf8Path) -> Utf8PathBuf {
+    let hash = hashes
+        .lines()
+        .find_map(|line| line.strip_prefix(kind)?.strip_prefix(": "));
+    hashed_site_file(site, hash)
+}
+
+/// the site file named <stem>.<hash>.<ext> when hashed
+pub fn hashed_site_file(site: &Utf8Path, hash: Option<&str>) -> Utf8PathBuf {
+    match (hash, site.extension()) {
+        (Some(hash), Some(ext)) => site.with_extension(format!("{hash}.{ext}")),
+        _ => site.to_path_buf(),
+    }
+}
+
+/// Writes the manifest of the build in the site root
+pub async fn manifest(proj: &Project) -> Result<()> {
+    let hashes = match &proj.hash_file {
+        Some(hash_file) => fs::read_to_string(hash_file).await?,
+        None => String::new(),
+    };
+    let manifest = Manifest::new(proj, &hashes);
+    let file = proj.site.root_dir.join(MANIFEST_FILE);
+    let json = serde_json::to_string_pretty(&manifest).context("serialize the build manifest")?;
+    fs::write(&file, json).await?;
+    log::debug!("Manifest written to {}", GRAY.paint(file.as_str()));
+    Ok(())
+}
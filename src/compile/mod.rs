@@ -1,13 +1,27 @@
+mod asset_pipeline;
 mod assets;
 mod change;
+mod dwarf;
 mod front;
+mod hash;
+mod index;
+mod manifest;
+mod precompress;
+mod sass;
 mod server;
 mod style;
+mod tailwind;
 #[cfg(test)]
 mod tests;
+mod wasm_size;
 
 pub use assets::assets;
 pub use change::{Change, ChangeSet};
-pub use front::{front, front_cargo_process};
-pub use server::{server, server_cargo_process};
+pub use front::{build_cargo_front_cmd, check_wasm_target, front, front_cargo_process};
+pub use hash::hash_files;
+pub use index::index;
+pub use manifest::manifest;
+pub use precompress::{is_compressible, precompress};
+pub use sass::SassError;
+pub use server::{build_cargo_server_cmd, server, server_cargo_process};
 pub use style::style;
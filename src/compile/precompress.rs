@@ -0,0 +1,76 @@
+use std::{collections::VecDeque, fs::File, io::Write};
+
+use crate::config::Project;
+use crate::ext::anyhow::{Context, Result};
+use crate::logger::GRAY;
+use camino::{Utf8Path, Utf8PathBuf};
+use flate2::{write::GzEncoder, Compression};
+
+/// Text based formats that benefit from compression.
+/// Others (png, jpg, woff2...) are already compressed.
+const COMPRESSIBLE: [&str; 10] = [
+    "wasm", "js", "mjs", "css", "html", "svg", "json", "xml", "txt", "map",
+];
+
+/// Writes a brotli (.br) and a gzip (.gz) compressed copy next to each compressible
+/// file of the site, for servers and CDNs serving precompressed content.
+pub async fn precompress(proj: &Project) -> Result<()> {
+    let dir = proj.site.root_dir.clone();
+    let min_size = proj.precompress_min_size;
+    let count = tokio::task::spawn_blocking(move || precompress_dir(&dir, min_size))
+        .await?
+        .context(format!("precompress {:?}", proj.site.root_dir))?;
+    log::info!("Precompress finished for {count} files");
+    Ok(())
+}
+
+/// returns the number of files compressed
+pub fn precompress_dir(dir: &Utf8Path, min_size: u64) -> Result<usize> {
+    let mut count = 0;
+    let mut dirs = VecDeque::from([dir.to_path_buf()]);
+
+    while let Some(dir) = dirs.pop_front() {
+        for entry in dir.read_dir_utf8()? {
+            let entry = entry?;
+            let path = entry.path().to_path_buf();
+            let meta = entry.metadata()?;
+            if meta.is_dir() {
+                dirs.push_back(path);
+            } else if is_compressible(&path) && meta.len() >= min_size {
+                compress(&path)?;
+                count += 1;
+            }
+        }
+    }
+    Ok(count)
+}
+
+pub fn is_compressible(path: &Utf8Path) -> bool {
+    path.extension()
+        .map(|ext| COMPRESSIBLE.contains(&ext.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+fn compress(path: &Utf8Path) -> Result<()> {
+    let data = std::fs::read(path).context(format!("read {path:?}"))?;
+    log::debug!("Precompress {}", GRAY.paint(path.as_str()));
+
+    let gz = sibling(path, "gz");
+    let mut encoder = GzEncoder::new(create(&gz)?, Compression::best());
+    encoder.write_all(&data)?;
+    encoder.finish().context(format!("write {gz:?}"))?;
+
+    let br = sibling(path, "br");
+    let mut encoder = brotli::CompressorWriter::new(create(&br)?, 4096, 11, 22);
+    encoder.write_all(&data).context(format!("write {br:?}"))?;
+    encoder.flush()?;
+    Ok(())
+}
+
+fn sibling(path: &Utf8Path, ext: &str) -> Utf8PathBuf {
+    Utf8PathBuf::from(format!("{path}.{ext}"))
+}
+
+fn create(path: &Utf8Path) -> Result<File> {
+    File::create(path).context(format!("create {path:?}"))
+}
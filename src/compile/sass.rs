@@ -0,0 +1,69 @@
+use camino::Utf8PathBuf;
+use regex::Regex;
+use std::fmt::{self, Display};
+
+lazy_static::lazy_static! {
+    /// a stack trace line: "  style/_theme.scss 3:10  @use"
+    static ref TRACE_LINE: Regex = Regex::new(r"^\s*(\S.*?) (\d+):(\d+)(\s.*)?$").unwrap();
+}
+
+/// A dart-sass compile error, with the location of the faulty line when sass gives it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SassError {
+    pub message: String,
+    pub file: Option<Utf8PathBuf>,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl SassError {
+    /// parses the stderr of dart-sass:
+    ///
+    /// ```text
+    /// Error: Undefined variable.
+    ///   ╷
+    /// 3 │   color: $red;
+    ///   │          ^^^^
+    ///   ╵
+    ///   style/_theme.scss 3:10  @use
+    ///   style/main.scss 1:1     root stylesheet
+    /// ```
+    ///
+    /// The first line of the stack trace is the faulty one. An unknown output is kept as the message.
+    pub fn parse(stderr: &str) -> Self {
+        let mut lines = stderr.lines();
+        let Some(message) = lines.find_map(|line| line.strip_prefix("Error: ")) else {
+            return Self {
+                message: stderr.trim().to_string(),
+                file: None,
+                line: 0,
+                column: 0,
+            };
+        };
+        // the trace comes after the code snippet
+        let trace = lines.skip_while(|line| !is_snippet_end(line)).skip(1);
+        let location = trace.filter_map(|line| TRACE_LINE.captures(line)).next();
+        Self {
+            message: message.trim().to_string(),
+            file: location.as_ref().map(|c| c[1].into()),
+            line: location.as_ref().map_or(0, |c| c[2].parse().unwrap_or(0)),
+            column: location.as_ref().map_or(0, |c| c[3].parse().unwrap_or(0)),
+        }
+    }
+}
+
+/// the closing line of the snippet, with or without --no-unicode
+fn is_snippet_end(line: &str) -> bool {
+    matches!(line.trim(), "╵" | "'")
+}
+
+impl Display for SassError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.file {
+            Some(file) => write!(f, "{file}:{}:{}: {}", self.line, self.column, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for SassError {}
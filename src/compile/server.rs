@@ -1,11 +1,14 @@
 use std::sync::Arc;
 
-use super::ChangeSet;
+use super::{
+    front::{encoded_rustflags, first_build_hint, jobs_arg},
+    ChangeSet,
+};
 use crate::{
     config::Project,
     ext::anyhow::{Context, Result},
     ext::sync::wait_interruptible,
-    logger::GRAY,
+    logger::{self, Event, GRAY},
     signal::{Interrupt, Outcome, Product},
 };
 use tokio::{
@@ -18,27 +21,34 @@ pub async fn server(proj: &Arc<Project>, changes: &ChangeSet) -> JoinHandle<Resu
     let changes = changes.clone();
 
     tokio::spawn(async move {
-        if !changes.need_server_build() {
-            return Ok(Outcome::Success(Product::None));
-        }
+        let bin = match &proj.bin {
+            Some(bin) if changes.need_server_build() => bin,
+            _ => return Ok(Outcome::Success(Product::None)),
+        };
 
+        log::info!(
+            "Cargo compiling the server{}",
+            first_build_hint(&bin.exe_file)
+        );
         let (envs, line, process) = server_cargo_process("build", &proj)?;
 
         match wait_interruptible("Cargo", process, Interrupt::subscribe_any()).await? {
             true => {
                 log::debug!("Cargo envs: {}", GRAY.paint(envs));
                 log::info!("Cargo finished {}", GRAY.paint(line));
+                logger::emit(Event::ServerCompiled);
 
                 let changed = proj
                     .site
-                    .did_external_file_change(&proj.bin.exe_file)
+                    .did_external_file_change(&bin.exe_file)
                     .await
                     .dot()?;
                 if changed {
                     log::debug!("Cargo server bin changed");
                     Ok(Outcome::Success(Product::Server))
                 } else {
-                    log::debug!("Cargo server bin unchanged");
+                    // keeps the running server (and its in-memory state) alive
+                    log::info!("Cargo server bin unchanged, skipping the server restart");
                     Ok(Outcome::Success(Product::None))
                 }
             }
@@ -58,26 +68,39 @@ pub fn build_cargo_server_cmd(
     proj: &Project,
     command: &mut Command,
 ) -> (String, String) {
-    let mut args = vec![
-        cmd.to_string(),
-        format!("--package={}", proj.bin.name.as_str()),
-        format!("--bin={}", proj.bin.target),
-        "--target-dir=target/server".to_string(),
-    ];
+    let bin = proj
+        .bin
+        .as_ref()
+        .expect("csr-only projects have no server to build");
+    let mut args = cmd.split(' ').map(str::to_string).collect::<Vec<_>>();
+    args.extend([
+        format!("--package={}", bin.name.as_str()),
+        format!("--bin={}", bin.target),
+        format!("--target-dir={}", bin.target_dir),
+    ]);
 
-    if !proj.bin.default_features {
+    if let Some(triple) = &bin.target_triple {
+        args.push(format!("--target={triple}"));
+    }
+
+    if !bin.default_features {
         args.push("--no-default-features".to_string());
     }
 
-    if !proj.bin.features.is_empty() {
-        args.push(format!("--features={}", proj.bin.features.join(",")));
+    if !bin.features.is_empty() {
+        args.push(format!("--features={}", bin.features.join(",")));
     }
 
     if proj.release {
         args.push("--release".to_string());
     }
+    args.extend(jobs_arg(cmd, proj));
+    args.extend(bin.cargo_args.iter().cloned());
 
-    let envs = proj.to_envs();
+    let mut envs = proj.to_envs();
+    if let Some(flags) = encoded_rustflags(&bin.rustflags) {
+        envs.push(("CARGO_ENCODED_RUSTFLAGS", flags));
+    }
 
     let envs_str = envs
         .iter()
@@ -1,22 +1,25 @@
-use std::sync::Arc;
+use std::{process::Stdio, sync::Arc};
 
-use super::ChangeSet;
+use super::{sass::SassError, tailwind::compile_tailwind, ChangeSet};
 use crate::{
-    config::{Project, StyleConfig},
+    config::{CssProcessor, Project, StyleConfig},
     ext::exe::Exe,
     ext::{
         anyhow::{anyhow, bail, Context, Result},
         PathBufExt,
     },
     fs,
-    logger::GRAY,
-    service::site::SourcedSiteFile,
+    logger::{self, Event, GRAY},
+    service::site::{SiteFile, SourcedSiteFile},
     signal::{Outcome, Product},
 };
+use camino::Utf8Path;
 use lightningcss::{
+    bundler::{Bundler, FileProvider},
     stylesheet::{MinifyOptions, ParserOptions, PrinterOptions, StyleSheet},
     targets::Browsers,
 };
+use parcel_sourcemap::SourceMap;
 use tokio::process::Command;
 use tokio::task::JoinHandle;
 
@@ -25,11 +28,20 @@ pub async fn style(proj: &Arc<Project>, changes: &ChangeSet) -> JoinHandle<Resul
     let proj = proj.clone();
 
     tokio::spawn(async move {
-        if !changes.need_style_build(true, false) {
+        let css_in_source = proj
+            .style
+            .as_ref()
+            .map(|style| style.tailwind.is_some())
+            .unwrap_or(false);
+        if !changes.need_style_build(true, css_in_source) {
             log::debug!("Style no build needed {changes:?}");
             return Ok(Outcome::Success(Product::None));
         }
-        Ok(Outcome::Success(build(&proj).await?))
+        let product = build(&proj).await?;
+        if proj.style.is_some() {
+            logger::emit(Event::StyleDone);
+        }
+        Ok(Outcome::Success(product))
     })
 }
 
@@ -43,43 +55,165 @@ async fn build(proj: &Project) -> Result<Product> {
         .dot()?;
 
     log::debug!("Style found: {}", &style.file);
+    log::info!("Style compiling {}", GRAY.paint(style.file.source.as_str()));
+
+    if style.tailwind.is_some() {
+        return compile_to_tmp_and_process(proj, style).await;
+    }
+    if !style.additional_files.is_empty() {
+        return bundle_to_tmp_and_process(proj, style).await;
+    }
 
     match style.file.source.extension() {
-        Some("sass") | Some("scss") => compile_sass(&style.file, proj.release)
+        Some("sass") | Some("scss") => compile_to_tmp_and_process(proj, style).await,
+        Some("css") if style.processor == CssProcessor::Lightningcss => bundle_css(proj, style)
             .await
-            .context(format!("compile sass/scss: {}", &style.file))?,
-        Some("css") => {
-            fs::copy(&style.file.source, &style.file.dest).await.dot()?;
-        }
+            .context(format!("bundle css {}", &style.file)),
+        Some("css") => process_css(proj, style, &style.file.source)
+            .await
+            .context(format!("process css {}", &style.file)),
         _ => bail!("Not a css/sass/scss style file: {}", &style.file),
-    };
+    }
+}
+
+async fn compile_to_tmp_and_process(proj: &Project, style: &StyleConfig) -> Result<Product> {
+    // the compiled css is an intermediate, keep it out of the site
+    fs::create_dir_all(&proj.tmp_dir).await.dot()?;
+    let css_file = proj
+        .tmp_dir
+        .join(&proj.lib.output_name)
+        .with_extension("css");
+    let res = compile_and_process(proj, style, &css_file).await;
+    remove_intermediates(&css_file).await?;
+    res
+}
 
-    process_css(proj, &style)
+async fn compile_and_process(
+    proj: &Project,
+    style: &StyleConfig,
+    css_file: &Utf8Path,
+) -> Result<Product> {
+    if let Some(tailwind) = &style.tailwind {
+        compile_tailwind(tailwind, css_file, proj.release)
+            .await
+            .context(format!("compile tailwind: {}", &style.file))?;
+    } else {
+        compile_sass(
+            style,
+            &style.file.source,
+            css_file,
+            source_maps(proj, style),
+        )
+        .await
+        .context(format!("compile sass/scss: {}", &style.file))?;
+    }
+    process_css(proj, style, css_file)
         .await
         .context(format!("process css {}", &style.file))
 }
 
-async fn compile_sass(style_file: &SourcedSiteFile, optimise: bool) -> Result<()> {
-    let mut args = vec![style_file.source.as_str(), style_file.dest.as_str()];
-    optimise.then(|| args.push("--no-source-map"));
+/// compiles each style file to css and concatenates them in the declared order
+async fn bundle_to_tmp_and_process(proj: &Project, style: &StyleConfig) -> Result<Product> {
+    fs::create_dir_all(&proj.tmp_dir).await.dot()?;
+    let css_file = proj
+        .tmp_dir
+        .join(&proj.lib.output_name)
+        .with_extension("css");
+    let res = bundle_and_process(proj, style, &css_file).await;
+    remove_intermediates(&css_file).await?;
+    res
+}
+
+async fn bundle_and_process(
+    proj: &Project,
+    style: &StyleConfig,
+    css_file: &Utf8Path,
+) -> Result<Product> {
+    let mut bundle = String::new();
+    for source in style.sources() {
+        let css = match source.extension() {
+            Some("sass") | Some("scss") => {
+                // the source maps of the parts would not match the bundle
+                compile_sass(style, source, css_file, false)
+                    .await
+                    .context(format!("compile sass/scss: {source}"))?;
+                fs::read_to_string(css_file).await?
+            }
+            Some("css") if style.processor == CssProcessor::Lightningcss => {
+                bundle_imports(source).context(format!("bundle css {source}"))?
+            }
+            Some("css") => fs::read_to_string(source).await?,
+            _ => bail!("Not a css/sass/scss style file: {source}"),
+        };
+        bundle.push_str(&css);
+        bundle.push('\n');
+    }
+    fs::write(css_file, bundle).await?;
+    process_css(proj, style, css_file)
+        .await
+        .context(format!("process css {}", &style.file))
+}
+
+/// the css file with its imports inlined
+fn bundle_imports(css_file: &Utf8Path) -> Result<String> {
+    let fs = FileProvider::new();
+    let mut bundler = Bundler::new(&fs, None, ParserOptions::default());
+    let stylesheet = bundler
+        .bundle(css_file.as_std_path())
+        .map_err(|e| anyhow!("{e}"))?;
+    Ok(stylesheet.to_css(PrinterOptions::default())?.code)
+}
+
+async fn remove_intermediates(css_file: &Utf8Path) -> Result<()> {
+    for file in [css_file.to_path_buf(), css_file.with_extension("css.map")] {
+        if file.exists() {
+            fs::remove_file(&file).await.dot()?;
+        }
+    }
+    Ok(())
+}
+
+async fn compile_sass(
+    style: &StyleConfig,
+    style_file: &Utf8Path,
+    css_file: &Utf8Path,
+    source_map: bool,
+) -> Result<()> {
+    let mut args = vec![style_file.as_str(), css_file.as_str()];
+    match source_map {
+        // the embedded map is picked up by Lightning CSS when processing
+        true => args.extend(["--embed-source-map", "--embed-sources"]),
+        false => args.push("--no-source-map"),
+    }
+    for dir in &style.load_paths {
+        args.extend(["--load-path", dir.as_str()]);
+    }
 
-    let exe = Exe::Sass.get().await.dot()?;
+    let exe = Exe::Sass
+        .get_version(style.sass_version.as_deref())
+        .await
+        .dot()?;
 
-    let mut cmd = Command::new(exe).args(&args).spawn()?;
+    // the errors are parsed, instead of printing the output of sass
+    let cmd = Command::new(exe)
+        .args(&args)
+        .stderr(Stdio::piped())
+        .spawn()?;
 
     log::trace!(
         "Style running {}",
         GRAY.paint(format!("sass {}", args.join(" ")))
     );
 
-    cmd.wait()
+    let output = cmd
+        .wait_with_output()
         .await
         .context(format!("sass {}", args.join(" ")))?;
+    if !output.status.success() {
+        return Err(SassError::parse(&String::from_utf8_lossy(&output.stderr)).into());
+    }
 
-    log::trace!(
-        "Style compiled sass {}",
-        GRAY.paint(style_file.dest.to_string())
-    );
+    log::trace!("Style compiled sass {}", GRAY.paint(css_file.to_string()));
     Ok(())
 }
 
@@ -87,13 +221,53 @@ fn browser_lists(query: &str) -> Result<Option<Browsers>> {
     Browsers::from_browserslist([query]).context(format!("Error in browserlist query: {query}"))
 }
 
-async fn process_css(proj: &Project, style: &StyleConfig) -> Result<Product> {
-    let browsers = browser_lists(&style.browserquery).context("leptos.style.browserquery")?;
+async fn process_css(proj: &Project, style: &StyleConfig, css_file: &Utf8Path) -> Result<Product> {
+    let css = fs::read_to_string(css_file).await?;
 
-    let css = fs::read_to_string(&style.file.dest).await?;
+    let options = ParserOptions {
+        filename: css_file.to_string(),
+        ..Default::default()
+    };
+    let stylesheet = StyleSheet::parse(&css, options).map_err(|e| anyhow!("{e}"))?;
 
-    let mut stylesheet =
-        StyleSheet::parse(&css, ParserOptions::default()).map_err(|e| anyhow!("{e}"))?;
+    let mut source_map = source_maps(proj, style).then(|| SourceMap::new("/"));
+    if let Some(source_map) = &mut source_map {
+        // an input source map (i.e. from sass) is chained by the printer instead
+        if stylesheet.source_map(0).is_none() {
+            let index = source_map.add_source(css_file.as_str());
+            source_map
+                .set_source_content(index as usize, &css)
+                .map_err(|e| anyhow!("{e}"))?;
+        }
+    }
+
+    output_css(proj, style, stylesheet, source_map).await
+}
+
+/// bundles the css file with its imports in-process
+async fn bundle_css(proj: &Project, style: &StyleConfig) -> Result<Product> {
+    let fs = FileProvider::new();
+    let mut source_map = source_maps(proj, style).then(|| SourceMap::new("/"));
+    let mut bundler = Bundler::new(&fs, source_map.as_mut(), ParserOptions::default());
+    let stylesheet = bundler
+        .bundle(style.file.source.as_std_path())
+        .map_err(|e| anyhow!("{e}"))?;
+    drop(bundler);
+
+    output_css(proj, style, stylesheet, source_map).await
+}
+
+fn source_maps(proj: &Project, style: &StyleConfig) -> bool {
+    !proj.release || style.source_maps
+}
+
+async fn output_css(
+    proj: &Project,
+    style: &StyleConfig,
+    mut stylesheet: StyleSheet<'_, '_>,
+    mut source_map: Option<SourceMap>,
+) -> Result<Product> {
+    let browsers = browser_lists(&style.browserquery).context("leptos.style.browserquery")?;
 
     if proj.release {
         stylesheet.minify(MinifyOptions::default())?;
@@ -102,12 +276,23 @@ async fn process_css(proj: &Project, style: &StyleConfig) -> Result<Product> {
     let options = PrinterOptions::<'_> {
         targets: browsers,
         minify: proj.release,
+        source_map: source_map.as_mut(),
         ..Default::default()
     };
 
-    let style_output = stylesheet.to_css(options)?;
+    let mut css = stylesheet.to_css(options)?.code;
+
+    let map_file = source_map_file(&style.file);
+    if let Some(source_map) = &mut source_map {
+        let json = source_map.to_json(None).map_err(|e| anyhow!("{e}"))?;
+        proj.site.updated_with(&map_file, json.as_bytes()).await?;
+        let name = map_file.site.file_name().unwrap_or_default();
+        css.push_str(&format!("\n/*# sourceMappingURL={name} */\n"));
+    } else if map_file.dest.exists() {
+        fs::remove_file(&map_file.dest).await.dot()?;
+    }
 
-    let bytes = style_output.code.as_bytes();
+    let bytes = css.as_bytes();
 
     let prod = match proj
         .site
@@ -128,3 +313,10 @@ async fn process_css(proj: &Project, style: &StyleConfig) -> Result<Product> {
     };
     Ok(prod)
 }
+
+fn source_map_file(css_file: &SourcedSiteFile) -> SiteFile {
+    SiteFile {
+        dest: css_file.dest.with_extension("css.map"),
+        site: css_file.site.with_extension("css.map"),
+    }
+}
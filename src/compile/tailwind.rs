@@ -0,0 +1,53 @@
+use crate::{
+    config::TailwindConfig,
+    ext::{
+        anyhow::{bail, Context, Result},
+        exe::Exe,
+    },
+    logger::GRAY,
+};
+use camino::Utf8Path;
+use tokio::process::Command;
+
+pub async fn compile_tailwind(
+    tailwind: &TailwindConfig,
+    css_file: &Utf8Path,
+    minify: bool,
+) -> Result<()> {
+    let mut args = vec![
+        "--input",
+        tailwind.input_file.as_str(),
+        "--output",
+        css_file.as_str(),
+    ];
+    if let Some(config_file) = &tailwind.config_file {
+        args.extend(["--config", config_file.as_str()]);
+    }
+    minify.then(|| args.push("--minify"));
+
+    let exe = Exe::Tailwind.get().await.dot()?;
+
+    log::trace!(
+        "Style running {}",
+        GRAY.paint(format!("tailwindcss {}", args.join(" ")))
+    );
+
+    let output = Command::new(exe)
+        .args(&args)
+        .output()
+        .await
+        .context(format!("tailwindcss {}", args.join(" ")))?;
+
+    if !output.status.success() {
+        bail!(
+            "tailwindcss failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    log::trace!(
+        "Style compiled tailwind {}",
+        GRAY.paint(css_file.to_string())
+    );
+    Ok(())
+}
@@ -1,26 +1,23 @@
-use crate::{compile::front::build_cargo_front_cmd, config::Config};
+use crate::{
+    compile::front::build_cargo_front_cmd,
+    config::{AssetTransform, AssetsConfig, AssetsConflict, Config},
+    ext::PathBufExt,
+};
+use camino::{Utf8Path, Utf8PathBuf};
 use insta::assert_display_snapshot;
+use temp_dir::TempDir;
 use tokio::process::Command;
 
-use super::server::build_cargo_server_cmd;
+use super::{assets::resync, server::build_cargo_server_cmd};
 
 fn release_opts() -> crate::Opts {
     crate::Opts {
         release: true,
-        project: None,
-        verbose: 0,
-        bin_features: Vec::new(),
-        lib_features: Vec::new(),
+        ..Default::default()
     }
 }
 fn dev_opts() -> crate::Opts {
-    crate::Opts {
-        release: false,
-        project: None,
-        verbose: 0,
-        bin_features: Vec::new(),
-        lib_features: Vec::new(),
-    }
+    crate::Opts::default()
 }
 
 #[test]
@@ -37,8 +34,12 @@ fn test_project_dev() {
     LEPTOS_SITE_PKG_DIR=pkg \
     LEPTOS_SITE_ADDR=127.0.0.1:3000 \
     LEPTOS_RELOAD_PORT=3001 \
+    LEPTOS_RELOAD_WS_PATH=/live_reload \
     LEPTOS_LIB_DIR=. \
     LEPTOS_BIN_DIR=. \
+    LEPTOS_BIN_FEATURES=ssr \
+    LEPTOS_LIB_FEATURES=hydrate \
+    LEPTOS_PROFILE=debug \
     LEPTOS_WATCH=ON";
     assert_eq!(ENV_REF, envs);
 
@@ -66,6 +67,82 @@ fn test_project_release() {
     assert_display_snapshot!(cargo, @"cargo build --package=example --lib --target-dir=target/front --target=wasm32-unknown-unknown --no-default-features --features=hydrate --release");
 }
 
+#[test]
+fn test_project_jobs() {
+    let cli = crate::Opts {
+        jobs: Some(2),
+        ..release_opts()
+    };
+    let conf = Config::test_load(cli, "examples", "examples/project/Cargo.toml", false);
+
+    let mut command = Command::new("cargo");
+    let (_, cargo) = build_cargo_server_cmd("build", &conf.projects[0], &mut command);
+    assert!(cargo.ends_with("--release --jobs=2"), "{cargo}");
+
+    let mut command = Command::new("cargo");
+    let (_, cargo) = build_cargo_front_cmd("nextest run", false, &conf.projects[0], &mut command);
+    assert!(cargo.ends_with("--release --build-jobs=2"), "{cargo}");
+}
+
+#[test]
+fn test_project_cross_compiled_server() {
+    let cli = crate::Opts {
+        bin_target_triple: Some("x86_64-pc-windows-gnu".to_string()),
+        ..release_opts()
+    };
+    let conf = Config::test_load(cli, "examples", "examples/project/Cargo.toml", false);
+    let proj = &conf.projects[0];
+
+    let mut command = Command::new("cargo");
+    let (_, cargo) = build_cargo_server_cmd("build", proj, &mut command);
+
+    assert_display_snapshot!(cargo, @"cargo build --package=example --bin=example --target-dir=target/server --target=x86_64-pc-windows-gnu --no-default-features --features=ssr --release");
+    assert_eq!(
+        proj.bin.as_ref().unwrap().exe_file.test_string(),
+        "target/server/x86_64-pc-windows-gnu/release/example"
+    );
+    assert_eq!(proj.bin.as_ref().unwrap().exe_file.extension(), Some("exe"));
+
+    let mut command = Command::new("cargo");
+    let (_, cargo) = build_cargo_front_cmd("build", true, proj, &mut command);
+    assert!(cargo.contains("--target=wasm32-unknown-unknown"), "{cargo}");
+}
+
+#[test]
+fn test_project_cargo_args() {
+    let cli = crate::Opts {
+        offline: true,
+        cargo_args: vec!["--frozen".to_string()],
+        ..release_opts()
+    };
+    let conf = Config::test_load(cli, "examples", "examples/project/Cargo.toml", false);
+    let proj = &conf.projects[0];
+
+    let mut command = Command::new("cargo");
+    let (_, cargo) = build_cargo_server_cmd("build", proj, &mut command);
+    assert!(cargo.ends_with("--release --offline --frozen"), "{cargo}");
+
+    let mut command = Command::new("cargo");
+    let (_, cargo) = build_cargo_front_cmd("build", true, proj, &mut command);
+    assert!(cargo.ends_with("--release --offline --frozen"), "{cargo}");
+}
+
+#[test]
+fn test_project_release_with_dev_env() {
+    let cli = crate::Opts {
+        env: Some(crate::Env::Dev),
+        ..release_opts()
+    };
+    let conf = Config::test_load(cli, "examples", "examples/project/Cargo.toml", false);
+
+    let mut command = Command::new("cargo");
+    let (envs, cargo) = build_cargo_server_cmd("build", &conf.projects[0], &mut command);
+
+    assert!(envs.ends_with("LEPTOS_ENV=DEV"), "{envs}");
+    assert!(envs.contains("LEPTOS_PROFILE=release"), "{envs}");
+    assert!(cargo.ends_with("--release"), "{cargo}");
+}
+
 #[test]
 fn test_workspace_project1() {
     const ENV_REF: &str = if cfg!(windows) {
@@ -75,8 +152,12 @@ fn test_workspace_project1() {
     LEPTOS_SITE_PKG_DIR=pkg \
     LEPTOS_SITE_ADDR=127.0.0.1:3000 \
     LEPTOS_RELOAD_PORT=3001 \
+    LEPTOS_RELOAD_WS_PATH=/live_reload \
     LEPTOS_LIB_DIR=project1\\front \
     LEPTOS_BIN_DIR=project1\\server \
+    LEPTOS_BIN_FEATURES= \
+    LEPTOS_LIB_FEATURES= \
+    LEPTOS_PROFILE=debug \
     LEPTOS_WATCH=ON"
     } else {
         "\
@@ -85,8 +166,12 @@ fn test_workspace_project1() {
     LEPTOS_SITE_PKG_DIR=pkg \
     LEPTOS_SITE_ADDR=127.0.0.1:3000 \
     LEPTOS_RELOAD_PORT=3001 \
+    LEPTOS_RELOAD_WS_PATH=/live_reload \
     LEPTOS_LIB_DIR=project1/front \
     LEPTOS_BIN_DIR=project1/server \
+    LEPTOS_BIN_FEATURES= \
+    LEPTOS_LIB_FEATURES= \
+    LEPTOS_PROFILE=debug \
     LEPTOS_WATCH=ON"
     };
 
@@ -123,3 +208,453 @@ fn test_workspace_project2() {
 
     assert_display_snapshot!(cargo, @"cargo build --package=project2 --lib --target-dir=target/front --target=wasm32-unknown-unknown --no-default-features --features=hydrate");
 }
+
+/// a temp dir, removed when dropped, and its path
+fn tmp_root() -> (TempDir, Utf8PathBuf) {
+    let tmp = TempDir::new().unwrap();
+    let root = Utf8PathBuf::from_path_buf(tmp.path().to_path_buf()).unwrap();
+    (tmp, root)
+}
+
+/// the assets config of the tests, caching the transformed assets in the root
+fn assets_config(root: &Utf8Path, dirs: Vec<Utf8PathBuf>) -> AssetsConfig {
+    AssetsConfig {
+        dirs,
+        on_conflict: AssetsConflict::Warn,
+        copy_concurrency: 4,
+        pipeline: vec![],
+        cache_dir: root.join("cache"),
+        excluded: globset::GlobSet::empty(),
+    }
+}
+
+#[tokio::test]
+async fn test_assets_conflict() {
+    let (_tmp, root) = tmp_root();
+    let (first, second, dest) = (root.join("first"), root.join("second"), root.join("site"));
+    for dir in [&first, &second, &dest] {
+        std::fs::create_dir_all(dir.join("img")).unwrap();
+    }
+    std::fs::write(first.join("img/logo.png"), "first").unwrap();
+    std::fs::write(second.join("img/logo.png"), "second").unwrap();
+
+    let mut assets = assets_config(&root, vec![first, second]);
+
+    resync(&assets, &dest, "pkg".into(), false).await.unwrap();
+    let logo = std::fs::read_to_string(dest.join("img/logo.png")).unwrap();
+    assert_eq!(logo, "second");
+
+    assets.on_conflict = AssetsConflict::Error;
+    let err = resync(&assets, &dest, "pkg".into(), false)
+        .await
+        .unwrap_err();
+    assert!(format!("{err:#}").contains("Assets conflict"), "{err:#}");
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_assets_pipeline() {
+    let (_tmp, root) = tmp_root();
+    let (src, dest) = (root.join("assets"), root.join("site"));
+    for dir in [&src, &dest] {
+        std::fs::create_dir_all(dir).unwrap();
+    }
+    std::fs::write(src.join("upper.txt"), "text").unwrap();
+    std::fs::write(src.join("broken.md"), "text").unwrap();
+    std::fs::write(src.join("plain.css"), "text").unwrap();
+
+    let transform = |glob: &str, cmd: &str| AssetTransform {
+        matcher: globset::Glob::new(glob).unwrap().compile_matcher(),
+        cmd: cmd.to_string(),
+        in_place: false,
+    };
+    let assets = AssetsConfig {
+        pipeline: vec![
+            transform("*.txt", "tr a-z A-Z"),
+            transform("*.md", "non-existing-command"),
+        ],
+        ..assets_config(&root, vec![src])
+    };
+
+    resync(&assets, &dest, "pkg".into(), false).await.unwrap();
+    let read = |file: &str| std::fs::read_to_string(dest.join(file)).unwrap();
+    assert_eq!(read("upper.txt"), "TEXT");
+    assert_eq!(read("broken.md"), "text");
+    assert_eq!(read("plain.css"), "text");
+    assert_eq!(root.join("cache").read_dir().unwrap().count(), 1);
+}
+
+#[test]
+fn test_split_dwarf() {
+    fn custom(name: &str, data: &[u8]) -> Vec<u8> {
+        let mut payload = vec![name.len() as u8];
+        payload.extend(name.as_bytes());
+        payload.extend(data);
+        let mut section = vec![0, payload.len() as u8];
+        section.extend(payload);
+        section
+    }
+    let header = b"\0asm\x01\0\0\0".to_vec();
+    let name = custom("name", &[1, 2, 3]);
+    let wasm = [header.clone(), custom(".debug_info", &[4, 5]), name.clone()].concat();
+
+    let split = super::dwarf::split_dwarf(&wasm, "app.debug.wasm").unwrap();
+
+    let external = custom("external_debug_info", b"\x0eapp.debug.wasm");
+    assert_eq!(split, [header, name, external].concat());
+}
+
+#[test]
+fn test_encoded_rustflags() {
+    use super::front::encoded_rustflags;
+    assert_eq!(encoded_rustflags(&[]), None);
+
+    let flags = ["-C".to_string(), "target-feature=+bulk-memory".to_string()];
+    let encoded = encoded_rustflags(&flags).unwrap();
+    assert!(
+        encoded.ends_with("-C\x1ftarget-feature=+bulk-memory"),
+        "{encoded:?}"
+    );
+}
+
+#[test]
+fn test_wasm_size() {
+    let header = b"\0asm\x01\0\0\0".to_vec();
+    // one imported function, so the code bodies are the functions 1 and 2
+    let import = b"\x02\x07\x01\x01m\x01f\x00\x00".to_vec();
+    let code = b"\x0a\x09\x02\x02\x00\x0b\x04\x00\x01\x01\x0b".to_vec();
+    let names = b"\x01\x0d\x02\x01\x05small\x02\x03big";
+    let name = [b"\x00\x14\x04name".to_vec(), names.to_vec()].concat();
+    let wasm = [header, import, code, name].concat();
+
+    let size = super::wasm_size::WasmSize::parse(&wasm).unwrap();
+
+    assert_eq!(size.total, wasm.len());
+    assert_eq!(
+        size.sections,
+        [
+            ("custom name".to_string(), 22),
+            ("code".to_string(), 11),
+            ("import".to_string(), 9),
+        ]
+    );
+    assert_eq!(
+        size.functions,
+        [("big".to_string(), 5), ("small".to_string(), 3)]
+    );
+}
+
+#[test]
+fn test_precompress() {
+    let dir = TempDir::new().unwrap();
+    let root = Utf8PathBuf::from_path_buf(dir.path().to_path_buf()).unwrap();
+    std::fs::create_dir_all(root.join("pkg")).unwrap();
+    std::fs::write(root.join("pkg/app.wasm"), vec![0u8; 2048]).unwrap();
+    std::fs::write(root.join("pkg/app.js"), "small").unwrap();
+    std::fs::write(root.join("logo.png"), vec![0u8; 2048]).unwrap();
+
+    let count = super::precompress::precompress_dir(&root, 1024).unwrap();
+
+    assert_eq!(count, 1);
+    assert!(root.join("pkg/app.wasm.br").exists());
+    assert!(root.join("pkg/app.wasm.gz").exists());
+    assert!(!root.join("pkg/app.js.gz").exists());
+    assert!(!root.join("logo.png.gz").exists());
+}
+
+#[tokio::test]
+async fn test_hash_file_name() {
+    let dir = TempDir::new().unwrap();
+    let root = Utf8PathBuf::from_path_buf(dir.path().to_path_buf()).unwrap();
+    let js = root.join("app.js");
+    std::fs::write(root.join("app.0123abcd.js"), "stale").unwrap();
+    std::fs::write(root.join("app.debug.js"), "not hashed").unwrap();
+    std::fs::write(&js, "console.log('app')").unwrap();
+
+    let hash = super::hash::hash_file_name(&js).await.unwrap().unwrap();
+
+    assert!(!js.exists());
+    assert!(!root.join("app.0123abcd.js").exists());
+    assert!(root.join("app.debug.js").exists());
+    assert!(root.join(format!("app.{hash}.js")).exists());
+
+    // unchanged files keep their hashed name
+    let unchanged = super::hash::hash_file_name(&js).await.unwrap();
+    assert_eq!(unchanged, Some(hash));
+}
+
+#[tokio::test]
+async fn test_assets_concurrent_copy() {
+    let (_tmp, root) = tmp_root();
+    let (src, dest) = (root.join("assets"), root.join("site"));
+    let mut files = Vec::new();
+    let mut dir = src.clone();
+    for depth in 0..8 {
+        dir = dir.join(format!("level{depth}"));
+        std::fs::create_dir_all(&dir).unwrap();
+        for i in 0..10 {
+            let file = dir.join(format!("img{i}.png"));
+            std::fs::write(&file, format!("{depth}-{i}")).unwrap();
+            files.push(file);
+        }
+    }
+    std::fs::create_dir_all(&dest).unwrap();
+
+    let assets = AssetsConfig {
+        copy_concurrency: 16,
+        ..assets_config(&root, vec![src.clone()])
+    };
+
+    resync(&assets, &dest, "pkg".into(), false).await.unwrap();
+    for file in files {
+        let copied = dest.join(file.strip_prefix(&src).unwrap());
+        assert_eq!(
+            std::fs::read_to_string(copied).unwrap(),
+            std::fs::read_to_string(file).unwrap()
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_assets_excluded() {
+    let (_tmp, root) = tmp_root();
+    let (src, dest) = (root.join("assets"), root.join("site"));
+    for dir in [&src.join("mock"), &dest] {
+        std::fs::create_dir_all(dir).unwrap();
+    }
+    std::fs::write(src.join("mock/users.json"), "[]").unwrap();
+    std::fs::write(src.join("favicon.ico"), "icon").unwrap();
+
+    let mut excluded = globset::GlobSetBuilder::new();
+    excluded.add(globset::Glob::new("mock/**").unwrap());
+    let assets = AssetsConfig {
+        excluded: excluded.build().unwrap(),
+        ..assets_config(&root, vec![src.clone()])
+    };
+
+    resync(&assets, &dest, "pkg".into(), false).await.unwrap();
+    assert!(dest.join("favicon.ico").exists());
+    assert!(!dest.join("mock/users.json").exists());
+    assert!(assets.is_excluded_path(&src.join("mock/users.json")));
+}
+
+#[tokio::test]
+async fn test_assets_leptosignore() {
+    let (_tmp, root) = tmp_root();
+    let (src, dest) = (root.join("assets"), root.join("site"));
+    for dir in [&src.join("design/icons"), &src.join("img"), &dest] {
+        std::fs::create_dir_all(dir).unwrap();
+    }
+    std::fs::write(src.join(".leptosignore"), "# sources\n*.psd\n/design/\n").unwrap();
+    std::fs::write(src.join("design/icons/logo.svg"), "svg").unwrap();
+    std::fs::write(src.join("img/logo.psd"), "psd").unwrap();
+    std::fs::write(src.join("img/logo.png"), "png").unwrap();
+
+    let assets = assets_config(&root, vec![src]);
+
+    resync(&assets, &dest, "pkg".into(), false).await.unwrap();
+    assert!(dest.join("img/logo.png").exists());
+    assert!(!dest.join("img/logo.psd").exists());
+    assert!(!dest.join("design").exists());
+    assert!(!dest.join(".leptosignore").exists());
+}
+
+#[test]
+fn test_manifest() {
+    let conf = Config::test_load(
+        release_opts(),
+        "examples",
+        "examples/project/Cargo.toml",
+        false,
+    );
+    let manifest = super::manifest::Manifest::new(&conf.projects[0], "js: 123\nwasm: 456\n");
+    let json = serde_json::to_string(&manifest).unwrap();
+    assert_display_snapshot!(json, @r###"{"version":1,"release":true,"site_root":"target/site","site_pkg_dir":"pkg","lib_features":["hydrate"],"bin_features":["ssr"],"files":[]}"###);
+
+    let hashed = super::manifest::hashed_site_file("pkg/example.js".into(), Some("123"));
+    assert_eq!(hashed, "pkg/example.123.js");
+}
+
+#[test]
+fn test_index_render() {
+    let conf = Config::test_load(dev_opts(), "examples", "examples/project/Cargo.toml", false);
+    let proj = &conf.projects[0];
+    let hashes = "js: 123\nwasm: 456\ncss: 789\n";
+
+    let html = super::index::render(proj, "<head>\n<!-- leptos-head -->\n</head>", hashes).unwrap();
+    assert_display_snapshot!(html, @r###"
+    <head>
+    <link rel="modulepreload" href="/pkg/example.123.js">
+    <link rel="preload" href="/pkg/example.456.wasm" as="fetch" type="application/wasm" crossorigin="">
+    <link id="leptos" rel="stylesheet" href="/pkg/example.789.css">
+    <script type="module">import init, { hydrate } from '/pkg/example.123.js'; init('/pkg/example.456.wasm').then(hydrate);</script>
+    </head>
+    "###);
+
+    let missing = super::index::render(proj, "<head></head>", hashes).unwrap_err();
+    assert!(missing.to_string().contains("<!-- leptos-head -->"));
+}
+
+#[tokio::test]
+async fn test_assets_incremental_resync() {
+    let (_tmp, root) = tmp_root();
+    let (src, dest) = (root.join("assets"), root.join("site"));
+    for dir in [&src, &dest.join("pkg"), &dest.join("old")] {
+        std::fs::create_dir_all(dir).unwrap();
+    }
+    std::fs::write(src.join("logo.png"), "png").unwrap();
+    std::fs::write(dest.join("pkg/app.wasm"), "wasm").unwrap();
+    std::fs::write(dest.join("old/stale.txt"), "stale").unwrap();
+
+    let assets = assets_config(&root, vec![src]);
+
+    resync(&assets, &dest, "pkg".into(), false).await.unwrap();
+    let modified = || {
+        std::fs::metadata(dest.join("logo.png"))
+            .unwrap()
+            .modified()
+            .unwrap()
+    };
+    let copied = modified();
+    assert!(dest.join("pkg/app.wasm").exists());
+    assert!(!dest.join("old").exists());
+
+    resync(&assets, &dest, "pkg".into(), false).await.unwrap();
+    assert_eq!(modified(), copied);
+
+    resync(&assets, &dest, "pkg".into(), true).await.unwrap();
+    assert!(dest.join("logo.png").exists());
+}
+
+#[tokio::test]
+async fn test_assets_case_rename() {
+    let (_tmp, root) = tmp_root();
+    let (src, dest) = (root.join("assets"), root.join("site"));
+    for dir in [&src, &dest] {
+        std::fs::create_dir_all(dir).unwrap();
+    }
+    std::fs::write(src.join("Logo.png"), "png").unwrap();
+
+    let assets = assets_config(&root, vec![src.clone()]);
+    resync(&assets, &dest, "pkg".into(), false).await.unwrap();
+
+    std::fs::rename(src.join("Logo.png"), src.join("logo.png")).unwrap();
+    resync(&assets, &dest, "pkg".into(), false).await.unwrap();
+    let names = dest
+        .read_dir_utf8()
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string())
+        .collect::<Vec<_>>();
+    // a case-insensitive file system keeps the first name, but not two files
+    let case_sensitive = !src.join("LOGO.png").exists();
+    match case_sensitive {
+        true => assert_eq!(names, ["logo.png"]),
+        false => assert_eq!(names.len(), 1),
+    }
+}
+
+#[tokio::test]
+async fn test_assets_nested_pkg_dir() {
+    let (_tmp, root) = tmp_root();
+    let (src, dest) = (root.join("assets"), root.join("site"));
+    for dir in [&src.join("assets/js"), &dest.join("assets/js")] {
+        std::fs::create_dir_all(dir).unwrap();
+    }
+    std::fs::write(src.join("assets/logo.png"), "png").unwrap();
+    std::fs::write(src.join("assets/js/app.js"), "shadowing").unwrap();
+    std::fs::write(dest.join("assets/js/app.wasm"), "wasm").unwrap();
+
+    let assets = assets_config(&root, vec![src]);
+
+    resync(&assets, &dest, "assets/js".into(), false)
+        .await
+        .unwrap();
+    assert!(dest.join("assets/logo.png").exists());
+    assert!(dest.join("assets/js/app.wasm").exists());
+    assert!(!dest.join("assets/js/app.js").exists());
+
+    // a forced resync cleans the dest first, but for the pkg dir
+    std::fs::write(dest.join("assets/stale.txt"), "stale").unwrap();
+    resync(&assets, &dest, "assets/js".into(), true)
+        .await
+        .unwrap();
+    assert!(dest.join("assets/logo.png").exists());
+    assert!(dest.join("assets/js/app.wasm").exists());
+    assert!(!dest.join("assets/stale.txt").exists());
+}
+
+#[test]
+fn test_change_set_extend() {
+    use super::{Change, ChangeSet};
+    let mut changes = ChangeSet::default();
+    changes.add(Change::LibSource);
+    let mut during_build = ChangeSet::default();
+    during_build.add(Change::LibSource);
+    during_build.add(Change::Style);
+
+    changes.extend(during_build);
+    assert!(changes.need_front_build());
+    assert!(changes.need_style_build(true, false));
+    assert!(!changes.need_server_build());
+}
+
+#[test]
+fn test_sass_error() {
+    use super::SassError;
+
+    let err = SassError::parse(
+        "Error: Undefined variable.
+  ╷
+3 │   color: $red;
+  │          ^^^^
+  ╵
+  style/_theme.scss 3:10  @use
+  style/main.scss 1:1     root stylesheet
+",
+    );
+    assert_eq!(
+        err.to_string(),
+        "style/_theme.scss:3:10: Undefined variable."
+    );
+
+    // with --no-unicode
+    let err = SassError::parse(
+        "Error: expected \";\".
+  ,
+2 | a { b: c }
+  |          ^
+  '
+  style/main.scss 2:10  root stylesheet
+",
+    );
+    assert_eq!(err.file.as_deref(), Some("style/main.scss".into()));
+    assert_eq!((err.line, err.column), (2, 10));
+    assert_eq!(err.message, "expected \";\".");
+
+    let err = SassError::parse("Could not find an option named \"foo\".\n");
+    assert_eq!(err.file, None);
+    assert_eq!(err.to_string(), "Could not find an option named \"foo\".");
+}
+
+#[test]
+fn test_stopped_build_keeps_products() {
+    use crate::signal::{Outcome, Product, ProductSet};
+
+    // the server was built, and its new hash registered, while the front build was stopped
+    let mut built = ProductSet::empty();
+    built.add(vec![
+        Outcome::Success(Product::Server),
+        Outcome::Stopped,
+        Outcome::Success(Product::None),
+    ]);
+    // the redone build finds the server binary unchanged
+    built.add(vec![
+        Outcome::Success(Product::None),
+        Outcome::Success(Product::Front),
+        Outcome::Failed,
+    ]);
+    let set = built.take();
+    assert!(set.contains(&Product::Server));
+    assert!(set.contains(&Product::Front));
+    assert!(built.is_empty());
+}
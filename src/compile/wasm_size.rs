@@ -0,0 +1,246 @@
+use super::dwarf::read_leb128;
+use crate::ext::anyhow::{ensure, Context, Result};
+use crate::logger::GRAY;
+use camino::Utf8Path;
+use std::{cmp::Reverse, collections::HashMap};
+
+/// the number of sections and functions listed in the detailed report
+const TOP: usize = 10;
+
+/// The sizes of the sections and of the function bodies of a wasm module
+#[derive(Debug)]
+pub struct WasmSize {
+    pub total: usize,
+    /// (name, size) largest first
+    pub sections: Vec<(String, usize)>,
+    /// (name, size) largest first. The functions are named from the name section, if any.
+    pub functions: Vec<(String, usize)>,
+}
+
+impl WasmSize {
+    pub fn parse(wasm: &[u8]) -> Result<Self> {
+        ensure!(
+            wasm.len() >= 8 && wasm.starts_with(b"\0asm"),
+            "Not a wasm module"
+        );
+        let mut sections = Vec::new();
+        let mut bodies = Vec::new();
+        let mut names = HashMap::new();
+        let mut imported_funcs = 0;
+        let mut pos = 8;
+
+        while pos < wasm.len() {
+            let start = pos;
+            let id = wasm[pos];
+            pos += 1;
+            let size = read_leb128(wasm, &mut pos)?;
+            let payload = wasm
+                .get(pos..pos + size)
+                .context("Truncated wasm section")?;
+            pos += size;
+
+            let name = match id {
+                0 => {
+                    let name = read_name(payload, &mut 0)?;
+                    if name == "name" {
+                        names = function_names(payload).unwrap_or_default();
+                    }
+                    format!("custom {name}")
+                }
+                2 => {
+                    imported_funcs = imported_functions(payload)?;
+                    "import".to_string()
+                }
+                10 => {
+                    bodies = function_bodies(payload)?;
+                    "code".to_string()
+                }
+                id => SECTIONS.get(id as usize).unwrap_or(&"unknown").to_string(),
+            };
+            sections.push((name, pos - start));
+        }
+
+        let mut functions = bodies
+            .into_iter()
+            .enumerate()
+            .map(|(i, size)| {
+                let index = imported_funcs + i;
+                let name = names.remove(&index).unwrap_or_else(|| format!("code[{i}]"));
+                (name, size)
+            })
+            .collect::<Vec<_>>();
+        sections.sort_by_key(|(_, size)| Reverse(*size));
+        functions.sort_by_key(|(_, size)| Reverse(*size));
+
+        Ok(Self {
+            total: wasm.len(),
+            sections,
+            functions,
+        })
+    }
+}
+
+const SECTIONS: [&str; 13] = [
+    "custom",
+    "type",
+    "import",
+    "function",
+    "table",
+    "memory",
+    "global",
+    "export",
+    "start",
+    "element",
+    "code",
+    "data",
+    "datacount",
+];
+
+/// Logs the size of the wasm, compared to the wasm before wasm-opt when given.
+/// When detailed, the largest sections and functions are logged too.
+pub fn report(wasm: &Utf8Path, unoptimized: Option<&Utf8Path>, detailed: bool) -> Result<()> {
+    let size = WasmSize::parse(&std::fs::read(wasm).context(format!("read {wasm}"))?)?;
+    let before = match unoptimized {
+        Some(file) => Some(WasmSize::parse(
+            &std::fs::read(file).context(format!("read {file}"))?,
+        )?),
+        None => None,
+    };
+
+    match &before {
+        Some(before) => log::info!(
+            "Front wasm is {} ({} before wasm-opt, {:+.0}%)",
+            human(size.total),
+            human(before.total),
+            (size.total as f64 / before.total as f64 - 1.0) * 100.0
+        ),
+        None => log::info!("Front wasm is {}", human(size.total)),
+    }
+    if !detailed {
+        return Ok(());
+    }
+
+    log::info!("Front largest wasm sections:");
+    log_top(&size.sections, size.total);
+
+    // wasm-opt strips the names, the functions of the unoptimized wasm are more telling
+    let (named, when) = match &before {
+        Some(before) if !has_names(&size) => (before, " before wasm-opt"),
+        _ => (&size, ""),
+    };
+    if !named.functions.is_empty() {
+        log::info!("Front largest wasm functions{when}:");
+        log_top(&named.functions, named.total);
+    }
+    Ok(())
+}
+
+fn log_top(entries: &[(String, usize)], total: usize) {
+    for (name, size) in entries.iter().take(TOP) {
+        let line = format!(
+            "{:>10} {:>5.1}%  {name}",
+            human(*size),
+            *size as f64 * 100.0 / total as f64
+        );
+        log::info!("  {}", GRAY.paint(line));
+    }
+}
+
+fn has_names(size: &WasmSize) -> bool {
+    size.functions
+        .iter()
+        .any(|(name, _)| !name.starts_with("code["))
+}
+
+fn human(bytes: usize) -> String {
+    if bytes < 1024 {
+        format!("{bytes} B")
+    } else if bytes < 1024 * 1024 {
+        format!("{:.1} KiB", bytes as f64 / 1024.0)
+    } else {
+        format!("{:.1} MiB", bytes as f64 / 1024.0 / 1024.0)
+    }
+}
+
+fn read_name<'a>(bytes: &'a [u8], pos: &mut usize) -> Result<&'a str> {
+    let len = read_leb128(bytes, pos)?;
+    let name = bytes.get(*pos..*pos + len).context("Truncated wasm name")?;
+    *pos += len;
+    std::str::from_utf8(name).context("Invalid wasm name")
+}
+
+fn imported_functions(payload: &[u8]) -> Result<usize> {
+    let mut pos = 0;
+    let mut funcs = 0;
+    for _ in 0..read_leb128(payload, &mut pos)? {
+        read_name(payload, &mut pos)?;
+        read_name(payload, &mut pos)?;
+        let kind = *payload.get(pos).context("Truncated wasm import")?;
+        pos += 1;
+        match kind {
+            // function type index
+            0 => {
+                read_leb128(payload, &mut pos)?;
+                funcs += 1;
+            }
+            // table: reference type and limits
+            1 => {
+                pos += 1;
+                skip_limits(payload, &mut pos)?;
+            }
+            2 => skip_limits(payload, &mut pos)?,
+            // global: value type and mutability
+            3 => pos += 2,
+            _ => break,
+        }
+    }
+    Ok(funcs)
+}
+
+fn skip_limits(bytes: &[u8], pos: &mut usize) -> Result<()> {
+    let flags = *bytes.get(*pos).context("Truncated wasm limits")?;
+    *pos += 1;
+    read_leb128(bytes, pos)?;
+    if flags & 1 == 1 {
+        read_leb128(bytes, pos)?;
+    }
+    Ok(())
+}
+
+fn function_bodies(payload: &[u8]) -> Result<Vec<usize>> {
+    let mut pos = 0;
+    let mut bodies = Vec::new();
+    for _ in 0..read_leb128(payload, &mut pos)? {
+        let start = pos;
+        let size = read_leb128(payload, &mut pos)?;
+        pos += size;
+        bodies.push(pos - start);
+    }
+    Ok(bodies)
+}
+
+/// the function names from the function subsection of the name section
+fn function_names(payload: &[u8]) -> Result<HashMap<usize, String>> {
+    let mut pos = 0;
+    read_name(payload, &mut pos)?;
+    let mut names = HashMap::new();
+    while pos < payload.len() {
+        let id = payload[pos];
+        pos += 1;
+        let size = read_leb128(payload, &mut pos)?;
+        let sub = payload
+            .get(pos..pos + size)
+            .context("Truncated wasm name subsection")?;
+        pos += size;
+        if id != 1 {
+            continue;
+        }
+        let mut sub_pos = 0;
+        for _ in 0..read_leb128(sub, &mut sub_pos)? {
+            let index = read_leb128(sub, &mut sub_pos)?;
+            let name = read_name(sub, &mut sub_pos)?;
+            names.insert(index, name.to_string());
+        }
+    }
+    Ok(names)
+}
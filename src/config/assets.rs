@@ -1,31 +1,174 @@
-use camino::Utf8PathBuf;
+use std::str::FromStr;
 
-use crate::ext::PathBufExt;
+use camino::{Utf8Path, Utf8PathBuf};
+use globset::{Glob, GlobMatcher, GlobSet, GlobSetBuilder};
+use serde::Deserialize;
+
+use crate::ext::{
+    anyhow::{bail, Context, Error, Result},
+    PathBufExt,
+};
 
 use super::ProjectConfig;
 
 pub struct AssetsConfig {
-    pub dir: Utf8PathBuf,
+    /// the assets dirs, in the order they are mirrored.
+    /// On conflict, files in later dirs override the ones in earlier dirs.
+    pub dirs: Vec<Utf8PathBuf>,
+    pub on_conflict: AssetsConflict,
+    /// how many files are copied at once
+    pub copy_concurrency: usize,
+    /// transformations applied when copying the assets
+    pub pipeline: Vec<AssetTransform>,
+    /// where the transformed assets are cached
+    pub cache_dir: Utf8PathBuf,
+    /// the assets not copied in the current profile (dev-only assets in release and vice versa)
+    pub excluded: GlobSet,
+}
+
+/// a command run over each asset matching the glob
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct AssetTransformConfig {
+    /// matched against the asset path relative to its assets dir
+    pub glob: String,
+    pub cmd: String,
+    /// when set, the cmd is given the path of a copy of the asset to transform,
+    /// otherwise the asset is piped through the cmd (stdin -> stdout)
+    #[serde(default)]
+    pub in_place: bool,
+}
+
+pub struct AssetTransform {
+    pub matcher: GlobMatcher,
+    pub cmd: String,
+    pub in_place: bool,
+}
+
+impl std::fmt::Debug for AssetTransform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AssetTransform")
+            .field("glob", &self.matcher.glob().glob())
+            .field("cmd", &self.cmd)
+            .field("in_place", &self.in_place)
+            .finish()
+    }
+}
+
+/// what to do when several assets are copied to the same site file
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AssetsConflict {
+    /// log a warning and let the last copied file win
+    #[default]
+    Warn,
+    /// fail the assets sync
+    Error,
+}
+
+impl FromStr for AssetsConflict {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "warn" => Ok(Self::Warn),
+            "error" => Ok(Self::Error),
+            _ => bail!(r#"Invalid assets-conflict "{s}", expected "warn" or "error""#),
+        }
+    }
+}
+
+impl AssetsConfig {
+    pub fn resolve(
+        config: &ProjectConfig,
+        target_dir: &Utf8Path,
+        release: bool,
+    ) -> Result<Option<Self>> {
+        if config.assets_dir.is_empty() {
+            return Ok(None);
+        }
+
+        let pipeline = config
+            .asset_pipeline
+            .iter()
+            .map(|transform| {
+                let matcher = Glob::new(&transform.glob)
+                    .context(format!("Invalid asset-pipeline glob {:?}", transform.glob))?
+                    .compile_matcher();
+                Ok(AssetTransform {
+                    matcher,
+                    cmd: transform.cmd.clone(),
+                    in_place: transform.in_place,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let (excluded_key, excluded_globs) = if release {
+            ("assets-dev-only", &config.assets_dev_only)
+        } else {
+            ("assets-release-only", &config.assets_release_only)
+        };
+        let mut excluded = GlobSetBuilder::new();
+        for glob in excluded_globs {
+            excluded.add(Glob::new(glob).context(format!("Invalid {excluded_key} glob {glob:?}"))?);
+        }
+        let excluded = excluded
+            .build()
+            .context(format!("Invalid {excluded_key} globs"))?;
+
+        Ok(Some(Self {
+            dirs: config
+                .assets_dir
+                .iter()
+                .map(|dir| config.config_relative(dir))
+                .collect(),
+            on_conflict: config.assets_conflict,
+            copy_concurrency: match config.assets_copy_concurrency {
+                Some(0) => bail!("assets-copy-concurrency must be at least 1"),
+                Some(n) => n,
+                None => std::thread::available_parallelism().map_or(1, usize::from),
+            },
+            pipeline,
+            cache_dir: target_dir.join("assets-cache"),
+            excluded,
+        }))
+    }
 }
 
 impl AssetsConfig {
-    pub fn resolve(config: &ProjectConfig) -> Option<Self> {
-        let Some(assets_dir) = &config
-            .assets_dir else {
-                return None;
-            };
-
-        Some(Self {
-            // relative to the configuration file
-            dir: config.config_dir.join(assets_dir),
-        })
+    /// the first transform matching the path relative to its assets dir
+    pub fn transform_for(&self, rel: &Utf8Path) -> Option<&AssetTransform> {
+        self.pipeline.iter().find(|t| t.matcher.is_match(rel))
+    }
+
+    /// if the path relative to its assets dir is not copied in the current profile
+    pub fn is_excluded(&self, rel: &Utf8Path) -> bool {
+        self.excluded.is_match(rel)
+    }
+
+    /// if the path is in an assets dir but not copied in the current profile
+    pub fn is_excluded_path(&self, path: &Utf8Path) -> bool {
+        self.dirs
+            .iter()
+            .filter_map(|dir| path.strip_prefix(dir).ok())
+            .any(|rel| self.is_excluded(rel))
     }
 }
 
 impl std::fmt::Debug for AssetsConfig {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("AssetsConfig")
-            .field("dir", &self.dir.test_string())
-            .finish()
+            .field(
+                "dirs",
+                &self
+                    .dirs
+                    .iter()
+                    .map(|p| p.test_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            )
+            .field("on_conflict", &self.on_conflict)
+            .field("pipeline", &self.pipeline)
+            .finish_non_exhaustive()
     }
 }
@@ -1,5 +1,5 @@
-use camino::Utf8PathBuf;
-use cargo_metadata::{Metadata, Target};
+use camino::{Utf8Path, Utf8PathBuf};
+use cargo_metadata::{Metadata, Package, Target};
 
 use crate::{
     ext::{
@@ -9,18 +9,35 @@ use crate::{
     Opts,
 };
 
-use super::{project::ProjectDefinition, ProjectConfig};
+use super::{
+    project::{cargo_args, rustflags, ProjectDefinition},
+    ProjectConfig, TestRunner,
+};
+
+/// the placeholder of the server-run-cmd replaced by the path of the server exe
+pub const SERVER_EXE: &str = "{exe}";
 
 pub struct BinPackage {
     pub name: String,
     pub abs_dir: Utf8PathBuf,
     pub rel_dir: Utf8PathBuf,
+    /// the cargo target dir of the server build
+    pub target_dir: Utf8PathBuf,
     pub exe_file: Utf8PathBuf,
     pub target: String,
+    /// the target triple when cross-compiling
+    pub target_triple: Option<String>,
     pub features: Vec<String>,
     pub default_features: bool,
     /// all source paths, including path dependencies'
     pub src_paths: Vec<Utf8PathBuf>,
+    pub test_runner: TestRunner,
+    /// the rustc flags of the server build, added to the ambient ones
+    pub rustflags: Vec<String>,
+    /// additional arguments of the cargo commands
+    pub cargo_args: Vec<String>,
+    /// the command running the server instead of the exe, with {exe} replaced by its path
+    pub run_cmd: Option<String>,
 }
 
 impl BinPackage {
@@ -70,18 +87,26 @@ impl BinPackage {
         let abs_dir = package.manifest_path.clone().without_last();
         let rel_dir = abs_dir.unbase(&metadata.workspace_root)?;
         let profile = cli.profile();
+        let target_triple = cli
+            .bin_target_triple
+            .clone()
+            .or_else(|| config.bin_target_triple.clone());
+        let target_dir = metadata.rel_target_dir().join("server");
+        let windows = match &target_triple {
+            Some(triple) => triple.contains("windows"),
+            None => cfg!(target_os = "windows"),
+        };
+        if windows {
+            warn_lib_name_collision(&package, &target);
+        }
         let exe_file = {
-            let file_ext = if cfg!(target_os = "windows") {
-                "exe"
-            } else {
-                ""
-            };
-            metadata
-                .rel_target_dir()
-                .join("server")
-                .join(&profile)
-                .join(&name)
-                .with_extension(file_ext)
+            let file_ext = if windows { "exe" } else { "" };
+            // cargo puts the output in a sub dir named after the triple when --target is given
+            let mut dir = target_dir.clone();
+            if let Some(triple) = &target_triple {
+                dir.push(triple);
+            }
+            dir.join(&profile).join(&name).with_extension(file_ext)
         };
 
         let mut src_paths = metadata.src_path_dependencies(&package.id);
@@ -94,22 +119,41 @@ impl BinPackage {
             name,
             abs_dir,
             rel_dir,
+            target_dir,
             exe_file,
             target: target.name.to_string(),
+            target_triple,
             features,
             default_features: config.bin_default_features,
             src_paths,
+            test_runner: config.bin_test_runner.unwrap_or(config.test_runner),
+            rustflags: rustflags(&config.bin_rustflags),
+            cargo_args: cargo_args(&config.bin_cargo_args, cli)?,
+            run_cmd: config.server_run_cmd.clone(),
         })
     }
 }
 
+/// the program and arguments running the server: the run-cmd or the exe itself
+pub fn server_run_args(run_cmd: Option<&str>, exe: &Utf8Path) -> Vec<String> {
+    match run_cmd {
+        Some(cmd) => cmd
+            .split_whitespace()
+            .map(|part| part.replace(SERVER_EXE, exe.as_str()))
+            .collect(),
+        None => vec![exe.to_string()],
+    }
+}
+
 impl std::fmt::Debug for BinPackage {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("BinPackage")
             .field("name", &self.name)
             .field("rel_dir", &self.rel_dir.test_string())
+            .field("target_dir", &self.target_dir.test_string())
             .field("exe_file", &self.exe_file.test_string())
             .field("target", &self.target)
+            .field("target_triple", &self.target_triple)
             .field("features", &self.features)
             .field("default_features", &self.default_features)
             .field(
@@ -121,10 +165,33 @@ impl std::fmt::Debug for BinPackage {
                     .collect::<Vec<_>>()
                     .join(", "),
             )
+            .field("test_runner", &self.test_runner)
+            .field("rustflags", &self.rustflags)
+            .field("cargo_args", &self.cargo_args)
+            .field("run_cmd", &self.run_cmd)
             .finish_non_exhaustive()
     }
 }
 
+/// On Windows, the debug info (.pdb) of a bin and a lib with the same crate name are written to
+/// the same file, see https://github.com/rust-lang/cargo/issues/6313
+fn warn_lib_name_collision(package: &Package, bin: &Target) {
+    let Some(lib) = package.cdylib_target() else {
+        return;
+    };
+    if lib.name.replace('-', "_") == bin.name.replace('-', "_") {
+        log::warn!(
+            "Config the bin target \"{}\" and the lib target \"{}\" of the package {} have the same \
+             crate name, so on Windows cargo writes their debug info to the same .pdb file and one \
+             overwrites the other. Rename one of them, for instance with [[bin]] name = \"{}-server\".",
+            bin.name,
+            lib.name,
+            package.name,
+            bin.name
+        );
+    }
+}
+
 fn many_targets_found(pkg: &str) -> Error {
     anyhow!(
         r#"Several bin targets found for member "{pkg}", please specify which one to use with: [[workspace.metadata.leptos]] bin-target = "name""#
@@ -0,0 +1,76 @@
+use std::fmt::Debug;
+
+use crate::ext::anyhow::{ensure, Context, Result};
+use globset::{Glob, GlobMatcher};
+use serde::Deserialize;
+
+use super::ProjectConfig;
+
+/// the hashed files never change under the same name
+const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+/// revalidated with the etag on each request
+const NO_CACHE: &str = "no-cache";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct CacheControlRule {
+    /// glob of the file paths, relative to the site root
+    pub path: String,
+    /// the Cache-Control header value of the matching files
+    pub value: String,
+}
+
+/// the Cache-Control header values sent by the static server of a csr-only project
+pub struct CacheControl {
+    rules: Vec<(GlobMatcher, String)>,
+}
+
+impl CacheControl {
+    pub fn resolve(config: &ProjectConfig) -> Result<Self> {
+        let mut rules = Vec::new();
+        for rule in &config.cache_control {
+            ensure!(
+                rule.value
+                    .chars()
+                    .all(|c| c == '\t' || (' '..='~').contains(&c)),
+                "Invalid cache-control value {:?}",
+                rule.value
+            );
+            rules.push((glob(&rule.path)?, rule.value.clone()));
+        }
+        if config.hash_files {
+            let hashed = format!(
+                "{}/{}.*.{{js,wasm,css}}",
+                config.site_pkg_dir, config.output_name
+            );
+            rules.push((glob(&hashed)?, IMMUTABLE.to_string()));
+        }
+        Ok(Self { rules })
+    }
+
+    /// the header value of the file, relative to the site root: the first matching rule's or no-cache
+    pub fn value(&self, path: &str) -> &str {
+        self.rules
+            .iter()
+            .find(|(glob, _)| glob.is_match(path))
+            .map_or(NO_CACHE, |(_, value)| value)
+    }
+}
+
+fn glob(path: &str) -> Result<GlobMatcher> {
+    Ok(Glob::new(path)
+        .context(format!("Invalid cache-control path {path:?}"))?
+        .compile_matcher())
+}
+
+impl Debug for CacheControl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(
+                self.rules
+                    .iter()
+                    .map(|(glob, value)| (glob.glob().glob(), value)),
+            )
+            .finish()
+    }
+}
@@ -3,37 +3,78 @@ use crate::{ext::anyhow::Result, logger::GRAY};
 use camino::{Utf8Path, Utf8PathBuf};
 use std::fs;
 
-pub fn find_env_file(directory: &Utf8Path) -> Option<Utf8PathBuf> {
-    let candidate = directory.join(".env");
+/// the env files of the first dir, from the directory up, having any of them:
+/// .env, .env.local then .env.{profile}, in increasing precedence
+pub fn find_env_files(directory: &Utf8Path, profile: &str) -> Vec<Utf8PathBuf> {
+    let files = env_file_names(profile)
+        .iter()
+        .map(|name| directory.join(name))
+        .filter(|candidate| fs::metadata(candidate).is_ok_and(|m| m.is_file()))
+        .collect::<Vec<_>>();
 
-    if let Ok(metadata) = fs::metadata(&candidate) {
-        if metadata.is_file() {
-            return Some(candidate);
-        }
+    if !files.is_empty() {
+        return files;
     }
-
     if let Some(parent) = directory.parent() {
-        find_env_file(parent)
+        find_env_files(parent, profile)
     } else {
-        None
+        Vec::new()
     }
 }
 
+/// the names of the env files, in increasing precedence
+pub fn env_file_names(profile: &str) -> [String; 3] {
+    [
+        ".env".to_string(),
+        ".env.local".to_string(),
+        format!(".env.{profile}"),
+    ]
+}
+
 pub fn overlay_env(conf: &mut ProjectConfig, file: &Utf8Path) -> Result<()> {
     for entry in dotenvy::from_path_iter(file)? {
         let (key, val) = entry?;
+        log::trace!("Env {key} = {val:?} {}", GRAY.paint(file.as_str()));
 
         match key.as_str() {
             "LEPTOS_OUTPUT_NAME" => conf.output_name = val,
             "LEPTOS_SITE_ROOT" => conf.site_root = Utf8PathBuf::from(val),
             "LEPTOS_SITE_PKG_DIR" => conf.site_pkg_dir = Utf8PathBuf::from(val),
-            "LEPTOS_STYLE_FILE" => conf.style_file = Some(Utf8PathBuf::from(val)),
-            "LEPTOS_ASSETS_DIR" => conf.assets_dir = Some(Utf8PathBuf::from(val)),
+            "LEPTOS_STYLE_FILE" => {
+                conf.style_file = val.split(',').map(Utf8PathBuf::from).collect()
+            }
+            "LEPTOS_TAILWIND_INPUT_FILE" => conf.tailwind_input_file = Some(Utf8PathBuf::from(val)),
+            "LEPTOS_TAILWIND_CONFIG_FILE" => {
+                conf.tailwind_config_file = Some(Utf8PathBuf::from(val))
+            }
+            "LEPTOS_ASSETS_DIR" => {
+                conf.assets_dir = val.split(',').map(Utf8PathBuf::from).collect()
+            }
+            "LEPTOS_SASS_LOAD_PATHS" => {
+                conf.sass_load_paths = val.split(',').map(Utf8PathBuf::from).collect()
+            }
+            "LEPTOS_ASSETS_CONFLICT" => conf.assets_conflict = val.parse()?,
             "LEPTOS_SITE_ADDR" => conf.site_addr = val.parse()?,
             "LEPTOS_RELOAD_PORT" => conf.reload_port = val.parse()?,
+            "LEPTOS_AUTO_RELOAD_PORT" => conf.auto_reload_port = val.parse()?,
+            "LEPTOS_RELOAD_WS_PATH" => conf.reload_ws_path = val,
+            "LEPTOS_TLS_CERT_FILE" => conf.tls_cert_file = Some(Utf8PathBuf::from(val)),
+            "LEPTOS_TLS_KEY_FILE" => conf.tls_key_file = Some(Utf8PathBuf::from(val)),
+            "LEPTOS_WATCH_POLL_INTERVAL" => conf.watch_poll_interval = Some(val.parse()?),
+            "LEPTOS_WATCH_DEBOUNCE_MS" => conf.watch_debounce_ms = val.parse()?,
+            "LEPTOS_GRACEFUL_SHUTDOWN_TIMEOUT" => {
+                conf.graceful_shutdown_timeout = Some(val.parse()?)
+            }
+            "LEPTOS_EXIT_ON_NO_CLIENTS" => conf.exit_on_no_clients = Some(val.parse()?),
             "LEPTOS_END2END_CMD" => conf.end2end_cmd = Some(val),
+            "LEPTOS_SERVER_RUN_CMD" => conf.server_run_cmd = Some(val),
             "LEPTOS_END2END_DIR" => conf.end2end_dir = Some(Utf8PathBuf::from(val)),
+            "LEPTOS_END2END_READY_TIMEOUT" => conf.end2end_ready_timeout = val.parse()?,
+            "LEPTOS_END2END_READY_PATH" => conf.end2end_ready_path = Some(val),
             "LEPTOS_BROWSERQUERY" => conf.browserquery = val,
+            "LEPTOS_CSS_PROCESSOR" => conf.css_processor = val.parse()?,
+            "LEPTOS_SOURCE_MAPS" => conf.source_maps = val.parse()?,
+            "LEPTOS_BINDGEN_TARGET" => conf.bindgen_target = val.parse()?,
             _ if key.starts_with("LEPTOS_") => {
                 log::warn!(
                     "Env {key} is not used by cargo-leptos {}",
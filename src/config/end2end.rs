@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use camino::Utf8PathBuf;
 
 use crate::ext::PathBufExt;
@@ -7,6 +9,10 @@ use super::ProjectConfig;
 pub struct End2EndConfig {
     pub cmd: String,
     pub dir: Utf8PathBuf,
+    /// how long to wait for the server to answer before running the cmd
+    pub ready_timeout: Duration,
+    /// requested until it succeeds, otherwise connecting to the site addr is enough
+    pub ready_path: Option<String>,
 }
 
 impl End2EndConfig {
@@ -20,6 +26,8 @@ impl End2EndConfig {
         Some(Self {
             cmd: cmd.clone(),
             dir: dir.clone(),
+            ready_timeout: Duration::from_millis(config.end2end_ready_timeout),
+            ready_path: config.end2end_ready_path.clone(),
         })
     }
 }
@@ -29,6 +37,8 @@ impl std::fmt::Debug for End2EndConfig {
         f.debug_struct("")
             .field("cmd", &self.cmd)
             .field("dir", &self.dir.test_string())
+            .field("ready_timeout", &self.ready_timeout)
+            .field("ready_path", &self.ready_path)
             .finish()
     }
 }
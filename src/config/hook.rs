@@ -0,0 +1,35 @@
+use camino::Utf8PathBuf;
+
+use crate::ext::PathBufExt;
+
+use super::ProjectConfig;
+
+/// A command run before or after the build
+pub struct Hook {
+    pub cmd: String,
+    /// the dir of the Cargo.toml declaring the hook
+    pub dir: Utf8PathBuf,
+}
+
+impl Hook {
+    pub fn resolve(config: &ProjectConfig, cmd: &Option<String>) -> Option<Self> {
+        let cmd = cmd.as_ref()?;
+        let dir = match config.config_dir.as_str() {
+            "" => Utf8PathBuf::from("."),
+            _ => config.config_dir.clone(),
+        };
+        Some(Self {
+            cmd: cmd.clone(),
+            dir,
+        })
+    }
+}
+
+impl std::fmt::Debug for Hook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Hook")
+            .field("cmd", &self.cmd)
+            .field("dir", &self.dir.test_string())
+            .finish()
+    }
+}
@@ -1,6 +1,6 @@
 use crate::{
     ext::{
-        anyhow::{anyhow, Result},
+        anyhow::{anyhow, bail, Error, Result},
         MetadataExt, PathBufExt, PathExt,
     },
     service::site::{SiteFile, SourcedSiteFile},
@@ -8,20 +8,107 @@ use crate::{
 };
 use camino::Utf8PathBuf;
 use cargo_metadata::Metadata;
+use serde::Deserialize;
+use std::str::FromStr;
 
-use super::{project::ProjectDefinition, ProjectConfig};
+use super::{
+    project::{cargo_args, rustflags, ProjectDefinition},
+    ProjectConfig, TestRunner,
+};
 
 pub struct LibPackage {
     pub name: String,
     /// absolute dir to package
     pub abs_dir: Utf8PathBuf,
     pub rel_dir: Utf8PathBuf,
+    /// the cargo target dir of the front build
+    pub target_dir: Utf8PathBuf,
     pub wasm_file: SourcedSiteFile,
     pub js_file: SiteFile,
+    /// the typescript declarations of the js file, when enabled
+    pub ts_file: Option<SiteFile>,
     pub features: Vec<String>,
     pub default_features: bool,
     pub output_name: String,
     pub src_paths: Vec<Utf8PathBuf>,
+    /// optimize the wasm with wasm-opt in release mode
+    pub wasm_opt: bool,
+    /// the wasm-opt optimization level, without the leading dash
+    pub wasm_opt_level: String,
+    /// fail when wasm-opt is unavailable, instead of using the unoptimized wasm
+    pub wasm_opt_required: bool,
+    /// the wasm-opt arguments instead of the optimization level
+    pub wasm_opt_args: Option<Vec<String>>,
+    /// the wasm-opt version instead of the default one
+    pub wasm_opt_version: Option<String>,
+    /// snip the panicking and formatting code, and the functions matching the patterns, in release
+    pub wasm_snip: bool,
+    pub wasm_snip_patterns: Vec<String>,
+    pub test_runner: TestRunner,
+    /// the rustc flags of the front build, added to the ambient ones
+    pub rustflags: Vec<String>,
+    /// additional arguments of the cargo commands
+    pub cargo_args: Vec<String>,
+    /// the wasm-bindgen version in the dependencies of the lib package
+    pub wasm_bindgen_version: Option<String>,
+    /// fail the build when the wasm-bindgen version differs from the one cargo-leptos was built with
+    pub strict_wasm_bindgen: bool,
+    pub bindgen_target: BindgenTarget,
+    /// what to do with the DWARF debug info of the wasm (always stripped in release)
+    pub wasm_dwarf: WasmDwarf,
+    /// keep the function names through wasm-opt, for the stack traces of the panics
+    pub wasm_debug: bool,
+    /// keep the release wasm before wasm-opt, instead of removing it once optimized
+    pub keep_unoptimized_wasm: bool,
+    /// the reference-types wasm feature, the tool defaults when not set
+    pub wasm_reference_types: Option<bool>,
+    /// the multi-value wasm feature, the tool defaults when not set
+    pub wasm_multi_value: Option<bool>,
+}
+
+/// the handling of the DWARF debug info of the wasm in dev builds
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WasmDwarf {
+    /// removed by wasm-bindgen
+    #[default]
+    Strip,
+    /// kept in the wasm file
+    Keep,
+    /// moved to a <output_name>.debug.wasm sidecar file
+    Split,
+}
+
+/// the kind of JS shim generated by wasm-bindgen
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BindgenTarget {
+    /// an ES module loaded by the browser
+    #[default]
+    Web,
+    /// a CommonJS module loading the wasm with `fs` (Node.js, Deno with npm compat)
+    Nodejs,
+}
+
+impl BindgenTarget {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Web => "web",
+            Self::Nodejs => "nodejs",
+        }
+    }
+}
+
+impl FromStr for BindgenTarget {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "web" => Ok(Self::Web),
+            "nodejs" => Ok(Self::Nodejs),
+            _ => bail!(r#"Invalid bindgen-target "{s}", expected "web" or "nodejs""#),
+        }
+    }
 }
 
 impl LibPackage {
@@ -56,18 +143,19 @@ impl LibPackage {
         let rel_dir = abs_dir.unbase(&metadata.workspace_root)?;
         let profile = cli.profile();
 
+        let target_dir = metadata.rel_target_dir().join("front");
         let wasm_file = {
-            let source = metadata
-                .rel_target_dir()
-                .join("front")
+            let source = target_dir
                 .join("wasm32-unknown-unknown")
                 .join(&profile)
                 .join(&name.replace('-', "_"))
                 .with_extension("wasm");
-            let site = config
-                .site_pkg_dir
-                .join(&output_name)
-                .with_extension("wasm");
+            // the nodejs shim loads the wasm from <output_name>_bg.wasm
+            let wasm_name = match config.bindgen_target {
+                BindgenTarget::Web => output_name.clone(),
+                BindgenTarget::Nodejs => format!("{output_name}_bg"),
+            };
+            let site = config.site_pkg_dir.join(&wasm_name).with_extension("wasm");
             let dest = config.site_root.join(&site);
             SourcedSiteFile { source, dest, site }
         };
@@ -77,6 +165,11 @@ impl LibPackage {
             let dest = config.site_root.join(&site);
             SiteFile { dest, site }
         };
+        let ts_file = config.typescript.then(|| {
+            let site = js_file.site.with_extension("d.ts");
+            let dest = config.site_root.join(&site);
+            SiteFile { dest, site }
+        });
 
         let mut src_deps = metadata.src_path_dependencies(&package.id);
         if rel_dir == "." {
@@ -88,14 +181,72 @@ impl LibPackage {
             name,
             abs_dir,
             rel_dir,
+            target_dir,
             wasm_file,
             js_file,
+            ts_file,
             features,
             default_features: config.lib_default_features,
             output_name,
             src_paths: src_deps,
+            wasm_opt: config.wasm_opt && !cli.no_wasm_opt,
+            wasm_opt_level: config.wasm_opt_level.clone(),
+            wasm_opt_required: config.wasm_opt_required,
+            wasm_opt_args: config.wasm_opt_args.clone(),
+            wasm_opt_version: config.wasm_opt_version.clone(),
+            wasm_snip: config.wasm_snip,
+            wasm_snip_patterns: config.wasm_snip_patterns.clone(),
+            test_runner: config.lib_test_runner.unwrap_or(config.test_runner),
+            rustflags: rustflags(&config.lib_rustflags),
+            cargo_args: cargo_args(&config.lib_cargo_args, cli)?,
+            wasm_bindgen_version: metadata
+                .resolved_dependency(&package.id, "wasm-bindgen")
+                .map(|p| p.version.to_string()),
+            strict_wasm_bindgen: cli.strict_wasm_bindgen,
+            bindgen_target: config.bindgen_target,
+            wasm_dwarf: if cli.release {
+                WasmDwarf::Strip
+            } else if config.wasm_debug && config.wasm_dwarf == WasmDwarf::Strip {
+                WasmDwarf::Keep
+            } else {
+                config.wasm_dwarf
+            },
+            wasm_debug: config.wasm_debug,
+            keep_unoptimized_wasm: config.keep_unoptimized_wasm,
+            wasm_reference_types: config.wasm_reference_types,
+            wasm_multi_value: config.wasm_multi_value,
         })
     }
+
+    /// the wasm-opt arguments, without the input and output
+    pub fn wasm_opt_args(&self) -> Vec<String> {
+        let mut args = match &self.wasm_opt_args {
+            Some(args) => args.clone(),
+            None => vec![format!("-{}", self.wasm_opt_level)],
+        };
+        args.extend(self.wasm_opt_features().into_iter().map(str::to_string));
+        if self.wasm_debug && !args.iter().any(|a| a == "-g" || a == "--debuginfo") {
+            args.push("--debuginfo".to_string());
+        }
+        args
+    }
+
+    /// the wasm-opt flags matching the configured wasm features, so that
+    /// wasm-opt doesn't introduce the disabled ones
+    pub fn wasm_opt_features(&self) -> Vec<&'static str> {
+        let mut flags = Vec::new();
+        match self.wasm_reference_types {
+            Some(true) => flags.push("--enable-reference-types"),
+            Some(false) => flags.push("--disable-reference-types"),
+            None => {}
+        }
+        match self.wasm_multi_value {
+            Some(true) => flags.push("--enable-multivalue"),
+            Some(false) => flags.push("--disable-multivalue"),
+            None => {}
+        }
+        flags
+    }
 }
 
 impl std::fmt::Debug for LibPackage {
@@ -103,8 +254,10 @@ impl std::fmt::Debug for LibPackage {
         f.debug_struct("LibPackage")
             .field("name", &self.name)
             .field("rel_dir", &self.rel_dir.test_string())
+            .field("target_dir", &self.target_dir.test_string())
             .field("wasm_file", &self.wasm_file)
             .field("js_file", &self.js_file)
+            .field("ts_file", &self.ts_file)
             .field("features", &self.features)
             .field("default_features", &self.default_features)
             .field("output_name", &self.output_name)
@@ -117,6 +270,21 @@ impl std::fmt::Debug for LibPackage {
                     .collect::<Vec<_>>()
                     .join(", "),
             )
+            .field("wasm_opt", &self.wasm_opt)
+            .field("wasm_opt_level", &self.wasm_opt_level)
+            .field("wasm_opt_required", &self.wasm_opt_required)
+            .field("wasm_opt_args", &self.wasm_opt_args)
+            .field("wasm_opt_version", &self.wasm_opt_version)
+            .field("wasm_snip", &self.wasm_snip)
+            .field("test_runner", &self.test_runner)
+            .field("rustflags", &self.rustflags)
+            .field("cargo_args", &self.cargo_args)
+            .field("bindgen_target", &self.bindgen_target)
+            .field("wasm_dwarf", &self.wasm_dwarf)
+            .field("wasm_debug", &self.wasm_debug)
+            .field("keep_unoptimized_wasm", &self.keep_unoptimized_wasm)
+            .field("wasm_reference_types", &self.wasm_reference_types)
+            .field("wasm_multi_value", &self.wasm_multi_value)
             .finish_non_exhaustive()
     }
 }
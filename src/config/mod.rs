@@ -3,33 +3,52 @@ mod tests;
 
 mod assets;
 mod bin_package;
+mod cache_control;
 mod dotenvs;
 mod end2end;
+mod hook;
 mod lib_package;
 mod project;
+mod proxy;
 mod style;
+mod tailwind;
+mod tls;
 
 use std::{fmt::Debug, sync::Arc};
 
 use crate::{
     ext::{
         anyhow::{Context, Result},
-        MetadataExt,
+        DirLock, MetadataExt,
     },
     Opts,
 };
 use anyhow::bail;
+pub use assets::{AssetTransform, AssetsConfig, AssetsConflict};
+pub use bin_package::{server_run_args, BinPackage};
+pub use cache_control::{CacheControl, CacheControlRule};
 use camino::{Utf8Path, Utf8PathBuf};
 use cargo_metadata::Metadata;
-pub use project::{Project, ProjectConfig};
-pub use style::StyleConfig;
+pub use end2end::End2EndConfig;
+pub use hook::Hook;
+pub use lib_package::{BindgenTarget, WasmDwarf};
+pub use project::{Project, ProjectConfig, TestRunner};
+pub use proxy::Proxy;
+pub use style::{CssProcessor, StyleConfig};
+pub use tailwind::TailwindConfig;
+pub use tls::TlsConfig;
 
 pub struct Config {
     /// absolute path to the working dir
     pub working_dir: Utf8PathBuf,
+    /// absolute path to the cargo target dir
+    pub target_dir: Utf8PathBuf,
     pub projects: Vec<Arc<Project>>,
     pub cli: Opts,
     pub watch: bool,
+    /// the dir and the absolute manifest path it was loaded from, for reloading it
+    cwd: Utf8PathBuf,
+    manifest_path: Utf8PathBuf,
 }
 
 impl Debug for Config {
@@ -44,7 +63,7 @@ impl Debug for Config {
 
 impl Config {
     pub fn load(cli: Opts, cwd: &Utf8Path, manifest_path: &Utf8Path, watch: bool) -> Result<Self> {
-        let metadata = Metadata::load_cleaned(manifest_path)?;
+        let metadata = Metadata::load_cleaned(manifest_path, cli.cargo_flags())?;
 
         let mut projects = Project::resolve(&cli, cwd, &metadata, watch).dot()?;
 
@@ -65,12 +84,20 @@ impl Config {
 
         Ok(Self {
             working_dir: metadata.workspace_root.clone(),
+            target_dir: metadata.target_directory.clone(),
             projects,
             cli,
             watch,
+            cwd: cwd.to_path_buf(),
+            manifest_path: cwd.join(manifest_path),
         })
     }
 
+    /// loads the config again, after a change of the Cargo.toml or .env files
+    pub fn reload(&self) -> Result<Self> {
+        Self::load(self.cli.clone(), &self.cwd, &self.manifest_path, self.watch)
+    }
+
     #[cfg(test)]
     pub fn test_load(cli: Opts, cwd: &str, manifest_path: &str, watch: bool) -> Self {
         use crate::ext::PathBufExt;
@@ -83,6 +110,11 @@ impl Config {
         Self::load(cli, &cwd, &manifest_path, watch).unwrap()
     }
 
+    /// Locks the target dir so that concurrent runs don't overwrite each other's output.
+    pub fn lock(&self) -> Result<DirLock> {
+        DirLock::acquire(&self.target_dir)
+    }
+
     pub fn current_project(&self) -> Result<Arc<Project>> {
         if self.projects.len() == 1 {
             Ok(self.projects[0].clone())
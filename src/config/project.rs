@@ -1,38 +1,100 @@
 use crate::{
     config::lib_package::LibPackage,
     ext::{
-        anyhow::{bail, ensure, Result},
-        PackageExt, PathBufExt, PathExt,
+        anyhow::{bail, ensure, Context, Result},
+        MetadataExt, PackageExt, PathBufExt, PathExt,
     },
     logger::GRAY,
-    service::site::Site,
-    Opts,
+    service::site::{Site, SourcedSiteFile},
+    Env, Opts,
 };
-use camino::{Utf8Path, Utf8PathBuf};
+use camino::{Utf8Component, Utf8Path, Utf8PathBuf};
 use cargo_metadata::{Metadata, Package};
-use serde::Deserialize;
-use std::{fmt::Debug, net::SocketAddr, sync::Arc};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use itertools::Itertools;
+use serde::{Deserialize, Deserializer};
+use std::{collections::BTreeMap, fmt::Debug, net::SocketAddr, sync::Arc, time::Duration};
 
 use super::{
-    assets::AssetsConfig,
-    bin_package::BinPackage,
-    dotenvs::{find_env_file, overlay_env},
+    assets::{AssetTransformConfig, AssetsConfig, AssetsConflict},
+    bin_package::{BinPackage, SERVER_EXE},
+    cache_control::{CacheControl, CacheControlRule},
+    dotenvs::{env_file_names, find_env_files, overlay_env},
     end2end::End2EndConfig,
-    style::StyleConfig,
+    hook::Hook,
+    lib_package::{BindgenTarget, WasmDwarf},
+    proxy::Proxy,
+    style::{CssProcessor, StyleConfig},
+    tls::TlsConfig,
 };
 
 pub struct Project {
     /// absolute path to the working dir
     pub working_dir: Utf8PathBuf,
+    /// the cargo target dir, relative to the working dir unless outside of it
+    pub target_dir: Utf8PathBuf,
     pub name: String,
     pub lib: LibPackage,
-    pub bin: BinPackage,
+    /// none for csr-only projects, which are served as static files
+    pub bin: Option<BinPackage>,
     pub style: Option<StyleConfig>,
     pub watch: bool,
+    /// tell the server not to inject the live-reload script: when not watching or with LEPTOS_DISABLE_RELOAD set
+    pub disable_reload: bool,
     pub release: bool,
+    /// build the front and the server concurrently
+    pub parallel: bool,
+    /// the number of parallel jobs of each cargo build, the cargo default when not set
+    pub jobs: Option<u32>,
     pub site: Arc<Site>,
     pub end2end: Option<End2EndConfig>,
+    /// run before each build, also in watch mode
+    pub pre_build: Option<Hook>,
+    /// run after each successful build, also in watch mode
+    pub post_build: Option<Hook>,
     pub assets: Option<AssetsConfig>,
+    /// the html template written to the site index.html, with the leptos head injected
+    pub index_file: Option<SourcedSiteFile>,
+    /// clean the site and copy all the assets on the first sync, instead of only the changed ones
+    pub force_resync: bool,
+    /// log the largest sections and functions of the wasm
+    pub size_report: bool,
+    /// open the site in the browser once the server is up
+    pub open: bool,
+    /// dir for the intermediate build files, so that the site only contains shippable files
+    pub tmp_dir: Utf8PathBuf,
+    /// write brotli and gzip compressed copies of the site files in release builds
+    pub precompress: bool,
+    /// files smaller than this (in bytes) are not precompressed
+    pub precompress_min_size: u64,
+    /// where the hashes are written when the site files are named with the hash of their content
+    pub hash_file: Option<Utf8PathBuf>,
+    /// the LEPTOS_ENV given on the command line
+    pub env: Option<Env>,
+    /// files not triggering a rebuild in watch mode, matched against the path relative to the working dir
+    pub watch_ignore: GlobSet,
+    /// additional paths triggering a full rebuild in watch mode, relative to the workspace unless outside of it
+    pub watch_additional_files: Vec<Utf8PathBuf>,
+    /// the Cargo.toml and .env files of the config, reloaded when changed in watch mode
+    pub config_files: Vec<Utf8PathBuf>,
+    /// the interval for polling the file system in watch mode, instead of relying on its change events
+    pub watch_poll: Option<Duration>,
+    /// file changes within this duration are coalesced into one rebuild in watch mode
+    pub watch_debounce: Duration,
+    /// the server is sent a SIGTERM on restart and killed if still running after this duration
+    pub graceful_shutdown_timeout: Option<Duration>,
+    /// in watch mode, shut down once no reload client was connected for this duration
+    pub exit_on_no_clients: Option<Duration>,
+    /// serve the reload websocket over wss://
+    pub tls: Option<TlsConfig>,
+    /// the static server of a csr-only project serves the index.html for the html requests not matching a file
+    pub spa_fallback: bool,
+    /// the Cache-Control headers sent by the static server of a csr-only project
+    pub cache_control: CacheControl,
+    /// the upstreams of the requests not matching a file of the static server of a csr-only project
+    pub proxy: Proxy,
+    /// the static server of a csr-only project compresses the responses accepting it
+    pub dev_compression: bool,
 }
 
 impl Debug for Project {
@@ -43,10 +105,44 @@ impl Debug for Project {
             .field("bin", &self.bin)
             .field("style", &self.style)
             .field("watch", &self.watch)
+            .field("disable_reload", &self.disable_reload)
             .field("release", &self.release)
+            .field("parallel", &self.parallel)
+            .field("jobs", &self.jobs)
             .field("site", &self.site)
             .field("end2end", &self.end2end)
+            .field("pre_build", &self.pre_build)
+            .field("post_build", &self.post_build)
             .field("assets", &self.assets)
+            .field("index_file", &self.index_file)
+            .field("force_resync", &self.force_resync)
+            .field("size_report", &self.size_report)
+            .field("open", &self.open)
+            .field("tmp_dir", &self.tmp_dir.test_string())
+            .field("precompress", &self.precompress)
+            .field("precompress_min_size", &self.precompress_min_size)
+            .field(
+                "hash_file",
+                &self.hash_file.as_ref().map(|f| f.test_string()),
+            )
+            .field("env", &self.env)
+            .field(
+                "watch_additional_files",
+                &self
+                    .watch_additional_files
+                    .iter()
+                    .map(|f| f.test_string())
+                    .collect::<Vec<_>>(),
+            )
+            .field("watch_poll", &self.watch_poll)
+            .field("watch_debounce", &self.watch_debounce)
+            .field("graceful_shutdown_timeout", &self.graceful_shutdown_timeout)
+            .field("exit_on_no_clients", &self.exit_on_no_clients)
+            .field("tls", &self.tls)
+            .field("spa_fallback", &self.spa_fallback)
+            .field("cache_control", &self.cache_control)
+            .field("proxy", &self.proxy)
+            .field("dev_compression", &self.dev_compression)
             .finish_non_exhaustive()
     }
 }
@@ -58,32 +154,161 @@ impl Project {
         metadata: &Metadata,
         watch: bool,
     ) -> Result<Vec<Arc<Project>>> {
-        let projects = ProjectDefinition::parse(&metadata)?;
+        let projects = ProjectDefinition::parse(metadata, &cli.profile())?;
+        let env_name = std::env::var("LEPTOS_OUTPUT_NAME").ok();
+        let count = projects.len();
 
         let mut resolved = Vec::new();
         for (project, mut config) in projects {
+            project.check_packages(metadata, config.csr_only)?;
+            let selected = cli.project.as_deref();
+            let from_env = env_output_name(env_name.as_deref(), &project.name, count, selected);
+            if let Some(name) = from_env {
+                config.output_name = name.to_string();
+            }
             if config.output_name.is_empty() {
                 config.output_name = project.name.to_string();
             }
+            validate_output_name(&config.output_name).context(format!(
+                "Invalid output-name of the project {}",
+                project.name
+            ))?;
+            if let Some(addr) = cli.site_addr {
+                config.site_addr = addr;
+            }
+            if let Some(host) = cli.host {
+                config.site_addr.set_ip(host);
+            }
+            if let Some(port) = cli.port {
+                config.site_addr.set_port(port);
+            }
+            if config.site_root.as_str().is_empty() {
+                config.site_root = metadata.rel_target_dir().join("site");
+            } else {
+                check_site_root(
+                    &config.site_root,
+                    &metadata.workspace_root,
+                    config.allow_external_site_root,
+                )?;
+            }
+
+            let assets = AssetsConfig::resolve(&config, &metadata.rel_target_dir(), cli.release)?;
+            if let Some(assets) = &assets {
+                check_assets_dirs(&assets.dirs, &config.site_root, &metadata.workspace_root)?;
+            }
+
+            let bin = match config.csr_only {
+                true => None,
+                false => Some(BinPackage::resolve(cli, metadata, &project, &config)?),
+            };
+            let tmp_dir = match &config.tmp_dir {
+                Some(dir) => config.config_relative(dir),
+                None => metadata.rel_target_dir().join("tmp").join(&project.name),
+            };
+            // the server looks for the hash file next to its executable, while only the
+            // index-file of a csr-only project is rendered with it
+            let hash_file = config.hash_files.then(|| match &bin {
+                Some(bin) => bin.exe_file.with_file_name(HASH_FILE_NAME),
+                None => tmp_dir.join(HASH_FILE_NAME),
+            });
+
+            let lib = LibPackage::resolve(cli, metadata, &project, &config)?;
+            let mut style = StyleConfig::new(&config);
+            // compared with the watched paths, which are relative to the workspace unless outside of it
+            for dir in style.iter_mut().flat_map(|style| &mut style.load_paths) {
+                *dir = workspace_relative(&metadata.workspace_root, dir);
+            }
+            let config_files = config_files(
+                &config,
+                &metadata.workspace_root,
+                &lib,
+                bin.as_ref(),
+                &cli.profile(),
+            );
 
             let proj = Project {
                 working_dir: metadata.workspace_root.clone(),
+                target_dir: metadata.rel_target_dir(),
                 name: project.name.clone(),
-                lib: LibPackage::resolve(cli, &metadata, &project, &config)?,
-                bin: BinPackage::resolve(cli, &metadata, &project, &config)?,
-                style: StyleConfig::new(&config),
+                lib,
+                bin,
+                style,
                 watch,
+                disable_reload: !watch || std::env::var_os("LEPTOS_DISABLE_RELOAD").is_some(),
                 release: cli.release,
+                parallel: !cli.no_parallel,
+                jobs: cli.jobs,
                 site: Arc::new(Site::new(&config)),
                 end2end: End2EndConfig::resolve(&config),
-                assets: AssetsConfig::resolve(&config),
+                pre_build: Hook::resolve(&config, &config.pre_build),
+                post_build: Hook::resolve(&config, &config.post_build),
+                assets,
+                index_file: config.index_file.as_ref().map(|file| SourcedSiteFile {
+                    source: config.config_relative(file),
+                    dest: config.site_root.join("index.html"),
+                    site: "index.html".into(),
+                }),
+                force_resync: cli.force_resync,
+                size_report: cli.size_report,
+                open: cli.open,
+                tmp_dir,
+                precompress: config.precompress,
+                precompress_min_size: config.precompress_min_size,
+                hash_file,
+                env: cli.env,
+                watch_ignore: config.watch_ignore_set()?,
+                // compared with the watched paths, as the sass load paths
+                watch_additional_files: config
+                    .watch_additional_files
+                    .iter()
+                    .map(|path| {
+                        workspace_relative(&metadata.workspace_root, &config.config_relative(path))
+                    })
+                    .collect(),
+                config_files,
+                watch_poll: match (config.watch_poll_interval, cli.poll) {
+                    (Some(ms), _) => Some(Duration::from_millis(ms)),
+                    (None, true) => Some(DEFAULT_WATCH_POLL_INTERVAL),
+                    (None, false) => None,
+                },
+                watch_debounce: watch_debounce(config.watch_debounce_ms),
+                graceful_shutdown_timeout: config
+                    .graceful_shutdown_timeout
+                    .map(Duration::from_millis),
+                exit_on_no_clients: config.exit_on_no_clients.map(Duration::from_millis),
+                tls: TlsConfig::resolve(&config),
+                spa_fallback: config.spa_fallback.unwrap_or(config.csr_only),
+                cache_control: CacheControl::resolve(&config)?,
+                proxy: Proxy::resolve(&config)?,
+                dev_compression: config.dev_compression.unwrap_or(config.csr_only),
             };
             resolved.push(Arc::new(proj));
         }
 
+        // projects sharing a site root overwrite each other's js, wasm and css files
+        for (i, proj) in resolved.iter().enumerate() {
+            if let Some(other) = resolved[..i].iter().find(|other| {
+                other.lib.output_name == proj.lib.output_name
+                    && other.site.root_dir == proj.site.root_dir
+            }) {
+                bail!(
+                    "The projects {} and {} both write the output-name {:?} to the site-root {}. Set a distinct output-name for each.",
+                    other.name,
+                    proj.name,
+                    proj.lib.output_name,
+                    proj.site.root_dir
+                );
+            }
+        }
+
         let projects_in_cwd = resolved
             .iter()
-            .filter(|p| p.bin.abs_dir.starts_with(&cwd) || p.lib.abs_dir.starts_with(&cwd))
+            .filter(|p| {
+                p.lib.abs_dir.starts_with(cwd)
+                    || p.bin
+                        .as_ref()
+                        .is_some_and(|bin| bin.abs_dir.starts_with(cwd))
+            })
             .collect::<Vec<_>>();
 
         if projects_in_cwd.len() == 1 {
@@ -93,6 +318,20 @@ impl Project {
         }
     }
 
+    /// if changes to the file, relative to the working dir, are ignored in watch mode
+    pub fn is_watch_ignored(&self, path: &Utf8Path) -> bool {
+        self.watch_ignore.is_match(path)
+    }
+
+    /// the cargo profile the project is built with: debug or release
+    pub fn profile(&self) -> &'static str {
+        if self.release {
+            "release"
+        } else {
+            "debug"
+        }
+    }
+
     /// env vars to use when running external command
     pub fn to_envs(&self) -> Vec<(&'static str, String)> {
         let mut vec = vec![
@@ -101,12 +340,38 @@ impl Project {
             ("LEPTOS_SITE_PKG_DIR", self.site.pkg_dir.to_string()),
             ("LEPTOS_SITE_ADDR", self.site.addr.to_string()),
             ("LEPTOS_RELOAD_PORT", self.site.reload.port().to_string()),
+            ("LEPTOS_RELOAD_WS_PATH", self.site.reload_path.clone()),
             ("LEPTOS_LIB_DIR", self.lib.rel_dir.to_string()),
-            ("LEPTOS_BIN_DIR", self.bin.rel_dir.to_string()),
         ];
+        if let Some(bin) = &self.bin {
+            vec.push(("LEPTOS_BIN_DIR", bin.rel_dir.to_string()));
+            vec.push(("LEPTOS_BIN_FEATURES", bin.features.join(",")));
+        }
+        vec.push(("LEPTOS_LIB_FEATURES", self.lib.features.join(",")));
+        vec.push(("LEPTOS_PROFILE", self.profile().to_string()));
         if self.watch {
             vec.push(("LEPTOS_WATCH", "ON".to_string()))
         }
+        if self.disable_reload {
+            vec.push(("LEPTOS_DISABLE_RELOAD", "true".to_string()))
+        }
+        if self.tls.is_some() {
+            vec.push(("LEPTOS_RELOAD_WS_PROTOCOL", "wss".to_string()))
+        }
+        if let Some(env) = self.env {
+            vec.push(("LEPTOS_ENV", env.as_str().to_string()))
+        }
+        if self.hash_file.is_some() {
+            vec.push(("LEPTOS_HASH_FILES", "true".to_string()));
+            vec.push(("LEPTOS_HASH_FILE_NAME", HASH_FILE_NAME.to_string()));
+        }
+        if self.lib.bindgen_target != BindgenTarget::Web {
+            vec.push((
+                "LEPTOS_BINDGEN_TARGET",
+                self.lib.bindgen_target.as_str().to_string(),
+            ));
+            vec.push(("LEPTOS_WASM_FILE", self.lib.wasm_file.site.to_string()));
+        }
         vec
     }
 }
@@ -118,24 +383,86 @@ pub struct ProjectConfig {
     pub output_name: String,
     #[serde(default = "default_site_addr")]
     pub site_addr: SocketAddr,
-    #[serde(default = "default_site_root")]
+    /// defaults to the site dir in the cargo target dir
+    #[serde(default)]
     pub site_root: Utf8PathBuf,
+    /// allow a site-root outside of the workspace, its content is erased on each build
+    #[serde(default)]
+    pub allow_external_site_root: bool,
     #[serde(default = "default_pkg_dir")]
     pub site_pkg_dir: Utf8PathBuf,
-    pub style_file: Option<Utf8PathBuf>,
-    /// assets dir. content will be copied to the target/site dir
-    pub assets_dir: Option<Utf8PathBuf>,
+    /// one or several style files, bundled in the declared order into the site css file
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub style_file: Vec<Utf8PathBuf>,
+    /// what processes the style file: sass (default) or lightningcss
+    #[serde(default)]
+    pub css_processor: CssProcessor,
+    /// the dart-sass release downloaded when not on the PATH, ex: 1.57.1
+    pub sass_version: Option<String>,
+    /// the dirs, relative to this Cargo.toml, where sass also looks for the @use and @import
+    #[serde(default)]
+    pub sass_load_paths: Vec<Utf8PathBuf>,
+    /// emit the css source map also in release. It is always emitted in dev
+    #[serde(default)]
+    pub source_maps: bool,
+    /// the tailwind input css file. Cannot be used together with the style-file
+    pub tailwind_input_file: Option<Utf8PathBuf>,
+    /// the tailwind config file, when not in the default location
+    pub tailwind_config_file: Option<Utf8PathBuf>,
+    /// assets dirs. content will be copied to the target/site dir
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub assets_dir: Vec<Utf8PathBuf>,
+    /// html template written to the site index.html, with the leptos head injected at the
+    /// <!-- leptos-head --> placeholder
+    pub index_file: Option<Utf8PathBuf>,
+    /// what to do when several assets are copied to the same site file
+    #[serde(default)]
+    pub assets_conflict: AssetsConflict,
+    /// how many assets are copied at once. Defaults to the number of CPUs
+    pub assets_copy_concurrency: Option<usize>,
+    /// globs of the assets only copied in dev, relative to the assets dir
+    #[serde(default)]
+    pub assets_dev_only: Vec<String>,
+    /// globs of the assets only copied in release, relative to the assets dir
+    #[serde(default)]
+    pub assets_release_only: Vec<String>,
+    /// commands transforming the assets when copied to the site
+    #[serde(default)]
+    pub asset_pipeline: Vec<AssetTransformConfig>,
     #[serde(default = "default_reload_port")]
     pub reload_port: u16,
+    /// use the next free port when the reload-port is busy
+    #[serde(default)]
+    pub auto_reload_port: bool,
+    /// the path of the reload websocket, on the reload-port
+    #[serde(default = "default_reload_ws_path")]
+    pub reload_ws_path: String,
+    /// PEM certificate for serving the reload websocket over wss://, together with the tls-key-file
+    pub tls_cert_file: Option<Utf8PathBuf>,
+    /// PEM private key for serving the reload websocket over wss://, together with the tls-cert-file
+    pub tls_key_file: Option<Utf8PathBuf>,
     /// command for launching end-2-end integration tests
     pub end2end_cmd: Option<String>,
     /// the dir used when launching end-2-end integration tests
     pub end2end_dir: Option<Utf8PathBuf>,
+    /// the ms to wait for the server to answer before running the end2end-cmd
+    #[serde(default = "default_end2end_ready_timeout")]
+    pub end2end_ready_timeout: u64,
+    /// the path requested until the server answers with a success, instead of only connecting
+    pub end2end_ready_path: Option<String>,
+    /// command run in the dir of this Cargo.toml before each build, for instance for generating code
+    pub pre_build: Option<String>,
+    /// command run in the dir of this Cargo.toml after each successful build
+    pub post_build: Option<String>,
+    /// command running the server instead of its exe, which replaces the {exe} placeholder
+    pub server_run_cmd: Option<String>,
     #[serde(default = "default_browserquery")]
     pub browserquery: String,
     /// the bin target to use for building the server
     #[serde(default)]
     pub bin_target: String,
+    /// the target triple for cross-compiling the server, defaults to the host
+    pub bin_target_triple: Option<String>,
     #[serde(default)]
     pub lib_features: Vec<String>,
     #[serde(default)]
@@ -144,43 +471,507 @@ pub struct ProjectConfig {
     pub bin_features: Vec<String>,
     #[serde(default)]
     pub bin_default_features: bool,
+    /// rustc flags only for the front (wasm) build, added to the ambient RUSTFLAGS
+    pub lib_rustflags: Option<String>,
+    /// rustc flags only for the server build, added to the ambient RUSTFLAGS
+    pub bin_rustflags: Option<String>,
+    /// additional arguments of the cargo commands of the front
+    #[serde(default)]
+    pub lib_cargo_args: Vec<String>,
+    /// additional arguments of the cargo commands of the server
+    #[serde(default)]
+    pub bin_cargo_args: Vec<String>,
+    /// optimize the wasm with wasm-opt in release mode
+    #[serde(default = "default_true")]
+    pub wasm_opt: bool,
+    /// the wasm-opt optimization level: O0, O1, O2, O3, O4, Os or Oz
+    #[serde(default = "default_wasm_opt_level")]
+    pub wasm_opt_level: String,
+    /// the wasm-opt arguments replacing the optimization level, without the input and output
+    pub wasm_opt_args: Option<Vec<String>>,
+    /// the binaryen release tag of the wasm-opt downloaded when not on the PATH, ex: version_111
+    pub wasm_opt_version: Option<String>,
+    /// fail the release build when wasm-opt is unavailable, instead of shipping the unoptimized wasm
+    #[serde(default = "default_true")]
+    pub wasm_opt_required: bool,
+    /// remove the panicking and formatting code from the release wasm with wasm-snip, before wasm-opt
+    #[serde(default)]
+    pub wasm_snip: bool,
+    /// regexes of additional functions removed by wasm-snip
+    #[serde(default)]
+    pub wasm_snip_patterns: Vec<String>,
+    /// keep the DWARF debug info in dev builds, either in the wasm or in a sidecar file
+    #[serde(default)]
+    pub wasm_dwarf: WasmDwarf,
+    /// keep the function names of the wasm in release too, and its DWARF in dev when wasm-dwarf is not set
+    #[serde(default)]
+    pub wasm_debug: bool,
+    /// keep the release wasm before wasm-opt in the tmp-dir, for comparing with the optimized one
+    #[serde(default)]
+    pub keep_unoptimized_wasm: bool,
+    /// enable or disable the reference-types wasm feature in wasm-bindgen and wasm-opt
+    pub wasm_reference_types: Option<bool>,
+    /// enable or disable the multi-value wasm feature in wasm-opt
+    pub wasm_multi_value: Option<bool>,
+    /// write the typescript declarations of the js shim next to it
+    #[serde(default)]
+    pub typescript: bool,
+    /// the test runner used for both the lib and bin packages
+    #[serde(default)]
+    pub test_runner: TestRunner,
+    pub lib_test_runner: Option<TestRunner>,
+    pub bin_test_runner: Option<TestRunner>,
+    /// the JS shim generated for the wasm: web (default) or nodejs
+    #[serde(default)]
+    pub bindgen_target: BindgenTarget,
+    /// dir for the intermediate build files. Defaults to target/tmp/<project name>
+    pub tmp_dir: Option<Utf8PathBuf>,
+    /// write brotli and gzip compressed copies of the site files when building in release
+    #[serde(default)]
+    pub precompress: bool,
+    #[serde(default = "default_precompress_min_size")]
+    pub precompress_min_size: u64,
+    /// name the js, wasm and css files with the hash of their content
+    #[serde(default)]
+    pub hash_files: bool,
+    /// the project has no server: the bin is not built and the site is served as static files
+    #[serde(default)]
+    pub csr_only: bool,
+    /// serve the index.html for the html requests of the app routes. Defaults to csr-only
+    pub spa_fallback: Option<bool>,
+    /// the Cache-Control headers of the csr-only static server, by glob of the site paths
+    #[serde(default)]
+    pub cache_control: Vec<CacheControlRule>,
+    /// the upstream urls of the path prefixes, the csr-only static server forwards the requests to
+    #[serde(default)]
+    pub proxy: BTreeMap<String, String>,
+    /// compress the responses of the csr-only static server with br or gzip. Defaults to csr-only
+    pub dev_compression: Option<bool>,
+    /// globs of the files, relative to this Cargo.toml, not triggering a rebuild in watch mode
+    #[serde(default)]
+    pub watch_ignore: Vec<String>,
+    /// files or dirs, relative to this Cargo.toml, also triggering a full rebuild in watch mode
+    #[serde(default)]
+    pub watch_additional_files: Vec<Utf8PathBuf>,
+    /// poll the file system with this interval (in ms) in watch mode, instead of relying on its change events
+    pub watch_poll_interval: Option<u64>,
+    /// file changes within this many ms are coalesced into one rebuild in watch mode
+    #[serde(default = "default_watch_debounce_ms")]
+    pub watch_debounce_ms: u64,
+    /// on restart in watch mode, wait this many ms for the server to stop after a SIGTERM before killing it
+    pub graceful_shutdown_timeout: Option<u64>,
+    /// in watch mode, shut down this many ms after the last reload client disconnected, unless one reconnects
+    pub exit_on_no_clients: Option<u64>,
     #[serde(skip)]
     pub config_dir: Utf8PathBuf,
 }
 
 impl ProjectConfig {
-    fn parse(dir: &Utf8Path, metadata: &serde_json::Value) -> Result<Self> {
+    pub(crate) fn parse(
+        dir: &Utf8Path,
+        metadata: &serde_json::Value,
+        profile: &str,
+    ) -> Result<Self> {
         let mut conf: ProjectConfig = serde_json::from_value(metadata.clone())?;
         conf.config_dir = dir.to_path_buf();
-        if let Some(file) = find_env_file(dir) {
+        // the later files override the earlier ones, and the cli overrides them all in Project::resolve
+        for file in find_env_files(dir, profile) {
             overlay_env(&mut conf, &file)?;
         }
+        if !conf.style_file.is_empty() && conf.tailwind_input_file.is_some() {
+            bail!("style-file and tailwind-input-file cannot both be set. Import the sass/css file from the tailwind input file instead.");
+        }
+        if conf.css_processor == CssProcessor::Lightningcss {
+            if let Some(file) = conf
+                .style_file
+                .iter()
+                .find(|f| f.extension() != Some("css"))
+            {
+                bail!("The css-processor lightningcss only supports css style files, not {file:?}. Use the sass css-processor instead.");
+            }
+            if !conf.sass_load_paths.is_empty() {
+                bail!("sass-load-paths cannot be used with the css-processor lightningcss, which doesn't run sass.");
+            }
+        }
+        if conf.spa_fallback == Some(true) && !conf.csr_only {
+            bail!("spa-fallback requires csr-only: the server of the app serves its routes.");
+        }
+        if let Some(cmd) = &conf.server_run_cmd {
+            if conf.csr_only {
+                bail!("server-run-cmd cannot be used with csr-only, which has no server.");
+            }
+            if !cmd.split_whitespace().any(|part| part.contains(SERVER_EXE)) {
+                bail!("server-run-cmd {cmd:?} must contain {SERVER_EXE}, which is replaced by the path of the server exe.");
+            }
+        }
+        if !conf.cache_control.is_empty() && !conf.csr_only {
+            bail!("cache-control requires csr-only: the server of the app sets its headers.");
+        }
+        if !conf.proxy.is_empty() && !conf.csr_only {
+            bail!("proxy requires csr-only: the server of the app handles its routes.");
+        }
+        if conf.dev_compression == Some(true) && !conf.csr_only {
+            bail!("dev-compression requires csr-only: the server of the app compresses its responses.");
+        }
+        if conf.hash_files && conf.csr_only && conf.index_file.is_none() {
+            bail!("hash-files with csr-only requires an index-file, which is rendered with the hashed file names.");
+        }
+        if conf.hash_files && conf.bindgen_target == BindgenTarget::Nodejs {
+            bail!("hash-files cannot be used with the bindgen-target nodejs, which loads the wasm by name.");
+        }
+        if !WASM_OPT_LEVELS.contains(&conf.wasm_opt_level.as_str()) {
+            bail!(
+                "wasm-opt-level cannot be '{}'. Expected one of {}",
+                conf.wasm_opt_level,
+                WASM_OPT_LEVELS.join(", ")
+            );
+        }
+        if let Some(arg) = conf.wasm_opt_args.iter().flatten().find(|a| is_io_arg(a)) {
+            bail!("wasm-opt-args cannot contain {arg:?}, the input and output of wasm-opt are set by cargo-leptos");
+        }
+        for (name, version) in [
+            ("sass-version", &conf.sass_version),
+            ("wasm-opt-version", &conf.wasm_opt_version),
+        ] {
+            if let Some(version) = version {
+                if version.is_empty()
+                    || !version
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+                {
+                    bail!("{name} {version:?} is not a release version or tag");
+                }
+            }
+        }
+        if conf.tls_cert_file.is_some() != conf.tls_key_file.is_some() {
+            bail!("tls-cert-file and tls-key-file must be set together");
+        }
+        if conf.watch_poll_interval == Some(0) {
+            bail!("watch-poll-interval must be greater than 0 ms");
+        }
         if conf.site_root == "/" || conf.site_root == "." {
             bail!(
                 "site-root cannot be '{}'. All the content is erased when building the site.",
                 conf.site_root
             );
         }
+        check_site_pkg_dir(&conf.site_pkg_dir)?;
+        check_reload_ws_path(&conf.reload_ws_path)?;
         Ok(conf)
     }
+
+    /// resolves a path given in the configuration, which is relative to
+    /// the directory of the configuration file, to a path relative to the
+    /// working dir.
+    pub fn config_relative(&self, path: &Utf8Path) -> Utf8PathBuf {
+        self.config_dir.join(path)
+    }
+
+    /// the watch-ignore globs, made relative to the working dir
+    pub fn watch_ignore_set(&self) -> Result<GlobSet> {
+        let mut set = GlobSetBuilder::new();
+        for glob in &self.watch_ignore {
+            let rel = self.config_relative(Utf8Path::new(glob));
+            let glob =
+                Glob::new(rel.as_str()).context(format!("Invalid watch-ignore glob {glob:?}"))?;
+            set.add(glob);
+        }
+        set.build().context("Invalid watch-ignore globs")
+    }
+}
+
+/// the flags, separated by spaces like in RUSTFLAGS
+pub(crate) fn rustflags(flags: &Option<String>) -> Vec<String> {
+    flags
+        .iter()
+        .flat_map(|flags| flags.split_whitespace())
+        .map(str::to_string)
+        .collect()
+}
+
+/// the arguments set by cargo-leptos, from the config, which cannot be given as additional cargo args
+const CARGO_ARGS_SET: [(&str, &str); 10] = [
+    ("--package", "lib-package/bin-package"),
+    ("--lib", "lib-package"),
+    ("--bin", "bin-target"),
+    ("--target", "bin-target-triple"),
+    ("--target-dir", "the cargo target dir"),
+    ("--features", "lib-features/bin-features"),
+    (
+        "--no-default-features",
+        "lib-default-features/bin-default-features",
+    ),
+    ("--all-features", "lib-features/bin-features"),
+    ("--release", "--release"),
+    ("--profile", "--release"),
+];
+
+/// the --offline and --locked flags, then the additional cargo args of the config and of the command line
+pub(crate) fn cargo_args(config: &[String], cli: &Opts) -> Result<Vec<String>> {
+    for arg in config.iter().chain(&cli.cargo_args) {
+        let name = arg.split('=').next().unwrap_or_default();
+        let set = match name {
+            "-p" => Some("lib-package/bin-package"),
+            "-F" => Some("lib-features/bin-features"),
+            "-r" => Some("--release"),
+            _ => CARGO_ARGS_SET
+                .iter()
+                .find(|(flag, _)| *flag == name)
+                .map(|(_, by)| *by),
+        };
+        if let Some(by) = set {
+            bail!("The cargo argument {arg:?} is already set by cargo-leptos, use {by} instead");
+        }
+    }
+    let mut args = cli.cargo_flags();
+    args.extend(config.iter().chain(&cli.cargo_args).cloned());
+    Ok(args)
+}
+
+/// the output name is used for the js, wasm and css file names, which must be valid on all platforms
+pub(crate) fn validate_output_name(name: &str) -> Result<()> {
+    if let Some(c) = name
+        .chars()
+        .find(|c| !c.is_ascii_alphanumeric() && *c != '_' && *c != '-')
+    {
+        bail!(
+            "{name:?} contains {c:?}. Only ascii letters, digits, '_' and '-' can be used in file names on all platforms. Set the output-name or LEPTOS_OUTPUT_NAME."
+        );
+    }
+    Ok(())
+}
+
+/// the content of the site root is erased on each build, so it is kept in the workspace unless allowed
+pub(crate) fn check_site_root(
+    site_root: &Utf8Path,
+    workspace_root: &Utf8Path,
+    allow_external: bool,
+) -> Result<()> {
+    if site_root.is_absolute() {
+        log::warn!(
+            "Config site-root {site_root} is an absolute path, all its content is erased on each build"
+        );
+    }
+    let resolved = resolve_lexically(workspace_root, site_root);
+    if !allow_external && !resolved.starts_with(workspace_root) {
+        bail!(
+            "The site-root {site_root} is outside of the workspace {workspace_root}. All its content is erased on each build, set allow-external-site-root = true if this is intended."
+        );
+    }
+    Ok(())
+}
+
+/// the manifests and the env files, also the ones not created yet, relative to the working dir
+fn config_files(
+    config: &ProjectConfig,
+    workspace_root: &Utf8Path,
+    lib: &LibPackage,
+    bin: Option<&BinPackage>,
+    profile: &str,
+) -> Vec<Utf8PathBuf> {
+    let mut files = vec![Utf8PathBuf::from("Cargo.toml")];
+    for dir in [Some(&lib.abs_dir), bin.map(|bin| &bin.abs_dir)]
+        .into_iter()
+        .flatten()
+    {
+        let manifest = dir.join("Cargo.toml");
+        files.push(manifest.unbase(workspace_root).unwrap_or(manifest));
+    }
+    files.extend(
+        env_file_names(profile)
+            .iter()
+            .map(|name| config.config_relative(name.as_ref())),
+    );
+    files.extend(find_env_files(&config.config_dir, profile));
+    files.sort();
+    files.dedup();
+    files
+}
+
+/// LEPTOS_OUTPUT_NAME names a single project: in a workspace of several, it only applies to the
+/// one selected with --project
+pub(crate) fn env_output_name<'a>(
+    env_name: Option<&'a str>,
+    project: &str,
+    count: usize,
+    selected: Option<&str>,
+) -> Option<&'a str> {
+    env_name.filter(|_| count == 1 || selected == Some(project))
+}
+
+/// the site-pkg-dir can be nested, such as assets/js, but has to stay inside the site root
+pub(crate) fn check_site_pkg_dir(site_pkg_dir: &Utf8Path) -> Result<()> {
+    if site_pkg_dir.as_str().is_empty()
+        || site_pkg_dir
+            .components()
+            .any(|c| !matches!(c, Utf8Component::Normal(_)))
+    {
+        bail!("site-pkg-dir cannot be '{site_pkg_dir}'. It must be a dir inside the site-root, such as 'pkg' or 'assets/js'.");
+    }
+    Ok(())
+}
+
+/// the reload-ws-path is both an axum route and put in the reload script, so it stays a plain path
+pub(crate) fn check_reload_ws_path(path: &str) -> Result<()> {
+    if !path.starts_with('/')
+        || !path
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '/' | '-' | '_' | '.' | '~'))
+    {
+        bail!("reload-ws-path {path:?} must start with '/' and only contain letters, digits and '/-_.~'");
+    }
+    Ok(())
+}
+
+/// fails when an assets dir is in the site root or the site root in an assets dir,
+/// which would copy the assets into themselves on each sync
+pub(crate) fn check_assets_dirs(
+    assets_dirs: &[Utf8PathBuf],
+    site_root: &Utf8Path,
+    workspace_root: &Utf8Path,
+) -> Result<()> {
+    let site = resolve_lexically(workspace_root, site_root);
+    for dir in assets_dirs {
+        let assets = resolve_lexically(workspace_root, dir);
+        if assets.starts_with(&site) || site.starts_with(&assets) {
+            bail!(
+                "The assets-dir {dir} and the site-root {site_root} overlap, which would copy the assets into themselves. Use separate dirs."
+            );
+        }
+    }
+    Ok(())
+}
+
+/// the path relative to the workspace root, or the absolute path when outside of it
+pub(crate) fn workspace_relative(workspace_root: &Utf8Path, path: &Utf8Path) -> Utf8PathBuf {
+    let abs = resolve_lexically(workspace_root, path);
+    abs.unbase(workspace_root).unwrap_or(abs)
+}
+
+/// the absolute path, with the ".." resolved without the file system, as it might not exist yet
+fn resolve_lexically(base: &Utf8Path, path: &Utf8Path) -> Utf8PathBuf {
+    let mut resolved = Utf8PathBuf::new();
+    for component in base.join(path).components() {
+        match component {
+            Utf8Component::ParentDir => {
+                resolved.pop();
+            }
+            Utf8Component::CurDir => {}
+            component => resolved.push(component),
+        }
+    }
+    resolved
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TestRunner {
+    /// cargo test
+    #[default]
+    Cargo,
+    /// cargo nextest run, see https://nexte.st
+    Nextest,
+}
+
+impl TestRunner {
+    /// the cargo sub command running the tests
+    pub fn cargo_cmd(&self) -> &'static str {
+        match self {
+            Self::Cargo => "test",
+            Self::Nextest => "nextest run",
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct ProjectDefinition {
     name: String,
+    /// empty for csr-only projects
+    #[serde(default)]
     pub bin_package: String,
     pub lib_package: String,
 }
 impl ProjectDefinition {
+    /// Checks that the lib-package and the bin-package, which can be the same package or two
+    /// workspace members, exist and have a cdylib, respectively a bin, target.
+    /// The bin-package of a csr-only project is not needed.
+    pub(crate) fn check_packages(&self, metadata: &Metadata, csr_only: bool) -> Result<()> {
+        let packages = metadata.workspace_packages();
+        let find = |name: &str| packages.iter().find(|p| p.name == name);
+        let members = || packages.iter().map(|p| p.name.as_str()).join(", ");
+
+        if csr_only {
+            ensure!(
+                find(&self.lib_package).is_some_and(|lib| lib.cdylib_target().is_some()),
+                r#"The lib-package "{}" of the csr-only project {} was not found or has no cdylib target. The workspace members are: {}"#,
+                self.lib_package,
+                self.name,
+                members()
+            );
+            return Ok(());
+        }
+        ensure!(
+            !self.bin_package.is_empty(),
+            "The project {} has no bin-package. Set one, or set csr-only = true for a client-side rendered app.",
+            self.name
+        );
+
+        match (find(&self.lib_package), find(&self.bin_package)) {
+            (None, None) => bail!(
+                r#"Could not find the lib-package "{}" nor the bin-package "{}" of the project {} in the workspace members: {}"#,
+                self.lib_package,
+                self.bin_package,
+                self.name,
+                members()
+            ),
+            (None, Some(_)) => bail!(
+                r#"Could not find the lib-package "{}" of the project {}, only its bin-package "{}". The workspace members are: {}"#,
+                self.lib_package,
+                self.name,
+                self.bin_package,
+                members()
+            ),
+            (Some(_), None) => bail!(
+                r#"Could not find the bin-package "{}" of the project {}, only its lib-package "{}". The workspace members are: {}"#,
+                self.bin_package,
+                self.name,
+                self.lib_package,
+                members()
+            ),
+            (Some(lib), Some(bin)) => {
+                let swapped = bin.cdylib_target().is_some() && lib.has_bin_target();
+                let hint = if swapped && lib.name != bin.name {
+                    " The lib-package and the bin-package seem to be swapped."
+                } else {
+                    ""
+                };
+                ensure!(
+                    lib.cdylib_target().is_some(),
+                    r#"The lib-package "{}" of the project {} has no cdylib target, add crate-type = ["cdylib", "rlib"] to its [lib] section.{hint}"#,
+                    lib.name,
+                    self.name
+                );
+                ensure!(
+                    bin.has_bin_target(),
+                    r#"The bin-package "{}" of the project {} has no bin target.{hint}"#,
+                    bin.name,
+                    self.name
+                );
+            }
+        }
+        Ok(())
+    }
+
     fn from_workspace(
         metadata: &serde_json::Value,
         dir: &Utf8Path,
+        profile: &str,
     ) -> Result<Vec<(Self, ProjectConfig)>> {
         let mut found = Vec::new();
         if let Some(arr) = metadata.as_array() {
             for section in arr {
-                let conf = ProjectConfig::parse(dir, section)?;
+                let conf = ProjectConfig::parse(dir, section, profile)?;
                 let def: Self = serde_json::from_value(section.clone())?;
                 found.push((def, conf))
             }
@@ -192,8 +983,9 @@ impl ProjectDefinition {
         package: &Package,
         metadata: &serde_json::Value,
         dir: &Utf8Path,
+        profile: &str,
     ) -> Result<(Self, ProjectConfig)> {
-        let conf = ProjectConfig::parse(dir, metadata)?;
+        let conf = ProjectConfig::parse(dir, metadata, profile)?;
 
         ensure!(
             package.cdylib_target().is_some(),
@@ -201,8 +993,8 @@ impl ProjectDefinition {
             GRAY.paint(package.manifest_path.as_str())
         );
         ensure!(
-            package.has_bin_target(),
-            "Cargo.toml has leptos metadata but is missing a bin target. {}",
+            conf.csr_only || package.has_bin_target(),
+            "Cargo.toml has leptos metadata but is missing a bin target. Set csr-only = true for a client-side rendered app. {}",
             GRAY.paint(package.manifest_path.as_str())
         );
 
@@ -216,11 +1008,11 @@ impl ProjectDefinition {
         ))
     }
 
-    fn parse(metadata: &Metadata) -> Result<Vec<(Self, ProjectConfig)>> {
+    fn parse(metadata: &Metadata, profile: &str) -> Result<Vec<(Self, ProjectConfig)>> {
         let workspace_dir = &metadata.workspace_root;
         let mut found: Vec<(Self, ProjectConfig)> =
             if let Some(md) = leptos_metadata(&metadata.workspace_metadata) {
-                Self::from_workspace(md, &Utf8PathBuf::default())?
+                Self::from_workspace(md, &Utf8PathBuf::default(), profile)?
             } else {
                 Default::default()
             };
@@ -229,7 +1021,7 @@ impl ProjectDefinition {
             let dir = package.manifest_path.unbase(workspace_dir)?.without_last();
 
             if let Some(metadata) = leptos_metadata(&package.metadata) {
-                found.push(Self::from_project(package, metadata, &dir)?);
+                found.push(Self::from_project(package, metadata, &dir, profile)?);
             }
         }
         Ok(found)
@@ -240,6 +1032,24 @@ fn leptos_metadata(metadata: &serde_json::Value) -> Option<&serde_json::Value> {
     metadata.as_object().map(|o| o.get("leptos")).flatten()
 }
 
+/// accepts either a single path or a list of paths
+fn one_or_many<'de, D>(deserializer: D) -> std::result::Result<Vec<Utf8PathBuf>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(Utf8PathBuf),
+        Many(Vec<Utf8PathBuf>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(path) => vec![path],
+        OneOrMany::Many(paths) => paths,
+    })
+}
+
 fn default_site_addr() -> SocketAddr {
     SocketAddr::new([127, 0, 0, 1].into(), 3000)
 }
@@ -248,14 +1058,61 @@ fn default_pkg_dir() -> Utf8PathBuf {
     Utf8PathBuf::from("pkg")
 }
 
-fn default_site_root() -> Utf8PathBuf {
-    Utf8PathBuf::from("target").join("site")
-}
-
 fn default_reload_port() -> u16 {
     3001
 }
 
+fn default_reload_ws_path() -> String {
+    "/live_reload".to_string()
+}
+
+const HASH_FILE_NAME: &str = "hash.txt";
+
+const DEFAULT_WATCH_POLL_INTERVAL: Duration = Duration::from_millis(1000);
+
+fn default_watch_debounce_ms() -> u64 {
+    200
+}
+
+/// the debouncer of the watcher spins without a delay
+const MIN_WATCH_DEBOUNCE: Duration = Duration::from_millis(10);
+
+pub(crate) fn watch_debounce(ms: u64) -> Duration {
+    let debounce = Duration::from_millis(ms);
+    if debounce < MIN_WATCH_DEBOUNCE {
+        log::warn!("Config watch-debounce-ms {ms} is too low, using {MIN_WATCH_DEBOUNCE:?}");
+        return MIN_WATCH_DEBOUNCE;
+    }
+    debounce
+}
+
+const WASM_OPT_LEVELS: [&str; 7] = ["O0", "O1", "O2", "O3", "O4", "Os", "Oz"];
+
+fn default_end2end_ready_timeout() -> u64 {
+    60_000
+}
+
+/// the output option or an input/output file of wasm-opt
+fn is_io_arg(arg: &str) -> bool {
+    arg == "-o"
+        || arg.starts_with("-o=")
+        || arg.starts_with("--output")
+        || arg.ends_with(".wasm")
+        || arg.ends_with(".wat")
+}
+
+fn default_wasm_opt_level() -> String {
+    "Os".to_string()
+}
+
+fn default_precompress_min_size() -> u64 {
+    1024
+}
+
+fn default_true() -> bool {
+    true
+}
+
 fn default_browserquery() -> String {
     "defaults".to_string()
 }
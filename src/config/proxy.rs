@@ -0,0 +1,66 @@
+use std::{cmp::Reverse, fmt::Debug};
+
+use crate::ext::anyhow::{ensure, Context, Result};
+use axum::http::Uri;
+
+use super::ProjectConfig;
+
+/// the upstreams the static server of a csr-only project forwards the unmatched requests to
+pub struct Proxy {
+    /// (path prefix, upstream), the longest prefix first
+    routes: Vec<(String, Uri)>,
+}
+
+impl Proxy {
+    pub fn resolve(config: &ProjectConfig) -> Result<Self> {
+        let mut routes = Vec::new();
+        for (prefix, upstream) in &config.proxy {
+            ensure!(
+                prefix.starts_with('/'),
+                "The proxy path {prefix:?} must start with '/'"
+            );
+            let uri: Uri = upstream
+                .parse()
+                .context(format!("Invalid proxy upstream {upstream:?}"))?;
+            ensure!(
+                uri.scheme_str() == Some("http")
+                    && uri.authority().is_some()
+                    && matches!(uri.path(), "" | "/")
+                    && uri.query().is_none(),
+                "The proxy upstream {upstream:?} must be an http url without a path, such as http://127.0.0.1:8080"
+            );
+            routes.push((prefix.trim_end_matches('/').to_string(), uri));
+        }
+        routes.sort_by_key(|(prefix, _)| Reverse(prefix.len()));
+        Ok(Self { routes })
+    }
+
+    /// the uri of the request at its upstream, with the same path and query,
+    /// when the path is under a proxied prefix
+    pub fn upstream(&self, uri: &Uri) -> Option<Uri> {
+        let path = uri.path();
+        let (_, upstream) = self.routes.iter().find(|(prefix, _)| {
+            path.strip_prefix(prefix.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        })?;
+        let path_and_query = uri.path_and_query().map_or(path, |pq| pq.as_str());
+        Uri::builder()
+            .scheme("http")
+            .authority(upstream.authority()?.clone())
+            .path_and_query(path_and_query)
+            .build()
+            .ok()
+    }
+}
+
+impl Debug for Proxy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map()
+            .entries(
+                self.routes
+                    .iter()
+                    .map(|(prefix, upstream)| (prefix, upstream.to_string())),
+            )
+            .finish()
+    }
+}
@@ -1,21 +1,81 @@
-use crate::service::site::SourcedSiteFile;
+use std::str::FromStr;
 
-use super::ProjectConfig;
+use camino::Utf8PathBuf;
+use serde::Deserialize;
+
+use crate::{
+    ext::{
+        anyhow::{bail, Error, Result},
+        PathBufExt,
+    },
+    service::site::SourcedSiteFile,
+};
+
+use super::{tailwind::TailwindConfig, ProjectConfig};
 
 #[derive(Debug)]
 pub struct StyleConfig {
+    /// the source is either the (first) style-file or the tailwind-input-file
     pub file: SourcedSiteFile,
+    /// the other style-files, bundled after the first one in the declared order
+    pub additional_files: Vec<Utf8PathBuf>,
     pub browserquery: String,
+    pub tailwind: Option<TailwindConfig>,
+    pub processor: CssProcessor,
+    /// emit the source map also in release
+    pub source_maps: bool,
+    /// the dart-sass version instead of the default one
+    pub sass_version: Option<String>,
+    /// the dirs given to sass as --load-path, watched like the style dirs
+    pub load_paths: Vec<Utf8PathBuf>,
+}
+
+/// what processes a plain css style file
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CssProcessor {
+    /// dart-sass for sass/scss files. Plain css files are only processed by Lightning CSS
+    #[default]
+    Sass,
+    /// Lightning CSS only, which also bundles the @import of plain css files.
+    /// No external binary is needed, but sass/scss files are not supported
+    Lightningcss,
+}
+
+impl FromStr for CssProcessor {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sass" => Ok(Self::Sass),
+            "lightningcss" => Ok(Self::Lightningcss),
+            _ => bail!(r#"Invalid css-processor "{s}", expected "sass" or "lightningcss""#),
+        }
+    }
 }
 
 impl StyleConfig {
+    /// all the style files, in the order they are bundled
+    pub fn sources(&self) -> impl Iterator<Item = &Utf8PathBuf> {
+        std::iter::once(&self.file.source).chain(&self.additional_files)
+    }
+
+    /// the dirs of the style files and the load paths, where a change rebuilds the style
+    pub fn dirs(&self) -> impl Iterator<Item = Utf8PathBuf> + '_ {
+        self.sources()
+            .map(|file| file.clone().without_last())
+            .chain(self.load_paths.iter().cloned())
+    }
+
     pub fn new(config: &ProjectConfig) -> Option<Self> {
-        let Some(file) = &config.style_file else {
-          return None;
+        let tailwind = TailwindConfig::resolve(config);
+        let mut style_files = config.style_file.iter().map(|f| config.config_relative(f));
+        let source = match (style_files.next(), &tailwind) {
+            (Some(file), _) => file,
+            (None, Some(tailwind)) => tailwind.input_file.clone(),
+            (None, None) => return None,
         };
         let style_file = {
-            // relative to the configuration file
-            let source = config.config_dir.join(file);
             let site = config
                 .site_pkg_dir
                 .join(&config.output_name)
@@ -25,7 +85,17 @@ impl StyleConfig {
         };
         Some(Self {
             file: style_file,
+            additional_files: style_files.collect(),
             browserquery: config.browserquery.clone(),
+            tailwind,
+            processor: config.css_processor,
+            source_maps: config.source_maps,
+            sass_version: config.sass_version.clone(),
+            load_paths: config
+                .sass_load_paths
+                .iter()
+                .map(|dir| config.config_relative(dir))
+                .collect(),
         })
     }
 }
@@ -0,0 +1,24 @@
+use camino::Utf8PathBuf;
+
+use super::ProjectConfig;
+
+#[derive(Debug)]
+pub struct TailwindConfig {
+    pub input_file: Utf8PathBuf,
+    pub config_file: Option<Utf8PathBuf>,
+}
+
+impl TailwindConfig {
+    pub fn resolve(config: &ProjectConfig) -> Option<Self> {
+        let Some(input_file) = &config.tailwind_input_file else {
+            return None;
+        };
+        Some(Self {
+            input_file: config.config_relative(input_file),
+            config_file: config
+                .tailwind_config_file
+                .as_ref()
+                .map(|file| config.config_relative(file)),
+        })
+    }
+}
@@ -1,15 +1,17 @@
-use super::Config;
+use super::{Config, ProjectConfig};
 
 fn opts(project: Option<&str>) -> crate::Opts {
     crate::Opts {
-        release: false,
         project: project.map(|s| s.to_string()),
-        verbose: 0,
-        bin_features: Vec::new(),
-        lib_features: Vec::new(),
+        ..Default::default()
     }
 }
 
+/// the config of a package in the working dir, in debug
+fn parse(json: serde_json::Value) -> crate::ext::anyhow::Result<ProjectConfig> {
+    ProjectConfig::parse(camino::Utf8Path::new(""), &json, "debug")
+}
+
 #[test]
 fn test_project() {
     let cli = opts(None);
@@ -59,3 +61,650 @@ fn test_workspace_in_subdir_project2() {
 
     insta::assert_debug_snapshot!(conf);
 }
+
+#[test]
+fn test_assets_dir_one_or_many() {
+    let one: ProjectConfig =
+        serde_json::from_value(serde_json::json!({ "assets-dir": "assets" })).unwrap();
+    assert_eq!(one.assets_dir, vec!["assets"]);
+
+    let many: ProjectConfig =
+        serde_json::from_value(serde_json::json!({ "assets-dir": ["public", "static"] })).unwrap();
+    assert_eq!(many.assets_dir, vec!["public", "static"]);
+
+    let none: ProjectConfig = serde_json::from_value(serde_json::json!({})).unwrap();
+    assert!(none.assets_dir.is_empty());
+}
+
+#[test]
+fn test_style_file_one_or_many() {
+    let json = serde_json::json!({ "style-file": ["style/main.scss", "style/vendor.css"] });
+    let conf = ProjectConfig::parse(camino::Utf8Path::new("project1"), &json, "debug").unwrap();
+    let style = super::StyleConfig::new(&conf).unwrap();
+    assert_eq!(style.file.source, "project1/style/main.scss");
+    assert_eq!(style.additional_files, vec!["project1/style/vendor.css"]);
+
+    let json = serde_json::json!({ "style-file": "style/main.scss" });
+    let conf = parse(json).unwrap();
+    let style = super::StyleConfig::new(&conf).unwrap();
+    assert!(style.additional_files.is_empty());
+}
+
+#[test]
+fn test_sass_load_paths() {
+    let json = serde_json::json!({
+        "style-file": "style/main.scss",
+        "sass-load-paths": ["../styles/lib"],
+    });
+    let conf = ProjectConfig::parse(camino::Utf8Path::new("project1"), &json, "debug").unwrap();
+    let style = super::StyleConfig::new(&conf).unwrap();
+    assert_eq!(style.load_paths, vec!["project1/../styles/lib"]);
+
+    use super::project::workspace_relative;
+    let root = camino::Utf8Path::new("/work/app");
+    let lib = workspace_relative(root, &style.load_paths[0]);
+    assert_eq!(lib, "styles/lib");
+    assert_eq!(workspace_relative(root, "../shared".into()), "/work/shared");
+}
+
+#[test]
+fn test_lock_detects_concurrent_run() {
+    let conf = Config::test_load(opts(None), "examples", "examples/project/Cargo.toml", false);
+
+    let lock = conf.lock().unwrap();
+    let second = conf.lock().unwrap_err();
+    assert!(second
+        .to_string()
+        .contains("Another cargo-leptos is running"));
+
+    drop(lock);
+    assert!(conf.lock().is_ok());
+}
+
+#[test]
+fn test_wasm_opt_level() {
+    let default = parse(serde_json::json!({})).unwrap();
+    assert_eq!(default.wasm_opt_level, "Os");
+
+    let oz = parse(serde_json::json!({ "wasm-opt-level": "Oz" })).unwrap();
+    assert_eq!(oz.wasm_opt_level, "Oz");
+
+    let err = parse(serde_json::json!({ "wasm-opt-level": "O5" })).unwrap_err();
+    assert!(err.to_string().contains("wasm-opt-level cannot be 'O5'"));
+}
+
+#[test]
+fn test_style_file_and_tailwind_conflict() {
+    let json = serde_json::json!({
+        "style-file": "style/main.scss",
+        "tailwind-input-file": "style/tailwind.css",
+    });
+    let err = parse(json).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("style-file and tailwind-input-file cannot both be set"));
+}
+
+#[test]
+fn test_watch_ignore() {
+    let json = serde_json::json!({ "watch-ignore": ["src/generated/**"] });
+    let conf = ProjectConfig::parse(camino::Utf8Path::new("project1"), &json, "debug").unwrap();
+    let ignore = conf.watch_ignore_set().unwrap();
+
+    assert!(ignore.is_match("project1/src/generated/routes.rs"));
+    assert!(!ignore.is_match("project1/src/app.rs"));
+    assert!(!ignore.is_match("src/generated/routes.rs"));
+
+    let json = serde_json::json!({ "watch-ignore": ["src/[gen"] });
+    let conf = parse(json).unwrap();
+    let err = conf.watch_ignore_set().unwrap_err();
+    assert!(err.to_string().contains("Invalid watch-ignore glob"));
+}
+
+#[test]
+fn test_watch_debounce() {
+    use super::project::watch_debounce;
+    let default = parse(serde_json::json!({})).unwrap();
+    assert_eq!(watch_debounce(default.watch_debounce_ms).as_millis(), 200);
+
+    let zero = parse(serde_json::json!({ "watch-debounce-ms": 0 })).unwrap();
+    assert_eq!(watch_debounce(zero.watch_debounce_ms).as_millis(), 10);
+}
+
+#[test]
+fn test_tls_files_set_together() {
+    let json = serde_json::json!({ "tls-cert-file": "cert.pem" });
+    let err = parse(json).unwrap_err();
+    assert!(err.to_string().contains("must be set together"));
+
+    let json = serde_json::json!({ "tls-cert-file": "cert.pem", "tls-key-file": "key.pem" });
+    let conf = ProjectConfig::parse(camino::Utf8Path::new("project1"), &json, "debug").unwrap();
+    let tls = super::TlsConfig::resolve(&conf).unwrap();
+    assert_eq!(tls.cert_file, "project1/cert.pem");
+    assert_eq!(tls.key_file, "project1/key.pem");
+}
+
+#[test]
+fn test_auto_reload_port() {
+    let busy = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = busy.local_addr().unwrap().port();
+    let json = serde_json::json!({ "site-addr": "127.0.0.1:3000", "reload-port": port });
+
+    let conf = parse(json).unwrap();
+    assert_eq!(crate::service::site::Site::new(&conf).reload.port(), port);
+
+    let mut conf = conf;
+    conf.auto_reload_port = true;
+    let site = crate::service::site::Site::new(&conf);
+    assert_ne!(site.reload.port(), port);
+    assert!(std::net::TcpListener::bind(site.reload).is_ok());
+}
+
+#[test]
+fn test_validate_output_name() {
+    use super::project::validate_output_name;
+    assert!(validate_output_name("my_app-2").is_ok());
+
+    let err = validate_output_name("my app").unwrap_err();
+    assert!(err.to_string().contains("' '"), "{err}");
+    assert!(validate_output_name("app:v2").is_err());
+}
+
+#[test]
+fn test_hooks() {
+    let json = serde_json::json!({ "pre-build": "cargo run --bin openapi-gen" });
+    let conf = parse(json).unwrap();
+
+    let pre_build = super::Hook::resolve(&conf, &conf.pre_build).unwrap();
+    assert_eq!(pre_build.cmd, "cargo run --bin openapi-gen");
+    assert_eq!(pre_build.dir, ".");
+    assert!(super::Hook::resolve(&conf, &conf.post_build).is_none());
+}
+
+#[test]
+fn test_split_workspace() {
+    // the fixture has no dependencies, it resolves without the network
+    let cli = crate::Opts {
+        offline: true,
+        ..opts(None)
+    };
+    let conf = Config::test_load(
+        cli,
+        "examples",
+        "examples/split-workspace/Cargo.toml",
+        false,
+    );
+    let proj = &conf.projects[0];
+    assert_eq!(proj.lib.name, "app-frontend");
+    assert_eq!(proj.lib.rel_dir, "app-frontend");
+    assert_eq!(proj.bin.as_ref().unwrap().name, "app-server");
+    assert_eq!(proj.bin.as_ref().unwrap().rel_dir, "app-server");
+}
+
+#[test]
+fn test_split_workspace_packages_not_found() {
+    use crate::ext::MetadataExt;
+    let manifest = camino::Utf8PathBuf::from("examples/split-workspace/Cargo.toml");
+    let metadata = cargo_metadata::Metadata::load_cleaned(&manifest, Vec::new()).unwrap();
+    let check = |bin: &str, lib: &str| {
+        let json = serde_json::json!({ "name": "app", "bin-package": bin, "lib-package": lib });
+        let def: super::project::ProjectDefinition = serde_json::from_value(json).unwrap();
+        def.check_packages(&metadata, false)
+            .unwrap_err()
+            .to_string()
+    };
+
+    let err = check("server", "app-frontend");
+    assert!(
+        err.starts_with(r#"Could not find the bin-package "server""#),
+        "{err}"
+    );
+    assert!(err.ends_with("app-frontend, app-server"), "{err}");
+
+    let err = check("app-server", "front");
+    assert!(
+        err.starts_with(r#"Could not find the lib-package "front""#),
+        "{err}"
+    );
+
+    let err = check("app-frontend", "app-server");
+    assert!(err.contains("seem to be swapped"), "{err}");
+}
+
+#[test]
+fn test_wasm_bindgen_version() {
+    let conf = Config::test_load(opts(None), "examples", "examples/project/Cargo.toml", false);
+    let version = conf.projects[0].lib.wasm_bindgen_version.as_ref().unwrap();
+    assert!(version.starts_with("0.2."), "{version}");
+}
+
+#[test]
+fn test_site_addr_override() {
+    let mut cli = opts(None);
+    cli.port = Some(8080);
+    let conf = Config::test_load(cli, "examples", "examples/project/Cargo.toml", false);
+    assert_eq!(conf.projects[0].site.addr.to_string(), "127.0.0.1:8080");
+
+    let mut cli = opts(None);
+    cli.host = Some("0.0.0.0".parse().unwrap());
+    let conf = Config::test_load(cli, "examples", "examples/project/Cargo.toml", false);
+    assert_eq!(conf.projects[0].site.addr.to_string(), "0.0.0.0:3000");
+
+    let err = crate::parse_site_addr("localhost:3000").unwrap_err();
+    assert!(err.contains("ex: 0.0.0.0:3000"), "{err}");
+}
+
+#[test]
+fn test_graceful_shutdown_timeout() {
+    let json = serde_json::json!({ "graceful-shutdown-timeout": 5000 });
+    let conf = parse(json).unwrap();
+    assert_eq!(conf.graceful_shutdown_timeout, Some(5000));
+
+    let conf = Config::test_load(opts(None), "examples", "examples/project/Cargo.toml", false);
+    assert_eq!(conf.projects[0].graceful_shutdown_timeout, None);
+}
+
+#[test]
+fn test_exit_on_no_clients() {
+    let json = serde_json::json!({ "exit-on-no-clients": 30000 });
+    let conf = parse(json).unwrap();
+    assert_eq!(conf.exit_on_no_clients, Some(30000));
+
+    let conf = Config::test_load(opts(None), "examples", "examples/project/Cargo.toml", false);
+    assert_eq!(conf.projects[0].exit_on_no_clients, None);
+}
+
+#[test]
+fn test_server_run_cmd() {
+    use super::server_run_args;
+    let exe = camino::Utf8Path::new("target/server/debug/app_leptos");
+    assert_eq!(server_run_args(None, exe), vec![exe.to_string()]);
+    assert_eq!(
+        server_run_args(
+            Some("systemfd --no-pid -s http::3000 -- {exe} --verbose"),
+            exe
+        ),
+        vec![
+            "systemfd",
+            "--no-pid",
+            "-s",
+            "http::3000",
+            "--",
+            "target/server/debug/app_leptos",
+            "--verbose"
+        ]
+    );
+
+    let conf = parse(serde_json::json!({ "server-run-cmd": "systemfd -- {exe}" })).unwrap();
+    assert_eq!(conf.server_run_cmd.as_deref(), Some("systemfd -- {exe}"));
+
+    let err = parse(serde_json::json!({ "server-run-cmd": "systemfd" })).unwrap_err();
+    assert!(err.to_string().contains("{exe}"), "{err}");
+    let json = serde_json::json!({ "server-run-cmd": "systemfd -- {exe}", "csr-only": true });
+    assert!(parse(json).is_err());
+}
+
+#[test]
+fn test_config_files() {
+    let conf = Config::test_load(opts(None), "examples", "examples/project/Cargo.toml", true);
+    assert_eq!(
+        conf.projects[0].config_files,
+        [".env", ".env.debug", ".env.local", "Cargo.toml"]
+    );
+
+    let conf = Config::test_load(
+        opts(Some("project1")),
+        "examples",
+        "examples/workspace/Cargo.toml",
+        true,
+    );
+    assert_eq!(
+        conf.projects[0].config_files,
+        [
+            ".env",
+            ".env.debug",
+            ".env.local",
+            "Cargo.toml",
+            "project1/front/Cargo.toml",
+            "project1/server/Cargo.toml"
+        ]
+    );
+}
+
+#[test]
+fn test_check_site_root() {
+    use super::project::check_site_root;
+    let root = camino::Utf8Path::new("/work/app");
+    assert!(check_site_root("target/site".into(), root, false).is_ok());
+    assert!(check_site_root("/work/app/dist".into(), root, false).is_ok());
+
+    let err = check_site_root("../www".into(), root, false).unwrap_err();
+    assert!(
+        err.to_string().contains("allow-external-site-root"),
+        "{err}"
+    );
+    assert!(check_site_root("/var/www".into(), root, false).is_err());
+    assert!(check_site_root("/var/www".into(), root, true).is_ok());
+}
+
+#[test]
+fn test_env_output_name() {
+    use super::project::env_output_name;
+    assert_eq!(
+        env_output_name(Some("app"), "project1", 1, None),
+        Some("app")
+    );
+    assert_eq!(env_output_name(None, "project1", 1, None), None);
+    // the workspace of several projects still loads, with their own names
+    assert_eq!(env_output_name(Some("app"), "project1", 2, None), None);
+    assert_eq!(
+        env_output_name(Some("app"), "project2", 2, Some("project1")),
+        None
+    );
+    assert_eq!(
+        env_output_name(Some("app"), "project1", 2, Some("project1")),
+        Some("app")
+    );
+}
+
+#[test]
+fn test_check_site_pkg_dir() {
+    use super::project::check_site_pkg_dir;
+    assert!(check_site_pkg_dir("pkg".into()).is_ok());
+    assert!(check_site_pkg_dir("assets/js".into()).is_ok());
+
+    for dir in ["", ".", "../pkg", "assets/../../pkg", "/pkg"] {
+        let err = check_site_pkg_dir(dir.into()).unwrap_err();
+        assert!(err.to_string().contains("site-pkg-dir"), "{err}");
+    }
+}
+
+#[test]
+fn test_check_reload_ws_path() {
+    use super::project::check_reload_ws_path;
+    assert!(check_reload_ws_path("/live_reload").is_ok());
+    assert!(check_reload_ws_path("/app/_reload").is_ok());
+
+    for path in ["", "live_reload", "/reload/:id", "/reload`+alert(1)+`"] {
+        let err = check_reload_ws_path(path).unwrap_err();
+        assert!(err.to_string().contains("reload-ws-path"), "{err}");
+    }
+}
+
+#[test]
+fn test_check_assets_dirs() {
+    use super::project::check_assets_dirs;
+    let root = camino::Utf8Path::new("/work/app");
+    let site = camino::Utf8Path::new("target/site");
+    assert!(check_assets_dirs(&["assets".into(), "public".into()], site, root).is_ok());
+    assert!(check_assets_dirs(&["target/site-assets".into()], site, root).is_ok());
+
+    let err = check_assets_dirs(&["target/site".into()], site, root).unwrap_err();
+    assert!(err.to_string().contains("overlap"), "{err}");
+    assert!(check_assets_dirs(&["target".into()], site, root).is_err());
+    assert!(check_assets_dirs(&["./target/site/img".into()], site, root).is_err());
+    assert!(check_assets_dirs(&["assets/../target".into()], site, root).is_err());
+}
+
+#[test]
+fn test_env_files_precedence() {
+    let tmp = temp_dir::TempDir::new().unwrap();
+    let dir = camino::Utf8PathBuf::from_path_buf(tmp.path().to_path_buf()).unwrap();
+    let write = |name: &str, content: &str| std::fs::write(dir.join(name), content).unwrap();
+    write(
+        ".env",
+        "LEPTOS_SITE_ROOT=env\nLEPTOS_SITE_PKG_DIR=env\nLEPTOS_RELOAD_PORT=3002",
+    );
+    write(
+        ".env.local",
+        "LEPTOS_SITE_PKG_DIR=local\nLEPTOS_RELOAD_PORT=3003",
+    );
+    write(".env.release", "LEPTOS_RELOAD_PORT=3004");
+    write(".env.debug", "LEPTOS_SITE_ROOT=debug");
+
+    let json = serde_json::json!({});
+    let conf = ProjectConfig::parse(&dir, &json, "release").unwrap();
+    assert_eq!(conf.site_root, "env");
+    assert_eq!(conf.site_pkg_dir, "local");
+    assert_eq!(conf.reload_port, 3004);
+
+    let conf = ProjectConfig::parse(&dir, &json, "debug").unwrap();
+    assert_eq!(conf.site_root, "debug");
+    assert_eq!(conf.reload_port, 3003);
+}
+
+#[test]
+fn test_tool_versions() {
+    let conf = parse(serde_json::json!({ "sass-version": "1.62.0" })).unwrap();
+    assert_eq!(conf.sass_version.as_deref(), Some("1.62.0"));
+
+    let err = parse(serde_json::json!({ "wasm-opt-version": "../111" })).unwrap_err();
+    assert!(err.to_string().starts_with("wasm-opt-version"), "{err}");
+}
+
+#[test]
+fn test_external_target_dir() {
+    use crate::ext::{MetadataExt, PathBufExt};
+    use camino::Utf8PathBuf;
+    use cargo_metadata::Metadata;
+
+    let manifest = Utf8PathBuf::from("examples/project/Cargo.toml")
+        .canonicalize_utf8()
+        .unwrap();
+    let mut metadata = Metadata::load_cleaned(&manifest, Vec::new()).unwrap();
+    // as with CARGO_TARGET_DIR=/tmp/shared-target
+    metadata.target_directory = "/tmp/shared-target".into();
+    let cwd = manifest.clone().without_last();
+    let projects = super::Project::resolve(&opts(None), &cwd, &metadata, false).unwrap();
+    let proj = &projects[0];
+
+    assert_eq!(proj.target_dir, "/tmp/shared-target");
+    assert_eq!(proj.lib.target_dir, "/tmp/shared-target/front");
+    assert_eq!(
+        proj.lib.wasm_file.source,
+        "/tmp/shared-target/front/wasm32-unknown-unknown/debug/example.wasm"
+    );
+    assert_eq!(
+        proj.bin.as_ref().unwrap().target_dir,
+        "/tmp/shared-target/server"
+    );
+    assert_eq!(proj.tmp_dir, "/tmp/shared-target/tmp/example");
+}
+
+#[test]
+fn test_wasm_features() {
+    let mut conf = Config::test_load(opts(None), "examples", "examples/project/Cargo.toml", false);
+    let lib = &mut std::sync::Arc::get_mut(&mut conf.projects[0]).unwrap().lib;
+    assert!(lib.wasm_opt_features().is_empty());
+
+    assert_eq!(lib.wasm_opt_args(), ["-Os"]);
+
+    lib.wasm_reference_types = Some(false);
+    lib.wasm_multi_value = Some(true);
+    assert_eq!(
+        lib.wasm_opt_features(),
+        ["--disable-reference-types", "--enable-multivalue"]
+    );
+    lib.wasm_opt_args = Some(vec!["-O2".to_string(), "--debuginfo".to_string()]);
+    assert_eq!(
+        lib.wasm_opt_args(),
+        [
+            "-O2",
+            "--debuginfo",
+            "--disable-reference-types",
+            "--enable-multivalue"
+        ]
+    );
+
+    lib.wasm_debug = true;
+    assert_eq!(lib.wasm_opt_args()[..2], ["-O2", "--debuginfo"]);
+    assert_eq!(lib.wasm_opt_args().len(), 4);
+    lib.wasm_opt_args = None;
+    assert_eq!(lib.wasm_opt_args()[0], "-Os");
+    assert_eq!(lib.wasm_opt_args().last().unwrap(), "--debuginfo");
+}
+
+#[test]
+fn test_wasm_opt_args() {
+    assert!(parse(serde_json::json!({ "wasm-opt-args": ["-O2", "--debuginfo"] })).is_ok());
+    for arg in ["-o", "--output=app.wasm", "app.wasm"] {
+        let err = parse(serde_json::json!({ "wasm-opt-args": ["-O2", arg] })).unwrap_err();
+        assert!(err.to_string().starts_with("wasm-opt-args"), "{err}");
+    }
+}
+
+#[test]
+fn test_end2end_ready() {
+    let json = serde_json::json!({ "end2end-cmd": "npx playwright test", "end2end-ready-path": "/health" });
+    let conf = parse(json).unwrap();
+    let e2e = super::End2EndConfig::resolve(&conf).unwrap();
+    assert_eq!(e2e.ready_timeout.as_secs(), 60);
+    assert_eq!(e2e.ready_path.as_deref(), Some("/health"));
+}
+
+#[test]
+fn test_cargo_args() {
+    use super::project::cargo_args;
+    let cli = crate::Opts {
+        offline: true,
+        cargo_args: vec!["-Zbuild-std".to_string()],
+        ..opts(None)
+    };
+    let args = cargo_args(&["--frozen".to_string()], &cli).unwrap();
+    assert_eq!(args, ["--offline", "--frozen", "-Zbuild-std"]);
+
+    let cli = crate::Opts {
+        cargo_args: vec!["--features=csr".to_string()],
+        ..opts(None)
+    };
+    let err = cargo_args(&[], &cli).unwrap_err();
+    assert!(
+        err.to_string().contains("lib-features/bin-features"),
+        "{err}"
+    );
+    assert!(cargo_args(&["-r".to_string()], &opts(None)).is_err());
+}
+
+#[test]
+fn test_spa_fallback() {
+    let conf = Config::test_load(opts(None), "examples", "examples/project/Cargo.toml", false);
+    assert!(!conf.projects[0].spa_fallback);
+
+    let json = serde_json::json!({ "spa-fallback": true });
+    let err = parse(json).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "spa-fallback requires csr-only: the server of the app serves its routes."
+    );
+}
+
+#[test]
+fn test_csr_only() {
+    use crate::ext::{MetadataExt, PathBufExt};
+    use camino::Utf8PathBuf;
+    use cargo_metadata::Metadata;
+
+    let manifest = Utf8PathBuf::from("examples/project/Cargo.toml")
+        .canonicalize_utf8()
+        .unwrap();
+    let mut metadata = Metadata::load_cleaned(&manifest, Vec::new()).unwrap();
+    let package = metadata
+        .packages
+        .iter_mut()
+        .find(|p| p.name == "example")
+        .unwrap();
+    package.metadata["leptos"]["csr-only"] = true.into();
+    // a csr app has no server
+    package.targets.retain(|t| !t.is_bin());
+    let cwd = manifest.clone().without_last();
+    let projects = super::Project::resolve(&opts(None), &cwd, &metadata, false).unwrap();
+    let proj = &projects[0];
+
+    assert!(proj.bin.is_none());
+    assert!(proj.spa_fallback);
+    assert!(proj.hash_file.is_none());
+    assert!(!proj
+        .to_envs()
+        .iter()
+        .any(|(k, _)| k.starts_with("LEPTOS_BIN")));
+
+    let package = metadata
+        .packages
+        .iter_mut()
+        .find(|p| p.name == "example")
+        .unwrap();
+    package.metadata["leptos"]["hash-files"] = true.into();
+    let err = super::Project::resolve(&opts(None), &cwd, &metadata, false).unwrap_err();
+    assert!(format!("{err:#}").contains("hash-files with csr-only requires an index-file"));
+}
+
+#[test]
+fn test_cache_control() {
+    let resolve = |json| super::CacheControl::resolve(&parse(json)?);
+    let cache = resolve(serde_json::json!({
+        "csr-only": true,
+        "index-file": "index.html",
+        "output-name": "app",
+        "hash-files": true,
+        "cache-control": [{ "path": "pkg/*.wasm", "value": "max-age=60" }]
+    }))
+    .unwrap();
+    assert_eq!(cache.value("pkg/app.wasm"), "max-age=60");
+    assert_eq!(
+        cache.value("pkg/app.1f2e3d.js"),
+        "public, max-age=31536000, immutable"
+    );
+    assert_eq!(cache.value("pkg/app.js"), "no-cache");
+    assert_eq!(cache.value("index.html"), "no-cache");
+
+    let err = resolve(serde_json::json!({
+        "cache-control": [{ "path": "*", "value": "no-store" }]
+    }))
+    .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "cache-control requires csr-only: the server of the app sets its headers."
+    );
+}
+
+#[test]
+fn test_proxy() {
+    let resolve = |json| super::Proxy::resolve(&parse(json)?);
+    let proxy = resolve(serde_json::json!({
+        "csr-only": true,
+        "proxy": { "/api": "http://127.0.0.1:8080", "/api/auth/": "http://127.0.0.1:9000" }
+    }))
+    .unwrap();
+    let upstream = |uri: &str| {
+        let uri = proxy.upstream(&uri.parse().unwrap())?;
+        Some(uri.to_string())
+    };
+    assert_eq!(
+        upstream("/api/users?page=2").unwrap(),
+        "http://127.0.0.1:8080/api/users?page=2"
+    );
+    assert_eq!(upstream("/api").unwrap(), "http://127.0.0.1:8080/api");
+    // the longest prefix wins
+    assert_eq!(
+        upstream("/api/auth/login").unwrap(),
+        "http://127.0.0.1:9000/api/auth/login"
+    );
+    assert_eq!(upstream("/apis"), None);
+    assert_eq!(upstream("/pkg/app.js"), None);
+
+    for (path, upstream, expected) in [
+        ("api", "http://127.0.0.1:8080", "must start with '/'"),
+        ("/api", "https://example.com", "must be an http url"),
+        ("/api", "http://127.0.0.1:8080/v1", "must be an http url"),
+    ] {
+        let json = serde_json::json!({ "csr-only": true, "proxy": { path: upstream } });
+        let err = resolve(json).unwrap_err();
+        assert!(err.to_string().contains(expected), "{err}");
+    }
+    let json = serde_json::json!({ "proxy": { "/api": "http://127.0.0.1:8080" } });
+    let err = parse(json).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "proxy requires csr-only: the server of the app handles its routes."
+    );
+}
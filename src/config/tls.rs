@@ -0,0 +1,23 @@
+use camino::Utf8PathBuf;
+
+use super::ProjectConfig;
+
+/// the certificate and key used by the reload server for serving wss://
+#[derive(Debug)]
+pub struct TlsConfig {
+    /// PEM encoded certificate chain
+    pub cert_file: Utf8PathBuf,
+    /// PEM encoded private key
+    pub key_file: Utf8PathBuf,
+}
+
+impl TlsConfig {
+    pub fn resolve(config: &ProjectConfig) -> Option<Self> {
+        let cert_file = config.tls_cert_file.as_ref()?;
+        let key_file = config.tls_key_file.as_ref()?;
+        Some(Self {
+            cert_file: config.config_relative(cert_file),
+            key_file: config.config_relative(key_file),
+        })
+    }
+}
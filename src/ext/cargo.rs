@@ -47,17 +47,21 @@ impl PackageExt for Package {
 }
 
 pub trait MetadataExt {
-    fn load_cleaned(manifest_path: &Utf8Path) -> Result<Metadata>;
+    fn load_cleaned(manifest_path: &Utf8Path, cargo_flags: Vec<String>) -> Result<Metadata>;
     fn rel_target_dir(&self) -> Utf8PathBuf;
     fn package_for(&self, id: &PackageId) -> Option<&Package>;
     fn path_dependencies(&self, id: &PackageId) -> Vec<Utf8PathBuf>;
     fn src_path_dependencies(&self, id: &PackageId) -> Vec<Utf8PathBuf>;
+    fn resolved_dependency(&self, id: &PackageId, name: &str) -> Option<&Package>;
 }
 
 impl MetadataExt for Metadata {
 
-    fn load_cleaned(manifest_path: &Utf8Path) -> Result<Metadata> {
-        let mut metadata = MetadataCommand::new().manifest_path(manifest_path).exec()?;
+    fn load_cleaned(manifest_path: &Utf8Path, cargo_flags: Vec<String>) -> Result<Metadata> {
+        let mut metadata = MetadataCommand::new()
+            .manifest_path(manifest_path)
+            .other_options(cargo_flags)
+            .exec()?;
         metadata.workspace_root.clean_windows_path();
         metadata.target_directory.clean_windows_path();
         for package in &mut metadata.packages {
@@ -70,7 +74,10 @@ impl MetadataExt for Metadata {
     }
 
     fn rel_target_dir(&self) -> Utf8PathBuf {
-        self.target_directory.clone().unbase(&self.workspace_root).unwrap()
+        // a CARGO_TARGET_DIR outside the workspace is kept absolute
+        self.target_directory
+            .unbase(&self.workspace_root)
+            .unwrap_or_else(|_| self.target_directory.clone())
     }
 
     fn package_for(&self, id: &PackageId) -> Option<&Package> {
@@ -100,6 +107,16 @@ impl MetadataExt for Metadata {
         self.path_dependencies(id).iter().map(|p| p.unbase(root).unwrap_or_else(|_| 
             p.to_path_buf()).join("src")).collect()
     }
+
+    /// the package with the name in the resolved dependency tree of the package
+    fn resolved_dependency(&self, id: &PackageId, name: &str) -> Option<&Package> {
+        let resolve = self.resolve.as_ref()?;
+        let mut set = HashSet::new();
+        resolve.deps_for(id, &mut set);
+        self.packages
+            .iter()
+            .find(|p| p.name == name && set.contains(&p.id))
+    }
 }
 
 pub trait ResolveExt {
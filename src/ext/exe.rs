@@ -6,17 +6,34 @@ use axum::body::Bytes;
 use std::{
     io::Cursor,
     path::{Path, PathBuf},
+    time::{Duration, Instant},
 };
 use zip::ZipArchive;
 
 use super::util::os_arch;
 
+/// a download is aborted when no bytes are received for this long
+const DOWNLOAD_STALL_TIMEOUT: Duration = Duration::from_secs(30);
+/// a download is aborted when not finished within this time
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(600);
+/// the number of times a download is tried before giving up
+const DOWNLOAD_ATTEMPTS: usize = 3;
+/// the wait before the second attempt, doubled for each following attempt
+const DOWNLOAD_BACKOFF: Duration = Duration::from_secs(2);
+/// the download progress is logged for downloads larger than this
+const LARGE_DOWNLOAD: u64 = 1024 * 1024;
+/// the downloads from github are made from this base url instead, ex: an internal artifact store
+const MIRROR_ENV: &str = "LEPTOS_BINARY_MIRROR";
+const GITHUB: &str = "https://github.com";
+
 #[derive(Debug)]
 pub struct ExeMeta {
     cache_dir: PathBuf,
     name: &'static str,
-    version: &'static str,
+    version: String,
     url: String,
+    /// the url of the published sha256 of the archive, if any
+    checksum_url: Option<String>,
     exe: String,
     manual: &'static str,
 }
@@ -46,15 +63,62 @@ impl ExeMeta {
     }
 
     async fn fetch_archive(&self) -> Result<Bytes> {
-        log::debug!(
-            "Install downloading {} {}",
-            self.name,
-            GRAY.paint(&self.url)
-        );
-        let data = reqwest::get(&self.url).await?.bytes().await?;
+        let mirror = std::env::var(MIRROR_ENV).ok();
+        let url = mirror_url(&self.url, mirror.as_deref());
+        let data = match self.fetch_with_retries(&url).await {
+            Err(e) if is_not_found(&e) => bail!(
+                "Release {} of {} not found at {url}. Check the configured {} version.",
+                self.version,
+                self.name,
+                self.name
+            ),
+            res => res.context(format!(
+                "Could not download {url} into the cache dir {:?}",
+                self.cache_dir
+            ))?,
+        };
+
+        if let Some(checksum_url) = &self.checksum_url {
+            let checksum_url = mirror_url(checksum_url, mirror.as_deref());
+            match self.fetch_with_retries(&checksum_url).await {
+                // older releases might not publish it
+                Err(e) if is_not_found(&e) => {
+                    log::debug!(
+                        "Install no checksum found for {} {}",
+                        self.name,
+                        self.version
+                    )
+                }
+                checksum => {
+                    let checksum = checksum
+                        .context(format!("Could not download the checksum {checksum_url}"))?;
+                    verify_sha256(&data, &checksum).context(format!("Checksum of {url}"))?;
+                    log::debug!("Install verified checksum of {}", self.name);
+                }
+            }
+        }
         Ok(data)
     }
 
+    async fn fetch_with_retries(&self, url: &str) -> Result<Bytes> {
+        let mut attempt = 1;
+        loop {
+            log::debug!("Install downloading {} {}", self.name, GRAY.paint(url));
+            match fetch(url, DOWNLOAD_STALL_TIMEOUT, DOWNLOAD_TIMEOUT).await {
+                Err(e) if attempt < DOWNLOAD_ATTEMPTS && !is_not_found(&e) => {
+                    let backoff = DOWNLOAD_BACKOFF * 2u32.pow(attempt as u32 - 1);
+                    log::warn!(
+                        "Install download of {} failed (attempt {attempt}/{DOWNLOAD_ATTEMPTS}), retrying in {backoff:?}: {e:#}",
+                        self.name
+                    );
+                    tokio::time::sleep(backoff).await;
+                    attempt += 1;
+                }
+                res => return res,
+            }
+        }
+    }
+
     fn extract_archive(&self, data: &Bytes) -> Result<()> {
         let dest_dir = &self.get_exe_dir_path();
 
@@ -62,8 +126,11 @@ impl ExeMeta {
             extract_zip(data, &dest_dir)?;
         } else if self.url.ends_with(".tar.gz") {
             extract_tar(data, &dest_dir)?;
+        } else if self.url.ends_with(".tar.bz2") {
+            extract_tar_bz2(data, dest_dir)?;
         } else {
-            bail!("The download URL does not contain either '.tar.gz' or '.zip' extension");
+            // not an archive but the executable itself
+            write_exe(data, &dest_dir.join(&self.exe))?;
         }
 
         log::debug!(
@@ -76,12 +143,15 @@ impl ExeMeta {
     }
 
     async fn download(&self) -> Result<PathBuf> {
-        log::info!("Command installing {} ...", self.get_name());
+        log::info!(
+            "Command downloading {} (only done once, it is then cached) ...",
+            self.get_name()
+        );
 
         let data = self
             .fetch_archive()
             .await
-            .context(format!("Could not download {}", self.get_name()))?;
+            .context(format!("Could not install {}", self.get_name()))?;
         self.extract_archive(&data)
             .context(format!("Could not extract {}", self.get_name()))?;
 
@@ -102,6 +172,88 @@ impl ExeMeta {
     }
 }
 
+fn is_not_found(e: &anyhow::Error) -> bool {
+    e.downcast_ref::<reqwest::Error>()
+        .and_then(reqwest::Error::status)
+        == Some(reqwest::StatusCode::NOT_FOUND)
+}
+
+/// the url on the mirror, when the url is on github and a mirror base url is given
+pub(crate) fn mirror_url(url: &str, mirror: Option<&str>) -> String {
+    match (mirror, url.strip_prefix(GITHUB)) {
+        (Some(mirror), Some(path)) => format!("{}{path}", mirror.trim_end_matches('/')),
+        _ => url.to_string(),
+    }
+}
+
+/// checks the data against a checksum file: the hex sha256, optionally followed by the file name
+pub(crate) fn verify_sha256(data: &[u8], checksum: &[u8]) -> Result<()> {
+    let checksum = String::from_utf8_lossy(checksum);
+    let Some(expected) = checksum.split_whitespace().next() else {
+        bail!("The checksum file is empty");
+    };
+    let actual = ring::digest::digest(&ring::digest::SHA256, data)
+        .as_ref()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect::<String>();
+    if !expected.eq_ignore_ascii_case(&actual) {
+        bail!("Expected sha256 {expected} but the download has {actual}");
+    }
+    Ok(())
+}
+
+/// downloads the url, aborting when no bytes are received for the `stall` duration
+/// or when it isn't finished within the `timeout`
+pub(crate) async fn fetch(url: &str, stall: Duration, timeout: Duration) -> Result<Bytes> {
+    let start = Instant::now();
+    let mut resp = tokio::time::timeout(stall, reqwest::get(url))
+        .await
+        .context(format!(
+            "Download of {url} stalled: no response for {stall:?}"
+        ))??
+        .error_for_status()?;
+
+    let total = resp.content_length();
+    let mut data = Vec::with_capacity(total.unwrap_or_default() as usize);
+    let mut reported = 0;
+    loop {
+        let remaining = timeout.saturating_sub(start.elapsed());
+        let chunk = match tokio::time::timeout(stall.min(remaining), resp.chunk()).await {
+            Ok(chunk) => chunk.context(format!(
+                "Download of {url} failed after {} bytes received",
+                data.len()
+            ))?,
+            Err(_) if remaining <= stall => bail!(
+                "Download of {url} timed out after {timeout:?} with {} bytes received",
+                data.len()
+            ),
+            Err(_) => bail!(
+                "Download of {url} stalled: no bytes received for {stall:?}, {} bytes received",
+                data.len()
+            ),
+        };
+        let Some(chunk) = chunk else {
+            break;
+        };
+        data.extend_from_slice(&chunk);
+
+        if let Some(total) = total.filter(|t| *t > LARGE_DOWNLOAD) {
+            // log every 10%
+            let percent = data.len() as u64 * 100 / total;
+            if percent / 10 > reported {
+                reported = percent / 10;
+                log::info!(
+                    "Install downloaded {percent}% of {:.1} MB {}",
+                    total as f64 / 1024.0 / 1024.0,
+                    GRAY.paint(url)
+                );
+            }
+        }
+    }
+    Ok(data.into())
+}
+
 // there's a issue in the tar crate: https://github.com/alexcrichton/tar-rs/issues/295
 // It doesn't handle TAR sparse extensions, with data ending up in a GNUSparseFile.0 sub-folder
 fn extract_tar(src: &Bytes, dest: &Path) -> Result<()> {
@@ -112,6 +264,27 @@ fn extract_tar(src: &Bytes, dest: &Path) -> Result<()> {
     Ok(())
 }
 
+fn extract_tar_bz2(src: &Bytes, dest: &Path) -> Result<()> {
+    let content = Cursor::new(src);
+    let dec = bzip2::read::BzDecoder::new(content);
+    let mut arch = tar::Archive::new(dec);
+    arch.unpack(dest).dot()?;
+    Ok(())
+}
+
+fn write_exe(src: &Bytes, dest: &Path) -> Result<()> {
+    if let Some(dir) = dest.parent() {
+        std::fs::create_dir_all(dir).dot()?;
+    }
+    std::fs::write(dest, src).dot()?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(dest, std::fs::Permissions::from_mode(0o755)).dot()?;
+    }
+    Ok(())
+}
+
 fn extract_zip(src: &Bytes, dest: &Path) -> Result<()> {
     let content = Cursor::new(src);
     let mut arch = ZipArchive::new(content).dot()?;
@@ -119,6 +292,45 @@ fn extract_zip(src: &Bytes, dest: &Path) -> Result<()> {
     Ok(())
 }
 
+fn check_executable(path: &Path) -> Result<()> {
+    let meta = std::fs::metadata(path).context(format!("Could not read {path:?}"))?;
+    if !meta.is_file() {
+        bail!("{path:?} is not a file");
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if meta.permissions().mode() & 0o111 == 0 {
+            bail!("{path:?} is not executable");
+        }
+    }
+    Ok(())
+}
+
+/// the first word with a digit in the output of `<exe> --version`
+async fn installed_version(path: &Path) -> Option<String> {
+    let output = tokio::process::Command::new(path)
+        .arg("--version")
+        .output()
+        .await
+        .ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout
+        .split_whitespace()
+        .find(|word| word.chars().any(|c| c.is_ascii_digit()))
+        .map(str::to_string)
+}
+
+/// compares the numbers of the versions, ex: "1.32.0" is older than "1.57.1" and "110" than "version_111"
+pub(crate) fn is_older(version: &str, than: &str) -> bool {
+    let numbers = |v: &str| {
+        v.split(|c: char| !c.is_ascii_digit())
+            .filter_map(|n| n.parse::<u32>().ok())
+            .collect::<Vec<_>>()
+    };
+    numbers(version) < numbers(than)
+}
+
 /// Returns the absolute path to app cache directory.
 ///
 /// May return an error when system cache directory does not exist,
@@ -141,17 +353,35 @@ fn get_cache_dir(name: &str) -> Result<PathBuf> {
     Ok(dir)
 }
 
+/// checks that the downloaded executables can be written to the cache dir
+pub fn check_cache_dir() -> Result<()> {
+    let dir = get_cache_dir("cargo-leptos")?;
+    let probe = dir.join(".write-check");
+    std::fs::write(&probe, "").context(format!("Could not write to {dir:?}"))?;
+    std::fs::remove_file(&probe).context(format!("Could not remove {probe:?}"))?;
+    Ok(())
+}
+
 pub enum Exe {
     CargoGenerate,
+    Grcov,
     Sass,
+    Tailwind,
     WasmOpt,
 }
 
 impl Exe {
     pub async fn get(&self) -> Result<PathBuf> {
-        let exe = self.meta()?;
+        self.get_version(None).await
+    }
+
+    /// the executable of the given version instead of the default one
+    pub async fn get_version(&self, version: Option<&str>) -> Result<PathBuf> {
+        let exe = self.meta(version)?;
 
-        let path = if let Some(path) = exe.from_global_path() {
+        let path = if let Some(path) = self.env_path(&exe).await? {
+            path
+        } else if let Some(path) = exe.from_global_path() {
             path
         } else {
             exe.from_cache().await.context(exe.manual)?
@@ -167,12 +397,45 @@ impl Exe {
         Ok(path)
     }
 
-    pub fn meta_with_dir(&self, cache_dir: PathBuf) -> Result<ExeMeta> {
+    /// the env var giving the path of a pre-installed executable, skipping the download
+    fn path_env(&self) -> Option<&'static str> {
+        match self {
+            Exe::Sass => Some("LEPTOS_SASS_PATH"),
+            Exe::WasmOpt => Some("LEPTOS_WASM_OPT_PATH"),
+            _ => None,
+        }
+    }
+
+    async fn env_path(&self, exe: &ExeMeta) -> Result<Option<PathBuf>> {
+        let Some(var) = self.path_env() else {
+            return Ok(None);
+        };
+        let Some(path) = std::env::var_os(var).map(PathBuf::from) else {
+            return Ok(None);
+        };
+        check_executable(&path).context(format!("Invalid {var}"))?;
+
+        match installed_version(&path).await {
+            Some(found) if is_older(&found, &exe.version) => log::warn!(
+                "Command {} {found} given by {var} is older than the expected {}",
+                exe.name,
+                exe.version
+            ),
+            Some(_) => {}
+            None => log::warn!(
+                "Command could not get the version of {} given by {var}",
+                exe.name
+            ),
+        }
+        Ok(Some(path))
+    }
+
+    pub fn meta_with_dir(&self, cache_dir: PathBuf, version: Option<&str>) -> Result<ExeMeta> {
         let (target_os, target_arch) = os_arch().unwrap();
 
         let exe = match self {
             Exe::CargoGenerate => {
-                let version = "0.17.3";
+                let version = version.unwrap_or("0.17.3");
 
                 let target = match (target_os, target_arch) {
                     ("macos", "aarch64") => "aarch64-apple-darwin",
@@ -191,14 +454,39 @@ impl Exe {
                 ExeMeta {
                     cache_dir: cache_dir.clone(),
                     name: "cargo-generate",
-                    version,
+                    version: version.to_string(),
                     url,
+                    checksum_url: None,
                     exe,
                     manual: "Try manually installing cargo-generate: https://github.com/cargo-generate/cargo-generate#installation"
                 }
             }
+            Exe::Grcov => {
+                let version = version.unwrap_or("0.8.19");
+                let url = match (target_os, target_arch) {
+                    ("windows", "x86_64") => format!("https://github.com/mozilla/grcov/releases/download/v{version}/grcov-x86_64-pc-windows-msvc.zip"),
+                    ("macos", "x86_64") => format!("https://github.com/mozilla/grcov/releases/download/v{version}/grcov-x86_64-apple-darwin.tar.bz2"),
+                    ("macos", "aarch64") => format!("https://github.com/mozilla/grcov/releases/download/v{version}/grcov-aarch64-apple-darwin.tar.bz2"),
+                    ("linux", "x86_64") => format!("https://github.com/mozilla/grcov/releases/download/v{version}/grcov-x86_64-unknown-linux-gnu.tar.bz2"),
+                    ("linux", "aarch64") => format!("https://github.com/mozilla/grcov/releases/download/v{version}/grcov-aarch64-unknown-linux-gnu.tar.bz2"),
+                    _ => bail!("No grcov binary found for {target_os} {target_arch}")
+                };
+                let exe = match target_os {
+                    "windows" => "grcov.exe".to_string(),
+                    _ => "grcov".to_string(),
+                };
+                ExeMeta {
+                    cache_dir: cache_dir.clone(),
+                    name: "grcov",
+                    version: version.to_string(),
+                    url,
+                    checksum_url: None,
+                    exe,
+                    manual: "Try manually installing grcov: https://github.com/mozilla/grcov#how-to-get-grcov",
+                }
+            }
             Exe::Sass => {
-                let version = "1.57.1";
+                let version = version.unwrap_or("1.57.1");
                 let url = match (target_os, target_arch) {
                     ("windows", "x86_64") => format!("https://github.com/sass/dart-sass/releases/download/{version}/dart-sass-{version}-windows-x64.zip"),
                     ("macos" | "linux", "x86_64") => format!("https://github.com/sass/dart-sass/releases/download/{version}/dart-sass-{version}-{target_os}-x64.tar.gz"),
@@ -212,14 +500,40 @@ impl Exe {
                 ExeMeta {
                     cache_dir: cache_dir.clone(),
                     name: "sass",
-                    version,
+                    version: version.to_string(),
                     url,
+                    checksum_url: None,
                     exe,
                     manual: "Try manually installing sass: https://sass-lang.com/install",
                 }
             }
+            Exe::Tailwind => {
+                let version = version.unwrap_or("v3.2.4");
+                let target = match (target_os, target_arch) {
+                    ("windows", "x86_64") => "windows-x64.exe",
+                    ("macos", "x86_64") => "macos-x64",
+                    ("macos", "aarch64") => "macos-arm64",
+                    ("linux", "x86_64") => "linux-x64",
+                    ("linux", "aarch64") => "linux-arm64",
+                    _ => bail!("No tailwindcss binary found for {target_os} {target_arch}"),
+                };
+                let url = format!("https://github.com/tailwindlabs/tailwindcss/releases/download/{version}/tailwindcss-{target}");
+                let exe = match target_os {
+                    "windows" => "tailwindcss.exe".to_string(),
+                    _ => "tailwindcss".to_string(),
+                };
+                ExeMeta {
+                    cache_dir: cache_dir.clone(),
+                    name: "tailwindcss",
+                    version: version.to_string(),
+                    url,
+                    checksum_url: None,
+                    exe,
+                    manual: "Try manually installing the tailwindcss standalone CLI: https://tailwindcss.com/blog/standalone-cli",
+                }
+            }
             Exe::WasmOpt => {
-                let version = "version_111";
+                let version = version.unwrap_or("version_111");
                 let target = match (target_os, target_arch) {
                     ("linux", _) => "x86_64-linux",
                     ("windows", _) => "x86_64-windows",
@@ -230,6 +544,8 @@ impl Exe {
                     }
                 };
                 let url = format!("https://github.com/WebAssembly/binaryen/releases/download/{version}/binaryen-{version}-{target}.tar.gz");
+                // binaryen publishes the sha256 next to each archive
+                let checksum_url = Some(format!("{url}.sha256"));
 
                 let exe = match target_os {
                     "windows" => format!("binaryen-{version}/bin/wasm-opt.exe"),
@@ -238,8 +554,9 @@ impl Exe {
                 ExeMeta {
                     cache_dir: cache_dir.clone(),
                     name: "wasm-opt",
-                    version,
+                    version: version.to_string(),
                     url,
+                    checksum_url,
                     exe,
                     manual:
                         "Try manually installing binaryen: https://github.com/WebAssembly/binaryen",
@@ -250,8 +567,8 @@ impl Exe {
         Ok(exe)
     }
 
-    pub fn meta(&self) -> Result<ExeMeta> {
+    pub fn meta(&self, version: Option<&str>) -> Result<ExeMeta> {
         let cache_dir = get_cache_dir("cargo-leptos").expect("Can not get cache directory");
-        self.meta_with_dir(cache_dir)
+        self.meta_with_dir(cache_dir, version)
     }
 }
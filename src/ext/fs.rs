@@ -44,13 +44,6 @@ pub async fn read(path: impl AsRef<Path>) -> Result<Vec<u8>> {
         .context(format!("Could not read {:?}", path.as_ref()))
 }
 
-pub async fn create_dir(path: impl AsRef<Path>) -> Result<()> {
-    log::trace!("FS create_dir {:?}", path.as_ref());
-    fs::create_dir(&path)
-        .await
-        .context(format!("Could not create dir {:?}", path.as_ref()))
-}
-
 pub async fn create_dir_all<P: AsRef<Path>>(path: P) -> Result<()> {
     log::trace!("FS create_dir_all {:?}", path.as_ref());
     fs::create_dir_all(&path)
@@ -102,15 +95,25 @@ pub async fn remove_dir_all<P: AsRef<Path>>(path: P) -> Result<()> {
         .context(format!("Could not remove dir {:?}", path.as_ref()))
 }
 
-pub async fn copy_dir_all(src: impl AsRef<Utf8Path>, dst: impl AsRef<Path>) -> Result<()> {
-    cp_dir_all(&src, &dst).await.context(format!(
+/// copies the src dir recursively, except the entries for which `skip(path, is_dir)`
+/// is true. A skipped dir is not descended into.
+pub async fn copy_dir_all(
+    src: impl AsRef<Utf8Path>,
+    dst: impl AsRef<Path>,
+    skip: impl Fn(&Utf8Path, bool) -> bool,
+) -> Result<()> {
+    cp_dir_all(&src, &dst, skip).await.context(format!(
         "Copy dir recursively from {:?} to {:?}",
         src.as_ref(),
         dst.as_ref()
     ))
 }
 
-async fn cp_dir_all(src: impl AsRef<Utf8Path>, dst: impl AsRef<Path>) -> Result<()> {
+async fn cp_dir_all(
+    src: impl AsRef<Utf8Path>,
+    dst: impl AsRef<Path>,
+    skip: impl Fn(&Utf8Path, bool) -> bool,
+) -> Result<()> {
     let src = src.as_ref();
     let dst = Utf8PathBuf::from_path_buf(dst.as_ref().to_path_buf()).unwrap();
 
@@ -125,9 +128,13 @@ async fn cp_dir_all(src: impl AsRef<Utf8Path>, dst: impl AsRef<Path>) -> Result<
         while let Some(Ok(entry)) = entries.next() {
             let from = entry.path().to_owned();
             let to = from.rebase(&src, &dst)?;
+            let is_dir = entry.file_type()?.is_dir();
+            if skip(&from, is_dir) {
+                continue;
+            }
 
-            if entry.file_type()?.is_dir() {
-                self::create_dir(&to).await?;
+            if is_dir {
+                self::create_dir_all(&to).await?;
                 dirs.push_back(from);
             } else {
                 self::copy(from, to).await?;
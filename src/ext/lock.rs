@@ -0,0 +1,45 @@
+use crate::ext::anyhow::{bail, Context, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use fs2::FileExt;
+use std::fs::{self, File, OpenOptions};
+
+const LOCK_FILE: &str = ".cargo-leptos.lock";
+
+/// An advisory lock on a directory, held for as long as the value lives.
+///
+/// The lock is taken by the OS on the open file, so it is released
+/// when the process exits for any reason, including signals and crashes.
+#[derive(Debug)]
+pub struct DirLock {
+    file: File,
+    path: Utf8PathBuf,
+}
+
+impl DirLock {
+    pub fn acquire(dir: &Utf8Path) -> Result<Self> {
+        fs::create_dir_all(dir).context(format!("Could not create {dir:?}"))?;
+        let path = dir.join(LOCK_FILE);
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .context(format!("Could not open lock file {path:?}"))?;
+
+        if file.try_lock_exclusive().is_err() {
+            bail!("Another cargo-leptos is running and holds the lock {path:?}. Wait for it to finish or stop it before trying again")
+        }
+        log::trace!("Lock acquired {path:?}");
+        Ok(Self { file, path })
+    }
+}
+
+impl Drop for DirLock {
+    fn drop(&mut self) {
+        if let Err(e) = FileExt::unlock(&self.file) {
+            log::warn!("Lock could not release {:?}: {e}", self.path);
+        } else {
+            log::trace!("Lock released {:?}", self.path);
+        }
+    }
+}
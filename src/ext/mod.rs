@@ -5,11 +5,13 @@ pub mod anyhow;
 mod cargo;
 pub mod exe;
 pub mod fs;
+mod lock;
 mod path;
 pub mod sync;
 mod util;
 
 pub use cargo::{MetadataExt, PackageExt};
 pub use exe::{Exe, ExeMeta};
+pub use lock::DirLock;
 pub use path::{remove_nested, append_str_to_filename, determine_pdb_filename, PathBufExt, PathExt};
 pub use util::{os_arch, StrAdditions};
@@ -193,7 +193,7 @@ pub fn remove_nested(paths: impl Iterator<Item = Utf8PathBuf>) -> Vec<Utf8PathBu
 ///
 /// # Example
 ///
-/// ```
+/// ```ignore
 /// let path: Utf8PathBuf = "foo.bar".into();
 /// assert_eq!(append_str_to_filename(path, "_bazz")?.as_str(), "foo_bazz.bar");
 /// let path: Utf8PathBuf = "a".into();
@@ -1,4 +1,5 @@
 use crate::ext::anyhow::{bail, Context, Result};
+use crate::logger::{self, Event};
 use std::{net::SocketAddr, time::Duration};
 use tokio::{net::TcpStream, process::Child, sync::broadcast, time::sleep};
 
@@ -16,6 +17,9 @@ pub async fn wait_interruptible(
                     Ok(true)
                 } else {
                     log::trace!("{name} process finished with code {:?}", exit.code());
+                    logger::emit(Event::BuildFailed {
+                        error: format!("{name} exited with code {:?}", exit.code()),
+                    });
                     Ok(false)
                 }
             }
@@ -1,12 +1,19 @@
-use super::exe::Exe;
+use super::exe::{fetch, is_older, mirror_url, verify_sha256, Exe};
 use crate::ext::path::PathBufExt;
 use camino::Utf8PathBuf;
+use std::time::Duration;
 use temp_dir::TempDir;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
 
 #[tokio::test]
 async fn download_sass() {
     let dir = TempDir::new().unwrap();
-    let meta = Exe::Sass.meta_with_dir(dir.path().to_path_buf()).unwrap();
+    let meta = Exe::Sass
+        .meta_with_dir(dir.path().to_path_buf(), None)
+        .unwrap();
     let e = meta.from_cache().await;
     assert!(e.is_ok(), "{e:#?}\n{:#?}\nFiles: \n {}", meta, ls(&dir));
 
@@ -18,7 +25,7 @@ async fn download_sass() {
 async fn download_cargo_generate() {
     let dir = TempDir::new().unwrap();
     let meta = Exe::CargoGenerate
-        .meta_with_dir(dir.path().to_path_buf())
+        .meta_with_dir(dir.path().to_path_buf(), None)
         .unwrap();
 
     let e = meta.from_cache().await;
@@ -32,7 +39,7 @@ async fn download_cargo_generate() {
 async fn download_wasmopt() {
     let dir = TempDir::new().unwrap();
     let meta = Exe::WasmOpt
-        .meta_with_dir(dir.path().to_path_buf())
+        .meta_with_dir(dir.path().to_path_buf(), None)
         .unwrap();
     let e = meta.from_cache().await;
     assert!(e.is_ok(), "{e:#?}\n{:#?}\nFiles: \n {}", meta, ls(&dir));
@@ -47,3 +54,93 @@ fn ls(dir: &TempDir) -> String {
         .ls_ascii(0)
         .unwrap_or_default()
 }
+
+#[tokio::test]
+async fn download_grcov() {
+    let dir = TempDir::new().unwrap();
+    let meta = Exe::Grcov
+        .meta_with_dir(dir.path().to_path_buf(), None)
+        .unwrap();
+    let e = meta.from_cache().await;
+    assert!(e.is_ok(), "{e:#?}\n{:#?}\nFiles: \n {}", meta, ls(&dir));
+
+    let e = e.unwrap();
+    assert!(e.exists(), "{:#?}\nFiles: \n{}", meta, ls(&dir));
+}
+
+#[tokio::test]
+async fn download_tailwind() {
+    let dir = TempDir::new().unwrap();
+    let meta = Exe::Tailwind
+        .meta_with_dir(dir.path().to_path_buf(), None)
+        .unwrap();
+    let e = meta.from_cache().await;
+    assert!(e.is_ok(), "{e:#?}\n{:#?}\nFiles: \n {}", meta, ls(&dir));
+
+    let e = e.unwrap();
+    assert!(e.exists(), "{:#?}\nFiles: \n{}", meta, ls(&dir));
+}
+
+#[tokio::test]
+async fn download_stalled() {
+    // a server sending the first 10 of 100 bytes and then nothing
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/stalled", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut request = [0; 1024];
+        let _ = socket.read(&mut request).await.unwrap();
+        socket
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\n0123456789")
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_secs(10)).await;
+    });
+
+    let err = fetch(&url, Duration::from_millis(200), Duration::from_secs(5))
+        .await
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("stalled"), "{err}");
+    assert!(err.contains(&url), "{err}");
+    assert!(err.contains("10 bytes received"), "{err}");
+}
+
+#[test]
+fn download_mirror_and_checksum() {
+    let url = "https://github.com/sass/dart-sass/releases/download/1.57.1/sass.tar.gz";
+    assert_eq!(mirror_url(url, None), url);
+    assert_eq!(
+        mirror_url(url, Some("https://artifacts.corp/github/")),
+        "https://artifacts.corp/github/sass/dart-sass/releases/download/1.57.1/sass.tar.gz"
+    );
+    assert_eq!(
+        mirror_url("https://example.com/a.zip", Some("https://m")),
+        "https://example.com/a.zip"
+    );
+
+    let sha = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+    assert!(verify_sha256(b"abc", format!("{sha}  abc.tar.gz\n").as_bytes()).is_ok());
+    let err = verify_sha256(b"abd", sha.as_bytes()).unwrap_err();
+    assert!(err.to_string().contains(sha), "{err}");
+}
+
+#[test]
+fn installed_version_is_older() {
+    assert!(is_older("1.32.0", "1.57.1"));
+    assert!(!is_older("1.57.1", "1.57.1"));
+    assert!(!is_older("1.60.0", "1.57.1"));
+    assert!(is_older("110", "version_111"));
+    assert!(!is_older("116", "version_111"));
+}
+
+#[test]
+fn pinned_version_in_url_and_cache() {
+    let dir = TempDir::new().unwrap();
+    let meta = Exe::Sass
+        .meta_with_dir(dir.path().to_path_buf(), Some("1.62.0"))
+        .unwrap();
+    let meta = format!("{meta:?}");
+    assert!(meta.contains("/1.62.0/dart-sass-1.62.0-"), "{meta}");
+    assert!(meta.contains(r#"version: "1.62.0""#), "{meta}");
+}
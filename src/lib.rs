@@ -0,0 +1,322 @@
+//! The build logic of cargo-leptos, for the tools driving the builds without shelling out.
+//! [`run`] executes a parsed [`Cli`], like the binary does. Otherwise [`Config::load`] resolves the
+//! projects and the functions of [`command`] build, serve or watch them.
+
+#[cfg(all(test, feature = "full_tests"))]
+mod tests;
+
+pub mod command;
+pub mod compile;
+pub mod config;
+mod ext;
+mod logger;
+pub mod service;
+pub mod signal;
+
+use crate::ext::anyhow::{Context, Result};
+use crate::ext::PathBufExt;
+use crate::logger::GRAY;
+use camino::Utf8PathBuf;
+use clap::{Parser, Subcommand, ValueEnum};
+use command::{BuildCommand, CleanCommand, NewCommand, ServeCommand, TestCommand, WatchCommand};
+pub use config::Config;
+use ext::fs;
+use signal::Interrupt;
+use std::{
+    env,
+    net::{IpAddr, SocketAddr},
+};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum Log {
+    /// WASM build (wasm, wasm-opt, walrus)
+    Wasm,
+    /// Internal reload and csr server (hyper, axum)
+    Server,
+    /// All the details of the style build (sass, tailwind and lightning css)
+    Sass,
+    /// All the decisions of the assets mirroring
+    Assets,
+}
+
+/// How the build progress is reported
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum MessageFormat {
+    /// log lines for humans
+    #[default]
+    Human,
+    /// newline delimited JSON events on stdout, with the logs on stderr
+    Json,
+}
+
+/// The leptos environment, controlling the dev-only features such as the reload script.
+/// It is independent of the release profile, which controls the optimizations.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Env {
+    Dev,
+    Prod,
+}
+
+impl Env {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Dev => "DEV",
+            Self::Prod => "PROD",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Parser, PartialEq, Default)]
+pub struct Opts {
+    /// Build artifacts in release mode, with optimizations.
+    #[arg(short, long)]
+    release: bool,
+
+    /// Which project to use, from a list of projects defined in a workspace
+    #[arg(short, long)]
+    pub project: Option<String>,
+
+    /// The address the server binds to, overriding the site-addr of the config, ex: 0.0.0.0:3000.
+    #[arg(long, value_parser = parse_site_addr, conflicts_with_all = ["host", "port"])]
+    pub site_addr: Option<SocketAddr>,
+
+    /// The ip the server binds to, keeping the port of the site-addr, ex: 0.0.0.0.
+    #[arg(long)]
+    pub host: Option<IpAddr>,
+
+    /// The port the server binds to, keeping the ip of the site-addr.
+    #[arg(long)]
+    pub port: Option<u16>,
+
+    /// The features to use when compiling the lib target
+    #[arg(long)]
+    pub lib_features: Vec<String>,
+
+    /// The features to use when compiling the bin target
+    #[arg(long)]
+    pub bin_features: Vec<String>,
+
+    /// The target triple to cross-compile the server for, ex: aarch64-unknown-linux-gnu. The front is always wasm32-unknown-unknown.
+    #[arg(long)]
+    pub bin_target_triple: Option<String>,
+
+    /// Skip the wasm-opt optimization of the wasm in release mode.
+    #[arg(long)]
+    pub no_wasm_opt: bool,
+
+    /// Set LEPTOS_ENV for the server, independently of --release. When not set, leptos defaults to dev.
+    #[arg(long, value_enum)]
+    pub env: Option<Env>,
+
+    /// Poll for file changes in watch mode, for file systems where the change events are missed (network drives, WSL2, Docker mounts).
+    #[arg(long)]
+    pub poll: bool,
+
+    /// Build the front and the server, and the wasm and the style, one after the other
+    /// instead of concurrently.
+    #[arg(long)]
+    pub no_parallel: bool,
+
+    /// The number of parallel jobs of each cargo build. The front and the server are built
+    /// concurrently, so up to twice as many jobs run at once unless --no-parallel is given.
+    #[arg(short, long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub jobs: Option<u32>,
+
+    /// Don't write the manifest.json, describing the build output, in the site root.
+    #[arg(long)]
+    pub no_manifest: bool,
+
+    /// Clean the site dir and copy all the assets, instead of only copying the changed ones.
+    #[arg(long)]
+    pub force_resync: bool,
+
+    /// Log the largest sections and functions of the wasm after the front build.
+    #[arg(long)]
+    pub size_report: bool,
+
+    /// Open the site in the browser once the server is up. The BROWSER env var overrides the command used.
+    #[arg(long)]
+    pub open: bool,
+
+    /// Fail the front build when the project uses another wasm-bindgen version than cargo-leptos, instead of warning.
+    #[arg(long)]
+    pub strict_wasm_bindgen: bool,
+
+    /// Also report the build progress as newline delimited JSON events on stdout, for editors and tools.
+    #[arg(long, value_enum, default_value_t)]
+    pub message_format: MessageFormat,
+
+    /// Verbosity (none: info, errors & warnings, -v: verbose, --vv: very verbose).
+    #[arg(short, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Only output warnings and errors.
+    #[arg(short, long, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Run cargo, including the cargo metadata resolving the projects, without accessing the network.
+    #[arg(long)]
+    pub offline: bool,
+
+    /// Require the Cargo.lock to be up to date, for cargo and the cargo metadata resolving the projects.
+    #[arg(long)]
+    pub locked: bool,
+
+    /// Additional arguments for the cargo builds of the front and the server, after a --, ex: -- --frozen
+    #[arg(last = true)]
+    pub cargo_args: Vec<String>,
+}
+
+fn parse_site_addr(addr: &str) -> Result<SocketAddr, String> {
+    addr.parse()
+        .map_err(|_| format!("{addr:?} is not a socket address <ip>:<port>, ex: 0.0.0.0:3000"))
+}
+
+impl Opts {
+    pub fn profile(&self) -> String {
+        if self.release { "release" } else { "debug" }.to_string()
+    }
+
+    /// the --offline and --locked flags given to every cargo command
+    pub fn cargo_flags(&self) -> Vec<String> {
+        let mut flags = Vec::new();
+        if self.offline {
+            flags.push("--offline".to_string());
+        }
+        if self.locked {
+            flags.push("--locked".to_string());
+        }
+        flags
+    }
+}
+
+#[derive(Debug, Parser)]
+#[clap(version)]
+pub struct Cli {
+    /// Path to Cargo.toml.
+    #[arg(long)]
+    manifest_path: Option<Utf8PathBuf>,
+
+    /// Output logs from dependencies, or all the logs of a build step (multiple --log accepted).
+    #[arg(long)]
+    log: Vec<Log>,
+
+    #[command(subcommand)]
+    command: Commands,
+}
+
+impl Cli {
+    fn opts(&self) -> Option<Opts> {
+        use Commands::{Build, Clean, Doctor, EndToEnd, New, Serve, Test, Watch};
+        match &self.command {
+            New(_) => None,
+            Clean(clean) => Some(clean.opts.clone()),
+            Test(test) => Some(test.opts.clone()),
+            Watch(watch) => Some(watch.opts.clone()),
+            Serve(serve) => Some(serve.opts.clone()),
+            Build(build) => Some(build.opts.clone()),
+            EndToEnd(opts) | Doctor(opts) => Some(opts.clone()),
+        }
+    }
+}
+
+#[derive(Debug, Subcommand, PartialEq)]
+enum Commands {
+    /// Build the server (feature ssr) and the client (wasm with feature hydrate).
+    Build(BuildCommand),
+    /// Run the cargo tests for app, client and server.
+    Test(TestCommand),
+    /// Start the server and end-2-end tests.
+    EndToEnd(Opts),
+    /// Serve. Defaults to hydrate mode.
+    Serve(ServeCommand),
+    /// Serve and automatically reload when files change.
+    Watch(WatchCommand),
+    /// WIP: Start wizard for creating a new project (using cargo-generate). Ask at Leptos discord before using.
+    New(NewCommand),
+    /// Remove the site root and the front and server target dirs, keeping the rest of the cargo target dir.
+    Clean(CleanCommand),
+    /// Check the environment (wasm target, cache dir, external tools, leptos metadata) and report what to fix.
+    Doctor(Opts),
+}
+
+/// runs the command, as the binary does with the parsed command line
+pub async fn run(args: Cli) -> Result<()> {
+    let res = run_command(args).await;
+    if let Err(e) = &res {
+        logger::emit(logger::Event::BuildFailed {
+            error: format!("{e:#}"),
+        });
+    }
+    res
+}
+
+async fn run_command(args: Cli) -> Result<()> {
+    let (verbose, quiet, message_format) = args
+        .opts()
+        .map(|o| (o.verbose, o.quiet, o.message_format))
+        .unwrap_or_default();
+    logger::setup(verbose, quiet, message_format, &args.log);
+
+    if let Commands::New(new) = &args.command {
+        return new.run().await;
+    }
+
+    let manifest_path = args
+        .manifest_path
+        .to_owned()
+        .unwrap_or_else(|| Utf8PathBuf::from("Cargo.toml"))
+        .resolve_home_dir()
+        .context(format!("manifest_path: {:?}", &args.manifest_path))?;
+    let mut cwd = Utf8PathBuf::from_path_buf(std::env::current_dir().unwrap()).unwrap();
+    cwd.clean_windows_path();
+
+    let opts = args.opts().unwrap();
+
+    if let Commands::Doctor(_) = &args.command {
+        return command::doctor(&opts, &cwd, &manifest_path).await;
+    }
+
+    let watch = matches!(&args.command, Commands::Watch(w) if !w.test);
+    let config = Config::load(opts, &cwd, &manifest_path, watch).dot()?;
+    env::set_current_dir(&config.working_dir).dot()?;
+    log::debug!(
+        "Path working dir {}",
+        GRAY.paint(config.working_dir.as_str())
+    );
+
+    // the test command only writes to the site when running the end2end tests
+    let _lock = match &args.command {
+        Commands::Test(test) if !test.all => None,
+        Commands::Watch(watch) if watch.test => None,
+        // cleaning on upgrade is pointless when everything is removed anyway
+        Commands::Clean(_) => Some(config.lock()?),
+        // the prebuilt site is served as is
+        Commands::Serve(serve) if serve.no_build => Some(config.lock()?),
+        _ => {
+            let lock = config.lock()?;
+            command::clean_on_upgrade(&config).await?;
+            Some(lock)
+        }
+    };
+
+    let _monitor = Interrupt::run_ctrl_c_monitor();
+    use Commands::{Build, Clean, Doctor, EndToEnd, New, Serve, Test, Watch};
+    match args.command {
+        New(_) | Doctor(_) => panic!(),
+        Build(build) => command::build_all(&config, &build).await,
+        Clean(clean) => command::clean_all(&config, &clean).await,
+        Serve(serve) => command::serve(&config.current_project()?, &serve).await,
+        Test(test) => command::test_all(&config, &test).await,
+        EndToEnd(_) => command::end2end_all(&config).await,
+        Watch(watch) if watch.test => {
+            let test = TestCommand {
+                opts: watch.opts,
+                filter: watch.test_filter,
+                ..Default::default()
+            };
+            command::watch_tests(&config.current_project()?, &test).await
+        }
+        Watch(_) => command::watch(&config).await,
+    }
+}
@@ -4,9 +4,14 @@ use flexi_logger::{
     DeferredNow, Level, Record,
 };
 use once_cell::sync::OnceCell;
+use serde::Serialize;
 use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
 
-use crate::{ext::StrAdditions, Log};
+use crate::{ext::StrAdditions, service::status, Log, MessageFormat};
+
+/// if the status line is shown, and has to be cleared before writing a log line
+static STATUS_SHOWN: AtomicBool = AtomicBool::new(false);
 
 // https://gist.github.com/fnky/458719343aabd01cfb17a3a4f7296797
 lazy_static::lazy_static! {
@@ -19,16 +24,73 @@ lazy_static::lazy_static! {
    pub static ref GRAY: ansi_term::Color = Fixed(241);
    pub static ref BOLD: ansi_term::Style = Style::new().bold();
    static ref LOG_SELECT: OnceCell<LogFlag> = OnceCell::new();
+   static ref MESSAGE_FORMAT: OnceCell<MessageFormat> = OnceCell::new();
+}
+
+/// The build progress events written to stdout with --message-format=json.
+/// The logs are written to stderr, so that stdout only holds the events.
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum Event {
+    BuildStarted,
+    WasmDone,
+    StyleDone,
+    ServerCompiled,
+    /// kind is "full" or "style"
+    ReloadSent {
+        kind: &'static str,
+    },
+    BuildFailed {
+        error: String,
+    },
 }
 
-pub fn setup(verbose: u8, logs: &[Log]) {
+/// writes the event as a line of JSON on stdout, when the message format is json
+pub fn emit(event: Event) {
+    status::record(&event);
+    if !is_json() {
+        return;
+    }
+    let line = serde_json::to_string(&event).expect("events are serializable");
+    let mut stdout = std::io::stdout().lock();
+    let _ = writeln!(stdout, "{line}").and_then(|_| stdout.flush());
+}
+
+/// (re)writes the status line, without a line end, so that the next one replaces it
+pub fn status_line(line: &str) {
+    let mut stderr = std::io::stderr().lock();
+    let _ = write!(
+        stderr,
+        "\r\x1b[2K{} {}",
+        INFO_GREEN.paint("Status".pad_left_to(12)),
+        GRAY.paint(line)
+    )
+    .and_then(|_| stderr.flush());
+    STATUS_SHOWN.store(true, Ordering::Relaxed);
+}
+
+/// if the stdout is reserved for the json events
+pub fn is_json() -> bool {
+    MESSAGE_FORMAT.get() == Some(&MessageFormat::Json)
+}
+
+pub fn setup(verbose: u8, quiet: bool, message_format: MessageFormat, logs: &[Log]) {
     let log_level = match verbose {
+        0 if quiet => "warn",
         0 => "info",
         1 => "debug",
         _ => "trace",
     };
 
-    flexi_logger::Logger::try_with_str(log_level)
+    // the selected cargo-leptos modules log everything, whatever the verbosity
+    let spec = logs
+        .iter()
+        .flat_map(Log::modules)
+        .fold(log_level.to_string(), |spec, module| {
+            format!("{spec}, {module}=trace")
+        });
+
+    flexi_logger::Logger::try_with_str(spec)
         .unwrap()
         .filter(Box::new(Filter))
         .format(format)
@@ -36,6 +98,7 @@ pub fn setup(verbose: u8, logs: &[Log]) {
         .unwrap();
 
     LOG_SELECT.set(LogFlag::new(logs)).unwrap();
+    MESSAGE_FORMAT.set(message_format).unwrap();
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -68,6 +131,23 @@ impl Log {
         match self {
             Self::Wasm => 0b0000_0001,
             Self::Server => 0b0000_0010,
+            Self::Sass => 0b0000_0100,
+            Self::Assets => 0b0000_1000,
+        }
+    }
+
+    /// the cargo-leptos modules of the log
+    fn modules(&self) -> &'static [&'static str] {
+        match self {
+            Self::Sass => &[
+                "cargo_leptos::compile::style",
+                "cargo_leptos::compile::tailwind",
+            ],
+            Self::Assets => &[
+                "cargo_leptos::compile::assets",
+                "cargo_leptos::compile::asset_pipeline",
+            ],
+            Self::Wasm | Self::Server => &[],
         }
     }
 }
@@ -80,6 +160,11 @@ fn format(
 ) -> Result<(), std::io::Error> {
     let args = record.args().to_string();
 
+    // the log line replaces the status line, which is rewritten at the next redraw
+    if STATUS_SHOWN.swap(false, Ordering::Relaxed) {
+        write!(write, "\r\x1b[2K")?;
+    }
+
     let lvl_color = record.level().color();
 
     if let Some(dep) = dependency(record) {
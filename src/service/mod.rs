@@ -1,4 +1,9 @@
+#[cfg(test)]
+mod tests;
+
 pub mod notify;
 pub mod reload;
 pub mod serve;
 pub mod site;
+pub mod static_site;
+pub mod status;
@@ -8,41 +8,74 @@ use crate::{
 };
 use camino::Utf8PathBuf;
 use itertools::Itertools;
-use notify::{DebouncedEvent, RecursiveMode, Watcher};
+use notify::{DebouncedEvent, PollWatcher, RecursiveMode, Watcher};
 use std::collections::HashSet;
 use std::path::Path;
 use std::sync::Arc;
-use std::{fmt::Display, time::Duration};
+use std::fmt::Display;
 use tokio::task::JoinHandle;
 
 pub async fn spawn(proj: &Arc<Project>) -> Result<JoinHandle<()>> {
     let mut set: HashSet<Utf8PathBuf> = HashSet::from_iter(vec![]);
 
     set.extend(proj.lib.src_paths.clone());
-    set.extend(proj.bin.src_paths.clone());
+    if let Some(bin) = &proj.bin {
+        set.extend(bin.src_paths.clone());
+    }
 
     if let Some(style) = &proj.style {
-        set.insert(style.file.source.clone().without_last());
+        set.extend(style.dirs());
+        if let Some(config_file) = style.tailwind.as_ref().and_then(|t| t.config_file.as_ref()) {
+            set.insert(config_file.clone().without_last());
+        }
     }
 
     if let Some(assets) = &proj.assets {
-        set.insert(assets.dir.clone());
+        set.extend(assets.dirs.clone());
+    }
+
+    if let Some(index) = &proj.index_file {
+        set.insert(index.source.clone().without_last());
+    }
+
+    for path in &proj.watch_additional_files {
+        match proj.working_dir.join(path).exists() {
+            true => {
+                set.insert(path.clone());
+            }
+            false => log::warn!("Notify cannot watch the missing watch-additional-files {path:?}"),
+        }
     }
 
     let paths = remove_nested(set.into_iter());
 
+    // the dirs of the config files, and not the files, are watched as saving can replace them
+    let config_dirs = proj
+        .config_files
+        .iter()
+        .map(|file| match file.parent() {
+            Some(dir) if dir != "" => dir.to_path_buf(),
+            _ => Utf8PathBuf::from("."),
+        })
+        .filter(|dir| dir.exists() && !dir.starts_with_any(&paths))
+        .unique()
+        .collect::<Vec<_>>();
+
     log::info!(
         "Notify watching folders {}",
         GRAY.paint(paths.iter().join(", "))
     );
     let proj = proj.clone();
 
-    Ok(tokio::spawn(async move { run(&paths, proj).await }))
+    Ok(tokio::spawn(async move {
+        run(&paths, &config_dirs, proj).await
+    }))
 }
 
-async fn run(paths: &[Utf8PathBuf], proj: Arc<Project>) {
+async fn run(paths: &[Utf8PathBuf], config_dirs: &[Utf8PathBuf], proj: Arc<Project>) {
     let (sync_tx, sync_rx) = std::sync::mpsc::channel::<DebouncedEvent>();
 
+    let (poll, debounce) = (proj.watch_poll, proj.watch_debounce);
     let proj = proj.clone();
     std::thread::spawn(move || {
         while let Ok(event) = sync_rx.recv() {
@@ -55,11 +88,29 @@ async fn run(paths: &[Utf8PathBuf], proj: Arc<Project>) {
         log::debug!("Notify stopped");
     });
 
-    let mut watcher = notify::watcher(sync_tx, Duration::from_millis(200))
-        .expect("failed to build file system watcher");
+    match poll {
+        Some(interval) => {
+            log::debug!("Notify polling every {interval:?}");
+            let watcher = PollWatcher::new(sync_tx, interval)
+                .expect("failed to build file system poll watcher");
+            watch(watcher, paths, config_dirs).await
+        }
+        None => {
+            let watcher = notify::watcher(sync_tx, debounce)
+                .expect("failed to build file system watcher");
+            watch(watcher, paths, config_dirs).await
+        }
+    }
+}
 
-    for path in paths {
-        if let Err(e) = watcher.watch(path, RecursiveMode::Recursive) {
+/// watches the paths, and the config dirs without their sub dirs, until shutdown
+async fn watch(mut watcher: impl Watcher, paths: &[Utf8PathBuf], config_dirs: &[Utf8PathBuf]) {
+    let recursive = paths.iter().map(|path| (path, RecursiveMode::Recursive));
+    let flat = config_dirs
+        .iter()
+        .map(|dir| (dir, RecursiveMode::NonRecursive));
+    for (path, mode) in recursive.chain(flat) {
+        if let Err(e) = watcher.watch(path, mode) {
             log::error!("Notify could not watch {path:?} due to {e:?}");
         }
     }
@@ -75,15 +126,43 @@ fn handle(watched: Watched, proj: Arc<Project>) {
         GRAY.paint(format!("{:?}", watched.path()))
     );
 
-    let Some(path) = watched.path() else {
+    if watched.path().is_none() {
         Interrupt::send_all_changed();
-        return
+        return;
+    }
+
+    let changes = changes(&watched, &proj);
+    if !changes.is_empty() {
+        Interrupt::send(&changes);
+    } else {
+        log::trace!(
+            "Notify changed but not watched: {}",
+            GRAY.paint(watched.to_string())
+        );
+    }
+}
+
+/// the changes of the project triggered by the watched file
+pub(crate) fn changes(watched: &Watched, proj: &Project) -> Vec<Change> {
+    let Some(path) = watched.path() else {
+        return Vec::new();
     };
 
+    // a rename is only ignored when both the from and to paths are
+    if watched.paths().iter().all(|p| proj.is_watch_ignored(p)) {
+        log::trace!("Notify ignored {}", GRAY.paint(watched.to_string()));
+        return Vec::new();
+    }
+
     let mut changes = Vec::new();
 
+    if watched.paths().iter().any(|p| proj.config_files.contains(p)) {
+        log::debug!("Notify config change {}", GRAY.paint(watched.to_string()));
+        changes.push(Change::Conf);
+    }
+
     if let Some(assets) = &proj.assets {
-        if path.starts_with(&assets.dir) {
+        if path.starts_with_any(&assets.dirs) && !assets.is_excluded_path(path) {
             log::debug!("Notify asset change {}", GRAY.paint(watched.to_string()));
             changes.push(Change::Asset(watched.clone()));
         }
@@ -97,7 +176,8 @@ fn handle(watched: Watched, proj: Arc<Project>) {
         changes.push(Change::LibSource);
     }
 
-    if path.starts_with_any(&proj.bin.src_paths) && path.is_ext_any(&["rs"]) {
+    let bin_src = proj.bin.as_ref().map(|bin| bin.src_paths.as_slice());
+    if path.starts_with_any(bin_src.unwrap_or_default()) && path.is_ext_any(&["rs"]) {
         log::debug!(
             "Notify bin source change {}",
             GRAY.paint(watched.to_string())
@@ -105,22 +185,31 @@ fn handle(watched: Watched, proj: Arc<Project>) {
         changes.push(Change::BinSource);
     }
 
+    if path.starts_with_any(&proj.watch_additional_files) {
+        log::debug!(
+            "Notify additional file change {}",
+            GRAY.paint(watched.to_string())
+        );
+        changes.push(Change::LibSource);
+        changes.push(Change::BinSource);
+    }
+
     if let Some(style) = &proj.style {
-        let src = style.file.source.clone().without_last();
-        if path.starts_with(src) && path.is_ext_any(&["scss", "sass", "css"]) {
+        let in_style_dir = style.dirs().any(|dir| path.starts_with(dir));
+        let tailwind_config = style.tailwind.as_ref().and_then(|t| t.config_file.as_ref());
+        if (in_style_dir && path.is_ext_any(&["scss", "sass", "css"]))
+            || Some(path) == tailwind_config
+        {
             log::debug!("Notify style change {}", GRAY.paint(watched.to_string()));
             changes.push(Change::Style)
         }
     }
 
-    if !changes.is_empty() {
-        Interrupt::send(&changes);
-    } else {
-        log::trace!(
-            "Notify changed but not watched: {}",
-            GRAY.paint(watched.to_string())
-        );
+    if proj.index_file.as_ref().map(|index| &index.source) == Some(path) {
+        log::debug!("Notify index change {}", GRAY.paint(watched.to_string()));
+        changes.push(Change::Index)
     }
+    changes
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -144,7 +233,7 @@ impl Watched {
             Chmod, Create, Error, NoticeRemove, NoticeWrite, Remove, Rename, Rescan, Write,
         };
 
-        Ok(match event {
+        let watched = match event {
             Chmod(_) | NoticeRemove(_) | NoticeWrite(_) => None,
             Create(f) => Some(Self::Create(convert(f, proj)?)),
             Remove(f) => Some(Self::Remove(convert(f, proj)?)),
@@ -159,7 +248,19 @@ impl Watched {
                 log::error!("Notify error: {e:?}");
                 None
             }
-        })
+        };
+        Ok(watched.and_then(Self::without_editor_temp))
+    }
+
+    /// Drops the changes of the temp and backup files written by the editors when saving atomically,
+    /// and turns the rename of a temp file over the saved file into a write of the saved file.
+    pub fn without_editor_temp(self) -> Option<Self> {
+        match self {
+            Self::Create(p) | Self::Write(p) | Self::Remove(p) if is_editor_temp(&p) => None,
+            Self::Rename(_, to) if is_editor_temp(&to) => None,
+            Self::Rename(from, to) if is_editor_temp(&from) => Some(Self::Write(to)),
+            watched => Some(watched),
+        }
     }
 
     pub fn path_ext(&self) -> Option<&str> {
@@ -173,6 +274,14 @@ impl Watched {
         }
     }
 
+    pub fn paths(&self) -> Vec<&Utf8PathBuf> {
+        match self {
+            Self::Remove(p) | Self::Write(p) | Self::Create(p) => vec![p],
+            Self::Rename(fr, to) => vec![fr, to],
+            Self::Rescan => vec![],
+        }
+    }
+
     pub fn path_starts_with(&self, path: &Utf8PathBuf) -> bool {
         match self {
             Self::Write(p) | Self::Create(p) | Self::Remove(p) => p.starts_with(path),
@@ -189,6 +298,21 @@ impl Watched {
     }
 }
 
+/// vim (backup~, .swp, 4913), emacs (.#lock, #autosave#), IntelliJ (___jb_tmp___, ___jb_old___)
+/// and the generic .tmp, .bak and .crswap (Chrome) files
+fn is_editor_temp(path: &Utf8PathBuf) -> bool {
+    let Some(name) = path.file_name() else {
+        return false;
+    };
+    name == "4913"
+        || name.starts_with(".#")
+        || (name.starts_with('#') && name.ends_with('#'))
+        || name.ends_with('~')
+        || name.ends_with("___jb_tmp___")
+        || name.ends_with("___jb_old___")
+        || path.is_ext_any(&["swp", "swo", "swx", "tmp", "bak", "crswap"])
+}
+
 impl Display for Watched {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
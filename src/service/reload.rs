@@ -1,4 +1,5 @@
-use crate::config::Project;
+use crate::config::{Project, TlsConfig};
+use crate::ext::anyhow::{bail, Context, Result};
 use crate::ext::sync::wait_for_socket;
 use crate::logger::GRAY;
 use crate::signal::Interrupt;
@@ -9,14 +10,38 @@ use axum::{
     routing::get,
     Router,
 };
+use hyper::server::conn::Http;
 use serde::Serialize;
+use std::sync::atomic::{AtomicU16, AtomicUsize, Ordering};
 use std::sync::Arc;
-use std::{fmt::Display, net::SocketAddr};
-use tokio::{net::TcpStream, select, sync::RwLock, task::JoinHandle};
+use std::time::Duration;
+use std::{fmt::Display, fs::File, io::BufReader, net::SocketAddr};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    select,
+    sync::broadcast::{error::RecvError, Receiver},
+    sync::{Notify, RwLock},
+    task::JoinHandle,
+    time::{sleep, timeout},
+};
+use tokio_rustls::{
+    rustls::{Certificate, PrivateKey, ServerConfig},
+    TlsAcceptor,
+};
+
+/// the number of connected browser pages
+static CLIENTS: AtomicUsize = AtomicUsize::new(0);
+/// the port of the running reload server, 0 when stopped
+static BOUND_PORT: AtomicU16 = AtomicU16::new(0);
+
+/// the reloads sent within this window are sent to the browser as one
+const RELOAD_DEBOUNCE: Duration = Duration::from_millis(150);
 
 lazy_static::lazy_static! {
   static ref SITE_ADDR: RwLock<SocketAddr> = RwLock::new(SocketAddr::new([127,0,0,1].into(), 3000));
   static ref CSS_LINK: RwLock<String> = RwLock::new(String::default());
+  /// notified when the last client disconnects
+  static ref NO_CLIENTS: Notify = Notify::new();
 }
 
 pub async fn spawn(proj: &Arc<Project>) -> JoinHandle<()> {
@@ -36,29 +61,105 @@ pub async fn spawn(proj: &Arc<Project>) -> JoinHandle<()> {
 
         if TcpStream::connect(&reload_addr).await.is_ok() {
             log::error!(
-                    "Reload TCP port {reload_addr} already in use. You can set the port in the server integration's RenderOptions reload_port"
+                    "Reload TCP port {reload_addr} already in use. Set another reload-port or set auto-reload-port = true"
                 );
             Interrupt::request_shutdown().await;
 
             return;
         }
-        let route = Router::new().route("/live_reload", get(websocket_handler));
+        let route = Router::new().route(&proj.site.reload_path, get(websocket_handler));
+        BOUND_PORT.store(reload_addr.port(), Ordering::Relaxed);
 
         log::debug!(
             "Reload server started {}",
             GRAY.paint(reload_addr.to_string())
         );
 
-        match axum::Server::bind(&reload_addr)
-            .serve(route.into_make_service())
-            .await
-        {
+        let mut int = Interrupt::subscribe_shutdown();
+        let res = match &proj.tls {
+            Some(tls) => select! {
+                res = serve_tls(reload_addr, tls, route) => res,
+                _ = int.recv() => Ok(()),
+            },
+            None => axum::Server::bind(&reload_addr)
+                .serve(route.into_make_service())
+                .with_graceful_shutdown(async move {
+                    let _ = int.recv().await;
+                })
+                .await
+                .map_err(Into::into),
+        };
+        BOUND_PORT.store(0, Ordering::Relaxed);
+        match res {
             Ok(_) => log::debug!("Reload server stopped"),
-            Err(e) => log::error!("Reload {e}"),
+            Err(e) => log::error!("Reload {e:#}"),
         }
     })
 }
 
+/// the port of the running reload server. It is released when restarting with a reloaded config,
+/// so it stays available to the reloaded project
+pub fn bound_port() -> Option<u16> {
+    match BOUND_PORT.load(Ordering::Relaxed) {
+        0 => None,
+        port => Some(port),
+    }
+}
+
+/// serves the route over TLS, for the reload websocket to use wss://
+async fn serve_tls(addr: SocketAddr, tls: &TlsConfig, route: Router) -> Result<()> {
+    let acceptor = TlsAcceptor::from(Arc::new(server_config(tls)?));
+    let listener = TcpListener::bind(addr).await?;
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let (acceptor, route) = (acceptor.clone(), route.clone());
+        tokio::spawn(async move {
+            let stream = match acceptor.accept(stream).await {
+                Ok(stream) => stream,
+                Err(e) => return log::debug!("Reload TLS handshake failed: {e}"),
+            };
+            // the websocket is an upgraded connection
+            let conn = Http::new().serve_connection(stream, route).with_upgrades();
+            if let Err(e) = conn.await {
+                log::debug!("Reload TLS connection error: {e}");
+            }
+        });
+    }
+}
+
+fn server_config(tls: &TlsConfig) -> Result<ServerConfig> {
+    let open = |file| {
+        File::open(file)
+            .map(BufReader::new)
+            .context(format!("Could not open {file:?}"))
+    };
+    let certs = rustls_pemfile::certs(&mut open(&tls.cert_file)?)
+        .context(format!("Invalid tls-cert-file {:?}", tls.cert_file))?;
+    let mut keys = open(&tls.key_file)?;
+    let key = loop {
+        match rustls_pemfile::read_one(&mut keys)
+            .context(format!("Invalid tls-key-file {:?}", tls.key_file))?
+        {
+            Some(rustls_pemfile::Item::RSAKey(key))
+            | Some(rustls_pemfile::Item::PKCS8Key(key))
+            | Some(rustls_pemfile::Item::ECKey(key)) => break key,
+            Some(_) => continue,
+            None => bail!(
+                "No private key found in the tls-key-file {:?}",
+                tls.key_file
+            ),
+        }
+    };
+    ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(
+            certs.into_iter().map(Certificate).collect(),
+            PrivateKey(key),
+        )
+        .context("Invalid tls-cert-file or tls-key-file")
+}
+
 async fn websocket_handler(ws: WebSocketUpgrade) -> impl IntoResponse {
     ws.on_upgrade(websocket)
 }
@@ -69,20 +170,22 @@ async fn websocket(mut stream: WebSocket) {
 
     log::trace!("Reload websocket connected");
     tokio::spawn(async move {
+        let _client = Client::connect();
         loop {
             select! {
                 res = rx.recv() =>{
                     match res {
-                        Ok(ReloadType::Full) => {
-                            send_and_close(stream, BrowserMessage::all()).await;
-                            return
-                        }
-                        Ok(ReloadType::Style) => {
-                            send(&mut stream, BrowserMessage::css().await).await;
+                        Ok(reload) => match debounce(&mut rx, reload).await {
+                            ReloadType::Full => {
+                                send_and_close(stream, BrowserMessage::all()).await;
+                                return
+                            }
+                            ReloadType::Style => {
+                                send(&mut stream, BrowserMessage::css().await).await;
+                            }
                         },
                         Err(e) => log::debug!("Reload recive error {e}")
                     }
-
                 }
                 _ = int.recv(), if Interrupt::is_shutdown_requested().await => {
                     log::trace!("Reload websocket closed");
@@ -93,6 +196,68 @@ async fn websocket(mut stream: WebSocket) {
     });
 }
 
+/// the number of browser pages connected to the reload websocket
+pub fn clients() -> usize {
+    CLIENTS.load(Ordering::Relaxed)
+}
+
+/// shuts down once no client was connected for the grace period after the last one disconnected,
+/// for the preview environments to tear down when their pages are closed
+pub fn spawn_exit_on_no_clients(grace: Duration) -> JoinHandle<()> {
+    let mut int = Interrupt::subscribe_shutdown();
+    tokio::spawn(async move {
+        loop {
+            select! {
+                _ = NO_CLIENTS.notified() => {},
+                _ = int.recv() => return,
+            }
+            // the pages reconnect right away when reloaded
+            select! {
+                _ = sleep(grace) => {},
+                _ = int.recv() => return,
+            }
+            if clients() == 0 {
+                log::info!(
+                    "Reload no client connected for {:.1}s, shutting down",
+                    grace.as_secs_f32()
+                );
+                Interrupt::request_shutdown().await;
+                return;
+            }
+        }
+    })
+}
+
+/// counts a connected page until dropped
+struct Client;
+
+impl Client {
+    fn connect() -> Self {
+        CLIENTS.fetch_add(1, Ordering::Relaxed);
+        Self
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        if CLIENTS.fetch_sub(1, Ordering::Relaxed) == 1 {
+            NO_CLIENTS.notify_one();
+        }
+    }
+}
+
+/// merges the reloads following the received one, so that a rebuild reloads the browser once
+async fn debounce(rx: &mut Receiver<ReloadType>, mut reload: ReloadType) -> ReloadType {
+    loop {
+        match timeout(RELOAD_DEBOUNCE, rx.recv()).await {
+            Ok(Ok(next)) => reload = reload.merge(next),
+            // the missed reloads could be full ones
+            Ok(Err(RecvError::Lagged(_))) => reload = ReloadType::Full,
+            Ok(Err(RecvError::Closed)) | Err(_) => return reload,
+        }
+    }
+}
+
 async fn send(stream: &mut WebSocket, msg: BrowserMessage) {
     let site_addr = *SITE_ADDR.read().await;
     if !wait_for_socket("Reload", site_addr).await {
@@ -116,9 +281,14 @@ async fn send_and_close(mut stream: WebSocket, msg: BrowserMessage) {
     log::trace!("Reload websocket closed");
 }
 
+/// the message sent to the reload script injected by the leptos server integration.
+/// With `css` set, the script replaces the href of the stylesheet link (cache-busted)
+/// instead of reloading the page
 #[derive(Serialize)]
 struct BrowserMessage {
+    /// the site path of the changed stylesheet
     css: Option<String>,
+    /// reload the whole page
     all: bool,
 }
 
@@ -1,24 +1,41 @@
-use std::sync::Arc;
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    process::Stdio,
+    sync::Arc,
+    time::Duration,
+};
 
 use crate::{
-    config::Project,
+    config::{server_run_args, Project},
     ext::{fs, anyhow::Result, append_str_to_filename, determine_pdb_filename},
-    logger::GRAY,
+    logger::{self, GRAY},
+    service::static_site,
     signal::{Interrupt, ReloadSignal, ServerRestart},
 };
 use camino::Utf8PathBuf;
 use tokio::{
+    net::TcpStream,
     process::{Child, Command},
     select,
     task::JoinHandle,
+    time::sleep,
 };
 
 pub async fn spawn(proj: &Arc<Project>) -> JoinHandle<Result<()>> {
+    let Some(bin) = &proj.bin else {
+        let proj = proj.clone();
+        return tokio::spawn(async move {
+            tokio::spawn(announce(proj.clone()));
+            static_site::serve(&proj).await
+        });
+    };
+    let (exe, run_cmd) = (bin.exe_file.clone(), bin.run_cmd.clone());
     let mut int = Interrupt::subscribe_shutdown();
     let proj = proj.clone();
     let mut change = ServerRestart::subscribe();
     tokio::spawn(async move {
-        let mut server = ServerProcess::start_new(&proj).await?;
+        let mut server = ServerProcess::start_new(&proj, exe, run_cmd).await?;
+        tokio::spawn(announce(proj.clone()));
         loop {
             select! {
               res = change.recv() => {
@@ -36,39 +53,127 @@ pub async fn spawn(proj: &Arc<Project>) -> JoinHandle<Result<()>> {
     })
 }
 
-struct ServerProcess(Option<Child>, Vec<(&'static str, String)>, Utf8PathBuf);
+/// logs the site url once the server accepts connections, whatever the app itself logs
+async fn announce(proj: Arc<Project>) {
+    let addr = proj.site.addr;
+    // the first start of the app can be slow, so waiting up to a minute
+    let mut tries = 0;
+    while TcpStream::connect(&addr).await.is_err() {
+        tries += 1;
+        if tries == 120 {
+            log::debug!("Serve gave up waiting for {addr}");
+            return;
+        }
+        sleep(Duration::from_millis(500)).await;
+    }
+    let reload = if !proj.disable_reload {
+        format!("with live-reload on port {}", proj.site.reload.port())
+    } else {
+        "without live-reload".to_string()
+    };
+    log::info!("Serving at http://{addr} {}", GRAY.paint(reload));
+    if proj.open {
+        open_browser(addr);
+    }
+}
+
+/// opens the site with the BROWSER command, or the platform default
+fn open_browser(addr: SocketAddr) {
+    // a server listening on all the interfaces is opened on localhost
+    let addr = match addr.ip().is_unspecified() {
+        true => SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), addr.port()),
+        false => addr,
+    };
+    let url = format!("http://{addr}");
+    let mut cmd = match std::env::var("BROWSER") {
+        Ok(browser) if !browser.trim().is_empty() => {
+            let mut parts = browser.split_whitespace();
+            let mut cmd = std::process::Command::new(parts.next().unwrap());
+            cmd.args(parts);
+            cmd
+        }
+        _ if cfg!(target_os = "macos") => std::process::Command::new("open"),
+        _ if cfg!(windows) => {
+            let mut cmd = std::process::Command::new("cmd");
+            cmd.args(["/C", "start", ""]);
+            cmd
+        }
+        _ => std::process::Command::new("xdg-open"),
+    };
+    let res = cmd
+        .arg(&url)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    match res {
+        Ok(_) => log::debug!("Serve opened {url} in the browser"),
+        Err(e) => log::warn!("Serve could not open {url} in the browser: {e}"),
+    }
+}
+
+struct ServerProcess {
+    process: Option<Child>,
+    envs: Vec<(&'static str, String)>,
+    binary: Utf8PathBuf,
+    /// how long a stopped server may drain its requests before it is killed
+    shutdown_timeout: Option<Duration>,
+    /// the server-run-cmd wrapping the binary
+    run_cmd: Option<String>,
+}
 
 impl ServerProcess {
-    fn new(proj: &Project) -> Self {
-        Self(None, proj.to_envs(), proj.bin.exe_file.clone())
+    fn new(proj: &Project, exe: Utf8PathBuf, run_cmd: Option<String>) -> Self {
+        Self {
+            process: None,
+            envs: proj.to_envs(),
+            binary: exe,
+            shutdown_timeout: proj.graceful_shutdown_timeout,
+            run_cmd,
+        }
     }
 
-    async fn start_new(proj: &Project) -> Result<Self> {
-        let mut me = Self::new(proj);
+    async fn start_new(
+        proj: &Project,
+        exe: Utf8PathBuf,
+        run_cmd: Option<String>,
+    ) -> Result<Self> {
+        let mut me = Self::new(proj, exe, run_cmd);
         me.start().await?;
         Ok(me)
     }
 
     async fn kill(&mut self) {
-        if let Some(proc) = self.0.as_mut() {
+        if let Some(proc) = self.process.as_mut() {
             if let Err(e) = proc.kill().await {
                 log::error!("Serve error killing server process: {e}");
             } else {
                 log::trace!("Serve stopped");
             }
-            self.0 = None;
+            self.process = None;
         }
     }
 
-    async fn restart(&mut self) -> Result<()> {
+    /// lets the server drain the in-flight requests when a graceful-shutdown-timeout is set
+    async fn stop(&mut self) {
+        #[cfg(unix)]
+        if let (Some(proc), Some(timeout)) = (self.process.as_mut(), self.shutdown_timeout) {
+            if terminate(proc, timeout).await {
+                self.process = None;
+                return;
+            }
+        }
         self.kill().await;
+    }
+
+    async fn restart(&mut self) -> Result<()> {
+        self.stop().await;
         self.start().await?;
         log::trace!("Serve restarted");
         Ok(())
     }
 
     async fn start(&mut self) -> Result<()> {
-        let bin = &self.2;
+        let bin = &self.binary;
         let child = if bin.exists() {
             // solution to allow cargo to overwrite a running binary on some platforms:
             //   copy cargo's output bin to [filename]_leptos and then run it
@@ -90,13 +195,48 @@ impl ServerProcess {
                 },
                 None => {},
             }
-            log::debug!("Serve running {}", GRAY.paint(new_bin_path.as_str()));
-            Some(Command::new(new_bin_path).envs(self.1.clone()).spawn()?)
+            let args = server_run_args(self.run_cmd.as_deref(), &new_bin_path);
+            log::debug!("Serve running {}", GRAY.paint(args.join(" ")));
+            let mut command = Command::new(&args[0]);
+            command.args(&args[1..]);
+            command.envs(self.envs.clone());
+            if logger::is_json() {
+                // the stdout is reserved for the json events
+                command.stdout(std::io::stderr());
+            }
+            Some(command.spawn()?)
         } else {
             log::debug!("Serve no exe found {}", GRAY.paint(bin.as_str()));
             None
         };
-        self.0 = child;
+        self.process = child;
         Ok(())
     }
 }
+
+/// sends a SIGTERM and waits for the process to exit, returns false if it has to be killed
+#[cfg(unix)]
+async fn terminate(proc: &mut Child, timeout: Duration) -> bool {
+    // no id when the process has already exited
+    let Some(pid) = proc.id() else {
+        return false;
+    };
+    // SAFETY: kill only sends a signal to the pid of our child process
+    if unsafe { libc::kill(pid as libc::pid_t, libc::SIGTERM) } != 0 {
+        return false;
+    }
+    match tokio::time::timeout(timeout, proc.wait()).await {
+        Ok(Ok(_)) => {
+            log::trace!("Serve stopped gracefully");
+            true
+        }
+        Ok(Err(e)) => {
+            log::error!("Serve error waiting for the server process: {e}");
+            false
+        }
+        Err(_) => {
+            log::warn!("Serve server still running {timeout:?} after SIGTERM, killing it");
+            false
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use std::{
     collections::HashMap,
     fmt::{self, Display},
-    net::SocketAddr,
+    net::{SocketAddr, TcpListener},
 };
 
 use camino::{Utf8Path, Utf8PathBuf};
@@ -13,6 +13,7 @@ use crate::{
         anyhow::{Context, Result},
         fs, PathBufExt,
     },
+    service::reload,
 };
 
 #[derive(Clone)]
@@ -76,6 +77,8 @@ impl std::fmt::Debug for SiteFile {
 pub struct Site {
     pub addr: SocketAddr,
     pub reload: SocketAddr,
+    /// the path of the reload websocket
+    pub reload_path: String,
     pub root_dir: Utf8PathBuf,
     pub pkg_dir: Utf8PathBuf,
     file_reg: RwLock<HashMap<String, u64>>,
@@ -87,6 +90,7 @@ impl fmt::Debug for Site {
         f.debug_struct("Site")
             .field("addr", &self.addr)
             .field("reload", &self.reload)
+            .field("reload_path", &self.reload_path)
             .field("root_dir", &self.root_dir)
             .field("pkg_dir", &self.pkg_dir)
             .field("file_reg", &self.file_reg.blocking_read())
@@ -95,13 +99,34 @@ impl fmt::Debug for Site {
     }
 }
 
+/// the first port, from the given one, that can be bound and isn't the site's.
+/// It is probed before the server is launched, as the port is passed to it in LEPTOS_RELOAD_PORT
+fn free_reload_port(site_addr: &SocketAddr, from: u16) -> u16 {
+    let port = (from..=u16::MAX)
+        .filter(|port| *port != site_addr.port())
+        .find(|port| {
+            // the running reload server's port is free again once it is restarted
+            Some(*port) == reload::bound_port()
+                || TcpListener::bind((site_addr.ip(), *port)).is_ok()
+        })
+        .unwrap_or(from);
+    if port != from {
+        log::info!("Reload port {from} is busy, using {port}");
+    }
+    port
+}
+
 impl Site {
     pub fn new(config: &ProjectConfig) -> Self {
         let mut reload = config.site_addr.clone();
         reload.set_port(config.reload_port);
+        if config.auto_reload_port {
+            reload.set_port(free_reload_port(&config.site_addr, config.reload_port));
+        }
         Self {
             addr: config.site_addr.clone(),
             reload,
+            reload_path: config.reload_ws_path.clone(),
             root_dir: config.site_root.clone(),
             pkg_dir: config.site_pkg_dir.clone(),
             file_reg: Default::default(),
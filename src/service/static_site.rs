@@ -0,0 +1,272 @@
+use std::sync::Arc;
+
+use crate::{
+    compile::is_compressible,
+    config::Project,
+    ext::anyhow::{Context, Result},
+    logger::GRAY,
+    signal::Interrupt,
+};
+use axum::{
+    body::{boxed, Body},
+    http::{header, HeaderMap, HeaderValue, Method, Request, StatusCode, Uri},
+    response::{IntoResponse, Response},
+    routing::any,
+    Router,
+};
+use camino::{Utf8Component, Utf8Path, Utf8PathBuf};
+use flate2::{write::GzEncoder, Compression};
+use hyper::client::{Client, HttpConnector};
+use std::io::Write;
+
+/// smaller files gain little from the compression
+const COMPRESS_MIN_SIZE: usize = 1024;
+
+/// headers of a single connection, not forwarded by the proxy
+const HOP_HEADERS: [&str; 8] = [
+    "connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+
+/// Serves the site root of a csr-only project, which has no server of its own.
+/// With the spa-fallback, the html requests of the app routes get the index.html.
+pub async fn serve(proj: &Arc<Project>) -> Result<()> {
+    let addr = proj.site.addr;
+    let mut int = Interrupt::subscribe_shutdown();
+
+    log::debug!(
+        "Serve static files from {}",
+        GRAY.paint(proj.site.root_dir.as_str())
+    );
+    let proj = proj.clone();
+    let client = Client::new();
+    let route = Router::new().fallback(any(move |req: Request<Body>| {
+        handle(proj.clone(), client.clone(), req)
+    }));
+    axum::Server::try_bind(&addr)
+        .context(format!("Could not serve the site at {addr}"))?
+        .serve(route.into_make_service())
+        .with_graceful_shutdown(async move {
+            let _ = int.recv().await;
+        })
+        .await?;
+    log::trace!("Serve stopped");
+    Ok(())
+}
+
+/// the site files first, then the proxied paths, then the spa fallback
+async fn handle(proj: Arc<Project>, client: Client<HttpConnector>, req: Request<Body>) -> Response {
+    if resolve(&proj.site.root_dir, req.uri().path(), false).is_none() {
+        if let Some(upstream) = proj.proxy.upstream(req.uri()) {
+            return forward(&client, upstream, req).await;
+        }
+    }
+    if !matches!(*req.method(), Method::GET | Method::HEAD) {
+        return StatusCode::METHOD_NOT_ALLOWED.into_response();
+    }
+    let (parts, _) = req.into_parts();
+    file(proj, parts.uri, parts.headers).await
+}
+
+/// forwards the request to the upstream uri, streaming the bodies both ways
+pub(crate) async fn forward(
+    client: &Client<HttpConnector>,
+    upstream: Uri,
+    mut req: Request<Body>,
+) -> Response {
+    let uri = std::mem::replace(req.uri_mut(), upstream);
+    remove_hop_headers(req.headers_mut());
+    // set by the client, for the upstream
+    req.headers_mut().remove(header::HOST);
+    match client.request(req).await {
+        Ok(mut res) => {
+            remove_hop_headers(res.headers_mut());
+            res.map(boxed)
+        }
+        Err(e) => {
+            log::warn!("Serve could not proxy {uri}: {e}");
+            StatusCode::BAD_GATEWAY.into_response()
+        }
+    }
+}
+
+fn remove_hop_headers(headers: &mut HeaderMap) {
+    for name in HOP_HEADERS {
+        headers.remove(name);
+    }
+}
+
+async fn file(proj: Arc<Project>, uri: Uri, headers: HeaderMap) -> Response {
+    let root = &proj.site.root_dir;
+    let fallback = proj.spa_fallback && accepts_html(&headers);
+    let Some(file) = resolve(root, uri.path(), fallback) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let body = match tokio::fs::read(&file).await {
+        Ok(body) => body,
+        Err(e) => {
+            log::warn!("Serve could not read {file}: {e}");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let site_path = file.strip_prefix(root).unwrap_or(&file);
+    let cache_control = proj.cache_control.value(site_path.as_str());
+    respond(&file, body, cache_control, proj.dev_compression, &headers).await
+}
+
+/// the response of the file body, compressed when enabled and accepted by the request
+pub(crate) async fn respond(
+    file: &Utf8Path,
+    body: Vec<u8>,
+    cache_control: &str,
+    compression: bool,
+    headers: &HeaderMap,
+) -> Response {
+    let compressible = compression && is_compressible(file) && body.len() >= COMPRESS_MIN_SIZE;
+    let encoding = compressible.then(|| accepted_encoding(headers)).flatten();
+    let hash = seahash::hash(&body);
+    // each encoding is a distinct representation, with its own etag
+    let etag = match encoding {
+        Some(encoding) => format!("\"{hash:x}-{encoding}\""),
+        None => format!("\"{hash:x}\""),
+    };
+    let mut cache_headers = HeaderMap::new();
+    if let Ok(value) = HeaderValue::from_str(cache_control) {
+        cache_headers.insert(header::CACHE_CONTROL, value);
+    }
+    if let Ok(value) = HeaderValue::from_str(&etag) {
+        cache_headers.insert(header::ETAG, value);
+    }
+    if compressible {
+        cache_headers.insert(header::VARY, HeaderValue::from_static("accept-encoding"));
+    }
+    if is_cached(headers, &etag) {
+        return (StatusCode::NOT_MODIFIED, cache_headers).into_response();
+    }
+    let content_type = [(header::CONTENT_TYPE, content_type(file))];
+    let Some(encoding) = encoding else {
+        return (cache_headers, content_type, body).into_response();
+    };
+    match tokio::task::spawn_blocking(move || encode(encoding, &body)).await {
+        Ok(Ok(body)) => {
+            let content_encoding = [(header::CONTENT_ENCODING, encoding)];
+            (cache_headers, content_type, content_encoding, body).into_response()
+        }
+        Ok(Err(e)) => {
+            log::warn!("Serve could not compress {file}: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+        Err(e) => {
+            log::warn!("Serve could not compress {file}: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// the preferred encoding accepted by the request, brotli over gzip
+fn accepted_encoding(headers: &HeaderMap) -> Option<&'static str> {
+    let accepted = headers
+        .get_all(header::ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|coding| {
+            let mut parts = coding.split(';');
+            let name = parts.next()?.trim().to_ascii_lowercase();
+            // a zero quality refuses the encoding
+            let refused = parts
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .any(|q| q.parse::<f32>().is_ok_and(|q| q <= 0.0));
+            (!refused).then_some(name)
+        })
+        .collect::<Vec<_>>();
+    ["br", "gzip"]
+        .into_iter()
+        .find(|encoding| accepted.iter().any(|name| name == encoding))
+}
+
+/// compressed with a fast level, as each response is compressed again
+fn encode(encoding: &str, body: &[u8]) -> std::io::Result<Vec<u8>> {
+    match encoding {
+        "br" => {
+            let mut encoder = brotli::CompressorWriter::new(Vec::new(), 4096, 5, 22);
+            encoder.write_all(body)?;
+            Ok(encoder.into_inner())
+        }
+        _ => {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
+            encoder.write_all(body)?;
+            encoder.finish()
+        }
+    }
+}
+
+/// if the If-None-Match of the request has the etag of the file
+fn is_cached(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|tag| tag.trim().trim_start_matches("W/"))
+        .any(|tag| tag == etag || tag == "*")
+}
+
+/// the file served for the request path: the file itself, the index.html of a dir or, with the
+/// fallback, the site index.html for the app routes. None for a missing asset.
+pub(crate) fn resolve(root: &Utf8Path, path: &str, fallback: bool) -> Option<Utf8PathBuf> {
+    let rel = Utf8Path::new(path.trim_start_matches('/'));
+    // no escaping the site root
+    if rel
+        .components()
+        .any(|c| !matches!(c, Utf8Component::Normal(_)))
+    {
+        return None;
+    }
+    let file = root.join(rel);
+    if file.is_file() {
+        return Some(file);
+    }
+    let index = file.join("index.html");
+    if index.is_file() {
+        return Some(index);
+    }
+    // a missing asset has an extension and stays a 404, to be noticed
+    let index = root.join("index.html");
+    (fallback && rel.extension().is_none() && index.is_file()).then_some(index)
+}
+
+/// browser navigations accept html, the fetch and asset requests usually don't
+fn accepts_html(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .any(|value| value.contains("text/html"))
+}
+
+fn content_type(file: &Utf8Path) -> &'static str {
+    match file.extension().unwrap_or_default() {
+        "html" => "text/html; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "wasm" => "application/wasm",
+        "json" | "map" => "application/json",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "txt" => "text/plain; charset=utf-8",
+        _ => "application/octet-stream",
+    }
+}
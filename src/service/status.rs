@@ -0,0 +1,107 @@
+use std::{
+    io::IsTerminal,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    logger::{self, Event},
+    service::reload,
+    signal::Interrupt,
+};
+use tokio::{select, task::JoinHandle, time::interval};
+
+/// how often the status line is redrawn on a terminal
+const REDRAW_INTERVAL: Duration = Duration::from_secs(1);
+/// how often the status is logged, when it changed, when not on a terminal
+const LOG_INTERVAL: Duration = Duration::from_secs(60);
+
+lazy_static::lazy_static! {
+    static ref STATUS: Mutex<Status> = Mutex::new(Status::default());
+}
+
+#[derive(Default)]
+struct Status {
+    /// when the current or last build started, i.e. the last change
+    started: Option<Instant>,
+    /// a watch build is running
+    building: bool,
+    /// if the current build failed
+    failed: bool,
+    /// (succeeded, duration) of the last finished build
+    last: Option<(bool, Duration)>,
+    /// incremented when the status changes, to only log the changed ones
+    generation: usize,
+}
+
+/// updates the status from the build progress events
+pub fn record(event: &Event) {
+    let mut status = STATUS.lock().unwrap();
+    match event {
+        Event::BuildStarted => {
+            status.started = Some(Instant::now());
+            status.building = true;
+            status.failed = false;
+        }
+        Event::BuildFailed { .. } => status.failed = true,
+        _ => return,
+    }
+    status.generation += 1;
+}
+
+/// the watch build is done, successful unless a failure was recorded
+pub fn build_finished() {
+    let mut status = STATUS.lock().unwrap();
+    if let Some(started) = status.started {
+        status.last = Some((!status.failed, started.elapsed()));
+        status.building = false;
+        status.generation += 1;
+    }
+}
+
+/// keeps a status line updated at the bottom of the terminal or, when not on a terminal,
+/// logs the status periodically
+pub fn spawn() -> JoinHandle<()> {
+    let mut int = Interrupt::subscribe_shutdown();
+    tokio::spawn(async move {
+        let tty = std::io::stderr().is_terminal();
+        let mut ticks = interval(if tty { REDRAW_INTERVAL } else { LOG_INTERVAL });
+        let mut logged = (0, 0);
+        loop {
+            select! {
+                _ = ticks.tick() => {},
+                _ = int.recv() => return,
+            }
+            let clients = reload::clients();
+            let (line, generation) = {
+                let status = STATUS.lock().unwrap();
+                (status.line(clients), status.generation)
+            };
+            if tty {
+                logger::status_line(&line);
+            } else if logged != (generation, clients) {
+                log::info!("Watch status: {line}");
+                logged = (generation, clients);
+            }
+        }
+    })
+}
+
+impl Status {
+    fn line(&self, clients: usize) -> String {
+        let build = match self.last {
+            _ if self.building => "building".to_string(),
+            None => "waiting for changes".to_string(),
+            Some((true, took)) => format!("built in {:.1}s", took.as_secs_f32()),
+            Some((false, took)) => format!("failed after {:.1}s", took.as_secs_f32()),
+        };
+        let plural = if clients == 1 { "" } else { "s" };
+        match self.started {
+            Some(started) => format!(
+                "{build}, {clients} reload client{plural}, last change {}s ago",
+                started.elapsed().as_secs()
+            ),
+            None => format!("{build}, {clients} reload client{plural}"),
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use super::notify::{changes, Watched};
+
+/// the events of an atomic save, with the remaining ones after dropping the editor temp files
+fn saved(events: Vec<Watched>) -> Vec<Watched> {
+    events
+        .into_iter()
+        .filter_map(Watched::without_editor_temp)
+        .collect()
+}
+
+#[test]
+fn test_vim_save() {
+    // with backupcopy=no, vim moves the file to its backup and writes a new one
+    let events = vec![
+        Watched::Create("src/4913".into()),
+        Watched::Remove("src/4913".into()),
+        Watched::Rename("src/app.rs".into(), "src/app.rs~".into()),
+        Watched::Create("src/app.rs".into()),
+        Watched::Write("src/.app.rs.swp".into()),
+        Watched::Remove("src/app.rs~".into()),
+    ];
+    assert_eq!(saved(events), [Watched::Create("src/app.rs".into())]);
+}
+
+#[test]
+fn test_intellij_save() {
+    let events = vec![
+        Watched::Create("src/app.rs___jb_tmp___".into()),
+        Watched::Write("src/app.rs___jb_tmp___".into()),
+        Watched::Rename("src/app.rs".into(), "src/app.rs___jb_old___".into()),
+        Watched::Rename("src/app.rs___jb_tmp___".into(), "src/app.rs".into()),
+        Watched::Remove("src/app.rs___jb_old___".into()),
+    ];
+    assert_eq!(saved(events), [Watched::Write("src/app.rs".into())]);
+}
+
+#[test]
+fn test_rename_kept() {
+    let events = vec![
+        Watched::Rename("assets/logo.png".into(), "assets/icon.png".into()),
+        Watched::Rename("style/main.scss.tmp".into(), "style/main.scss".into()),
+    ];
+    assert_eq!(
+        saved(events),
+        [
+            Watched::Rename("assets/logo.png".into(), "assets/icon.png".into()),
+            Watched::Write("style/main.scss".into()),
+        ]
+    );
+}
+
+#[test]
+fn test_additional_file_change() {
+    use crate::{compile::Change, config::Config};
+
+    // project2 watches "../content", relative to its Cargo.toml
+    let cli = crate::Opts {
+        project: Some("project2".to_string()),
+        ..Default::default()
+    };
+    let conf = Config::test_load(cli, "examples", "examples/workspace/Cargo.toml", true);
+    let proj = &conf.projects[0];
+    assert_eq!(proj.watch_additional_files, ["content"]);
+
+    let content = Watched::Write("content/post.md".into());
+    assert_eq!(
+        changes(&content, proj),
+        [Change::LibSource, Change::BinSource]
+    );
+    let other = Watched::Write("contents/post.md".into());
+    assert!(changes(&other, proj).is_empty());
+}
+
+#[tokio::test]
+async fn test_proxy_forward() {
+    use super::static_site::forward;
+    use axum::{
+        body::Body,
+        http::{HeaderMap, Request, StatusCode},
+        routing::post,
+        Router,
+    };
+
+    let upstream = Router::new().route(
+        "/api/echo",
+        post(|headers: HeaderMap, body: String| async move {
+            let host = headers["host"].to_str().unwrap().to_string();
+            let test = headers["x-test"].to_str().unwrap().to_string();
+            ([("x-upstream", host)], format!("{test} {body}"))
+        }),
+    );
+    let server =
+        axum::Server::bind(&([127, 0, 0, 1], 0).into()).serve(upstream.into_make_service());
+    let addr = server.local_addr();
+    tokio::spawn(server);
+
+    let client = hyper::Client::new();
+    let request = || {
+        Request::post("/api/echo?page=2")
+            .header("host", "localhost:3000")
+            .header("x-test", "forwarded")
+            .header("connection", "close")
+            .body(Body::from("body"))
+            .unwrap()
+    };
+    let uri = format!("http://{addr}/api/echo?page=2").parse().unwrap();
+    let res = forward(&client, uri, request()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    // the upstream is addressed with its own host
+    assert_eq!(res.headers()["x-upstream"], addr.to_string().as_str());
+    let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+    assert_eq!(body, "forwarded body");
+
+    let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let uri = format!("http://{}/api/echo", closed.local_addr().unwrap());
+    drop(closed);
+    let res = forward(&client, uri.parse().unwrap(), request()).await;
+    assert_eq!(res.status(), StatusCode::BAD_GATEWAY);
+}
+
+#[tokio::test]
+async fn test_compression() {
+    use super::static_site::respond;
+    use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+    use std::io::Read;
+
+    let js = "console.log(\"hello\");\n".repeat(100).into_bytes();
+    let request = |accept: &'static str| {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT_ENCODING, HeaderValue::from_static(accept));
+        headers
+    };
+    let body = |res: axum::response::Response| async {
+        hyper::body::to_bytes(res.into_body())
+            .await
+            .unwrap()
+            .to_vec()
+    };
+    let file = camino::Utf8Path::new("site/pkg/app.js");
+
+    let res = respond(file, js.clone(), "no-cache", true, &request("gzip, br")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers()[header::CONTENT_ENCODING], "br");
+    assert_eq!(res.headers()[header::VARY], "accept-encoding");
+    let etag = res.headers()[header::ETAG].to_str().unwrap().to_string();
+    assert!(etag.ends_with("-br\""), "{etag}");
+    let mut decoded = Vec::new();
+    brotli::Decompressor::new(body(res).await.as_slice(), 4096)
+        .read_to_end(&mut decoded)
+        .unwrap();
+    assert_eq!(decoded, js);
+
+    let res = respond(file, js.clone(), "no-cache", true, &request("gzip, br;q=0")).await;
+    assert_eq!(res.headers()[header::CONTENT_ENCODING], "gzip");
+    let mut decoded = Vec::new();
+    flate2::read::GzDecoder::new(body(res).await.as_slice())
+        .read_to_end(&mut decoded)
+        .unwrap();
+    assert_eq!(decoded, js);
+
+    // the etag of the compressed variant revalidates it
+    let mut headers = request("br");
+    headers.insert(header::IF_NONE_MATCH, etag.parse().unwrap());
+    let res = respond(file, js.clone(), "no-cache", true, &headers).await;
+    assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+
+    let res = respond(file, js.clone(), "no-cache", true, &HeaderMap::new()).await;
+    assert!(!res.headers().contains_key(header::CONTENT_ENCODING));
+    assert_eq!(res.headers()[header::VARY], "accept-encoding");
+    assert_eq!(body(res).await, js);
+
+    let png = camino::Utf8Path::new("site/logo.png");
+    let res = respond(png, js.clone(), "no-cache", true, &request("br")).await;
+    assert!(!res.headers().contains_key(header::CONTENT_ENCODING));
+    assert!(!res.headers().contains_key(header::VARY));
+
+    let res = respond(file, js.clone(), "no-cache", false, &request("br")).await;
+    assert!(!res.headers().contains_key(header::CONTENT_ENCODING));
+    assert_eq!(body(res).await, js);
+}
@@ -1,6 +1,9 @@
 use tokio::{
     signal,
-    sync::{broadcast, RwLock},
+    sync::{
+        broadcast::{self, error::TryRecvError},
+        RwLock,
+    },
     task::JoinHandle,
 };
 
@@ -33,6 +36,21 @@ impl Interrupt {
         SOURCE_CHANGES.read().await.clone()
     }
 
+    /// the changes to build, the ones arriving meanwhile are kept for the next build
+    pub async fn take_source_changes() -> ChangeSet {
+        std::mem::take(&mut *SOURCE_CHANGES.write().await)
+    }
+
+    /// puts back the changes of an interrupted build, to be built with the ones that interrupted it
+    pub async fn restore_source_changes(changes: ChangeSet) {
+        SOURCE_CHANGES.write().await.extend(changes);
+    }
+
+    /// skips the interrupts already received, which are covered by the source changes taken next
+    pub fn drain(int: &mut broadcast::Receiver<()>) {
+        while let Ok(()) | Err(TryRecvError::Lagged(_)) = int.try_recv() {}
+    }
+
     pub async fn clear_source_changes() {
         let mut ch = SOURCE_CHANGES.write().await;
         ch.clear();
@@ -69,6 +87,11 @@ impl Interrupt {
         }
     }
 
+    /// stops the running builds, like a change does, without requesting a shutdown
+    pub fn request_stop() {
+        Self::send_any()
+    }
+
     pub async fn request_shutdown() {
         {
             *SHUTDOWN_REQUESTED.write().await = true;
@@ -77,6 +100,12 @@ impl Interrupt {
         _ = ANY_INTERRUPT.send(());
     }
 
+    /// stops the services, like a shutdown, for restarting them with a reloaded config.
+    /// The reload websockets of the pages stay connected.
+    pub fn request_restart() {
+        _ = SHUTDOWN.send(());
+    }
+
     pub fn run_ctrl_c_monitor() -> JoinHandle<()> {
         tokio::spawn(async move {
             signal::ctrl_c().await.expect("failed to listen for event");
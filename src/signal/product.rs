@@ -11,6 +11,8 @@ lazy_static::lazy_static! {
 pub enum Outcome {
     Success(Product),
     Stopped,
+    /// the failure was reported, the other products are still served
+    Failed,
 }
 
 #[derive(Debug, Display, Clone, PartialEq, Eq, Hash)]
@@ -31,13 +33,24 @@ impl ProductSet {
     }
 
     pub fn from(vec: Vec<Outcome>) -> Self {
-        Self(HashSet::from_iter(vec.into_iter().filter_map(
-            |entry| match entry {
+        let mut set = Self::empty();
+        set.add(vec);
+        set
+    }
+
+    /// adds the products of the successful outcomes
+    pub fn add(&mut self, vec: Vec<Outcome>) {
+        self.0
+            .extend(vec.into_iter().filter_map(|entry| match entry {
                 Outcome::Success(Product::None) => None,
                 Outcome::Success(v) => Some(v),
                 _ => None,
-            },
-        )))
+            }));
+    }
+
+    /// the products, leaving the set empty
+    pub fn take(&mut self) -> Self {
+        std::mem::replace(self, Self::empty())
     }
 
     pub fn is_empty(&self) -> bool {
@@ -1,15 +1,29 @@
 use tokio::sync::broadcast;
 
+use crate::logger::{self, Event};
+
 lazy_static::lazy_static! {
   static ref RELOAD_CHANNEL: broadcast::Sender::<ReloadType> = broadcast::channel::<ReloadType>(1).0;
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ReloadType {
+    /// reload the page, i.e. after a front, server or assets change
     Full,
+    /// only swap the stylesheet, keeping the page and the app state
     Style,
 }
 
+impl ReloadType {
+    /// the reload covering both, a full reload also swaps the stylesheet
+    pub fn merge(self, other: Self) -> Self {
+        match (self, other) {
+            (Self::Style, Self::Style) => Self::Style,
+            _ => Self::Full,
+        }
+    }
+}
+
 pub struct ReloadSignal {}
 
 impl ReloadSignal {
@@ -17,11 +31,13 @@ impl ReloadSignal {
         if let Err(e) = RELOAD_CHANNEL.send(ReloadType::Full) {
             log::error!(r#"Error could not send reload "Full" due to: {e}"#);
         }
+        logger::emit(Event::ReloadSent { kind: "full" });
     }
     pub fn send_style() {
         if let Err(e) = RELOAD_CHANNEL.send(ReloadType::Style) {
             log::error!(r#"Error could not send reload "Style" due to: {e}"#);
         }
+        logger::emit(Event::ReloadSent { kind: "style" });
     }
 
     pub fn subscribe() -> broadcast::Receiver<ReloadType> {
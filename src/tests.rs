@@ -1,13 +1,14 @@
 use camino::Utf8PathBuf;
 
 use crate::{
+    command::BuildCommand,
     ext::{fs, PathBufExt},
-    run, Cli, Commands, Opts,
+    run, Cli, Commands,
 };
 
 #[tokio::test]
 async fn workspace_build() {
-    let command = Commands::Build(Opts::default());
+    let command = Commands::Build(BuildCommand::default());
 
     let cli = Cli {
         manifest_path: Some(Utf8PathBuf::from("examples/workspace/Cargo.toml")),
@@ -0,0 +1,39 @@
+#[cfg(feature = "ssr")]
+#[actix_web::main]
+async fn main() -> std::io::Result<()> {
+    use actix_files::Files;
+    use actix_web::*;
+    use leptos::*;
+    use leptos_actix::{generate_route_list, LeptosRoutes};
+    use {{crate_name}}::app::*;
+
+    simple_logger::init_with_level(log::Level::Info).expect("couldn't initialize logging");
+
+    // the leptos options are read from the LEPTOS_* env vars set by cargo-leptos
+    let conf = get_configuration(None).await.unwrap();
+    let addr = conf.leptos_options.site_address;
+    let routes = generate_route_list(|cx| view! { cx, <App/> });
+
+    log::info!("listening on http://{}", &addr);
+    HttpServer::new(move || {
+        let leptos_options = &conf.leptos_options;
+        let site_root = &leptos_options.site_root;
+
+        App::new()
+            .leptos_routes(
+                leptos_options.to_owned(),
+                routes.to_owned(),
+                |cx| view! { cx, <App/> },
+            )
+            .service(Files::new("/", site_root))
+            .wrap(middleware::Compress::default())
+    })
+    .bind(&addr)?
+    .run()
+    .await
+}
+
+#[cfg(not(feature = "ssr"))]
+pub fn main() {
+    // no client-side main function, the wasm is started by the hydrate function of the lib
+}
@@ -0,0 +1,35 @@
+#[cfg(feature = "ssr")]
+#[tokio::main]
+async fn main() {
+    use axum::{extract::Extension, Router};
+    use leptos::*;
+    use leptos_axum::{generate_route_list, LeptosRoutes};
+    use std::sync::Arc;
+    use tower_http::services::ServeDir;
+    use {{crate_name}}::app::*;
+
+    simple_logger::init_with_level(log::Level::Info).expect("couldn't initialize logging");
+
+    // the leptos options are read from the LEPTOS_* env vars set by cargo-leptos
+    let conf = get_configuration(None).await.unwrap();
+    let leptos_options = conf.leptos_options;
+    let addr = leptos_options.site_address;
+    let site_root = leptos_options.site_root.clone();
+    let routes = generate_route_list(|cx| view! { cx, <App/> }).await;
+
+    let app = Router::new()
+        .leptos_routes(leptos_options.clone(), routes, |cx| view! { cx, <App/> })
+        .fallback_service(ServeDir::new(site_root))
+        .layer(Extension(Arc::new(leptos_options)));
+
+    log::info!("listening on http://{}", &addr);
+    axum::Server::bind(&addr)
+        .serve(app.into_make_service())
+        .await
+        .unwrap();
+}
+
+#[cfg(not(feature = "ssr"))]
+pub fn main() {
+    // no client-side main function, the wasm is started by the hydrate function of the lib
+}
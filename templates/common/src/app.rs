@@ -0,0 +1,17 @@
+use leptos::*;
+use leptos_meta::*;
+
+#[component]
+pub fn App(cx: Scope) -> impl IntoView {
+    provide_meta_context(cx);
+    let (count, set_count) = create_signal(cx, 0);
+    view! {
+        cx,
+        <Stylesheet id="leptos" href="/pkg/{{project-name}}.css"/>
+        <Title text="Welcome to Leptos"/>
+        <h1>"Welcome to Leptos!"</h1>
+        <button on:click=move |_| set_count.update(|count| *count += 1)>
+            "Click Me: " {count}
+        </button>
+    }
+}